@@ -1,1224 +1,140 @@
-// the whole file taken from pikkr
-use x86intrin::{m256i, mm256_setr_epi8};
+#[cfg(target_arch = "x86")]
+use std::arch::x86::*;
+#[cfg(target_arch = "x86_64")]
+use std::arch::x86_64::*;
 
-#[inline]
-pub fn mm256i(i: i8) -> m256i {
-    mm256_setr_epi8(
-        i,
-        i,
-        i,
-        i,
-        i,
-        i,
-        i,
-        i,
-        i,
-        i,
-        i,
-        i,
-        i,
-        i,
-        i,
-        i,
-        i,
-        i,
-        i,
-        i,
-        i,
-        i,
-        i,
-        i,
-        i,
-        i,
-        i,
-        i,
-        i,
-        i,
-        i,
-        i,
-    )
-}
-
-/// Create a value of `m256i` from the slice of bytes.
+/// Build the bitmaps of the field separators, record terminators and optionally quotes in `buf`
+/// using AVX2 instructions.
 ///
 /// # Safety
-/// This function guarantees the safety only if the length of `s` is greater than or equal to `i + 32`.
-#[inline(always)]
-pub unsafe fn u8_to_m256i(s: &[u8], i: usize) -> m256i {
-    mm256_setr_epi8(
-        *s.as_ptr().offset(i as isize + 0) as i8,
-        *s.as_ptr().offset(i as isize + 1) as i8,
-        *s.as_ptr().offset(i as isize + 2) as i8,
-        *s.as_ptr().offset(i as isize + 3) as i8,
-        *s.as_ptr().offset(i as isize + 4) as i8,
-        *s.as_ptr().offset(i as isize + 5) as i8,
-        *s.as_ptr().offset(i as isize + 6) as i8,
-        *s.as_ptr().offset(i as isize + 7) as i8,
-        *s.as_ptr().offset(i as isize + 8) as i8,
-        *s.as_ptr().offset(i as isize + 9) as i8,
-        *s.as_ptr().offset(i as isize + 10) as i8,
-        *s.as_ptr().offset(i as isize + 11) as i8,
-        *s.as_ptr().offset(i as isize + 12) as i8,
-        *s.as_ptr().offset(i as isize + 13) as i8,
-        *s.as_ptr().offset(i as isize + 14) as i8,
-        *s.as_ptr().offset(i as isize + 15) as i8,
-        *s.as_ptr().offset(i as isize + 16) as i8,
-        *s.as_ptr().offset(i as isize + 17) as i8,
-        *s.as_ptr().offset(i as isize + 18) as i8,
-        *s.as_ptr().offset(i as isize + 19) as i8,
-        *s.as_ptr().offset(i as isize + 20) as i8,
-        *s.as_ptr().offset(i as isize + 21) as i8,
-        *s.as_ptr().offset(i as isize + 22) as i8,
-        *s.as_ptr().offset(i as isize + 23) as i8,
-        *s.as_ptr().offset(i as isize + 24) as i8,
-        *s.as_ptr().offset(i as isize + 25) as i8,
-        *s.as_ptr().offset(i as isize + 26) as i8,
-        *s.as_ptr().offset(i as isize + 27) as i8,
-        *s.as_ptr().offset(i as isize + 28) as i8,
-        *s.as_ptr().offset(i as isize + 29) as i8,
-        *s.as_ptr().offset(i as isize + 30) as i8,
-        *s.as_ptr().offset(i as isize + 31) as i8,
-    )
+/// The caller must ensure that the CPU supports AVX2.
+#[target_feature(enable = "avx2")]
+pub unsafe fn build_structural_character_bitmap(
+    buf: &[u8],
+    b_fs: &mut Vec<u64>,
+    b_rt: &mut Vec<u64>,
+    b_qt: &mut Vec<u64>,
+    fs: u8,
+    rt: u8,
+    qt: Option<u8>,
+) {
+    let m_fs = _mm256_set1_epi8(fs as i8);
+    let m_rt = _mm256_set1_epi8(rt as i8);
+    let m_qt = _mm256_set1_epi8(qt.unwrap_or(0) as i8);
+    let b_len = buf.len();
+    let mut i = 0;
+
+    while i + 64 <= b_len {
+        let p = buf.as_ptr().add(i);
+        let m1 = _mm256_loadu_si256(p as *const __m256i);
+        let m2 = _mm256_loadu_si256(p.add(32) as *const __m256i);
+
+        b_fs.push(mbitmap(m1, m2, m_fs));
+        b_rt.push(mbitmap(m1, m2, m_rt));
+        if qt.is_some() {
+            b_qt.push(mbitmap(m1, m2, m_qt));
+        }
+
+        i += 64;
+    }
+
+    if i < b_len {
+        let rest = b_len - i;
+        let mut block = [0u8; 64];
+        block[..rest].copy_from_slice(&buf[i..]);
+        let m1 = _mm256_loadu_si256(block.as_ptr() as *const __m256i);
+        let m2 = _mm256_loadu_si256(block.as_ptr().add(32) as *const __m256i);
+        let valid = (1u64 << rest) - 1;
+
+        b_fs.push(mbitmap(m1, m2, m_fs) & valid);
+        b_rt.push(mbitmap(m1, m2, m_rt) & valid);
+        if qt.is_some() {
+            b_qt.push(mbitmap(m1, m2, m_qt) & valid);
+        }
+    }
 }
 
 #[inline]
-pub unsafe fn u8_to_m256i_rest(s: &[u8], i: usize) -> m256i {
-    match s.len() - i {
-        31 => mm256_setr_epi8(
-            *s.as_ptr().offset(i as isize + 0) as i8,
-            *s.as_ptr().offset(i as isize + 1) as i8,
-            *s.as_ptr().offset(i as isize + 2) as i8,
-            *s.as_ptr().offset(i as isize + 3) as i8,
-            *s.as_ptr().offset(i as isize + 4) as i8,
-            *s.as_ptr().offset(i as isize + 5) as i8,
-            *s.as_ptr().offset(i as isize + 6) as i8,
-            *s.as_ptr().offset(i as isize + 7) as i8,
-            *s.as_ptr().offset(i as isize + 8) as i8,
-            *s.as_ptr().offset(i as isize + 9) as i8,
-            *s.as_ptr().offset(i as isize + 10) as i8,
-            *s.as_ptr().offset(i as isize + 11) as i8,
-            *s.as_ptr().offset(i as isize + 12) as i8,
-            *s.as_ptr().offset(i as isize + 13) as i8,
-            *s.as_ptr().offset(i as isize + 14) as i8,
-            *s.as_ptr().offset(i as isize + 15) as i8,
-            *s.as_ptr().offset(i as isize + 16) as i8,
-            *s.as_ptr().offset(i as isize + 17) as i8,
-            *s.as_ptr().offset(i as isize + 18) as i8,
-            *s.as_ptr().offset(i as isize + 19) as i8,
-            *s.as_ptr().offset(i as isize + 20) as i8,
-            *s.as_ptr().offset(i as isize + 21) as i8,
-            *s.as_ptr().offset(i as isize + 22) as i8,
-            *s.as_ptr().offset(i as isize + 23) as i8,
-            *s.as_ptr().offset(i as isize + 24) as i8,
-            *s.as_ptr().offset(i as isize + 25) as i8,
-            *s.as_ptr().offset(i as isize + 26) as i8,
-            *s.as_ptr().offset(i as isize + 27) as i8,
-            *s.as_ptr().offset(i as isize + 28) as i8,
-            *s.as_ptr().offset(i as isize + 29) as i8,
-            *s.as_ptr().offset(i as isize + 30) as i8,
-            0,
-        ),
-        30 => mm256_setr_epi8(
-            *s.as_ptr().offset(i as isize + 0) as i8,
-            *s.as_ptr().offset(i as isize + 1) as i8,
-            *s.as_ptr().offset(i as isize + 2) as i8,
-            *s.as_ptr().offset(i as isize + 3) as i8,
-            *s.as_ptr().offset(i as isize + 4) as i8,
-            *s.as_ptr().offset(i as isize + 5) as i8,
-            *s.as_ptr().offset(i as isize + 6) as i8,
-            *s.as_ptr().offset(i as isize + 7) as i8,
-            *s.as_ptr().offset(i as isize + 8) as i8,
-            *s.as_ptr().offset(i as isize + 9) as i8,
-            *s.as_ptr().offset(i as isize + 10) as i8,
-            *s.as_ptr().offset(i as isize + 11) as i8,
-            *s.as_ptr().offset(i as isize + 12) as i8,
-            *s.as_ptr().offset(i as isize + 13) as i8,
-            *s.as_ptr().offset(i as isize + 14) as i8,
-            *s.as_ptr().offset(i as isize + 15) as i8,
-            *s.as_ptr().offset(i as isize + 16) as i8,
-            *s.as_ptr().offset(i as isize + 17) as i8,
-            *s.as_ptr().offset(i as isize + 18) as i8,
-            *s.as_ptr().offset(i as isize + 19) as i8,
-            *s.as_ptr().offset(i as isize + 20) as i8,
-            *s.as_ptr().offset(i as isize + 21) as i8,
-            *s.as_ptr().offset(i as isize + 22) as i8,
-            *s.as_ptr().offset(i as isize + 23) as i8,
-            *s.as_ptr().offset(i as isize + 24) as i8,
-            *s.as_ptr().offset(i as isize + 25) as i8,
-            *s.as_ptr().offset(i as isize + 26) as i8,
-            *s.as_ptr().offset(i as isize + 27) as i8,
-            *s.as_ptr().offset(i as isize + 28) as i8,
-            *s.as_ptr().offset(i as isize + 29) as i8,
-            0,
-            0,
-        ),
-        29 => mm256_setr_epi8(
-            *s.as_ptr().offset(i as isize + 0) as i8,
-            *s.as_ptr().offset(i as isize + 1) as i8,
-            *s.as_ptr().offset(i as isize + 2) as i8,
-            *s.as_ptr().offset(i as isize + 3) as i8,
-            *s.as_ptr().offset(i as isize + 4) as i8,
-            *s.as_ptr().offset(i as isize + 5) as i8,
-            *s.as_ptr().offset(i as isize + 6) as i8,
-            *s.as_ptr().offset(i as isize + 7) as i8,
-            *s.as_ptr().offset(i as isize + 8) as i8,
-            *s.as_ptr().offset(i as isize + 9) as i8,
-            *s.as_ptr().offset(i as isize + 10) as i8,
-            *s.as_ptr().offset(i as isize + 11) as i8,
-            *s.as_ptr().offset(i as isize + 12) as i8,
-            *s.as_ptr().offset(i as isize + 13) as i8,
-            *s.as_ptr().offset(i as isize + 14) as i8,
-            *s.as_ptr().offset(i as isize + 15) as i8,
-            *s.as_ptr().offset(i as isize + 16) as i8,
-            *s.as_ptr().offset(i as isize + 17) as i8,
-            *s.as_ptr().offset(i as isize + 18) as i8,
-            *s.as_ptr().offset(i as isize + 19) as i8,
-            *s.as_ptr().offset(i as isize + 20) as i8,
-            *s.as_ptr().offset(i as isize + 21) as i8,
-            *s.as_ptr().offset(i as isize + 22) as i8,
-            *s.as_ptr().offset(i as isize + 23) as i8,
-            *s.as_ptr().offset(i as isize + 24) as i8,
-            *s.as_ptr().offset(i as isize + 25) as i8,
-            *s.as_ptr().offset(i as isize + 26) as i8,
-            *s.as_ptr().offset(i as isize + 27) as i8,
-            *s.as_ptr().offset(i as isize + 28) as i8,
-            0,
-            0,
-            0,
-        ),
-        28 => mm256_setr_epi8(
-            *s.as_ptr().offset(i as isize + 0) as i8,
-            *s.as_ptr().offset(i as isize + 1) as i8,
-            *s.as_ptr().offset(i as isize + 2) as i8,
-            *s.as_ptr().offset(i as isize + 3) as i8,
-            *s.as_ptr().offset(i as isize + 4) as i8,
-            *s.as_ptr().offset(i as isize + 5) as i8,
-            *s.as_ptr().offset(i as isize + 6) as i8,
-            *s.as_ptr().offset(i as isize + 7) as i8,
-            *s.as_ptr().offset(i as isize + 8) as i8,
-            *s.as_ptr().offset(i as isize + 9) as i8,
-            *s.as_ptr().offset(i as isize + 10) as i8,
-            *s.as_ptr().offset(i as isize + 11) as i8,
-            *s.as_ptr().offset(i as isize + 12) as i8,
-            *s.as_ptr().offset(i as isize + 13) as i8,
-            *s.as_ptr().offset(i as isize + 14) as i8,
-            *s.as_ptr().offset(i as isize + 15) as i8,
-            *s.as_ptr().offset(i as isize + 16) as i8,
-            *s.as_ptr().offset(i as isize + 17) as i8,
-            *s.as_ptr().offset(i as isize + 18) as i8,
-            *s.as_ptr().offset(i as isize + 19) as i8,
-            *s.as_ptr().offset(i as isize + 20) as i8,
-            *s.as_ptr().offset(i as isize + 21) as i8,
-            *s.as_ptr().offset(i as isize + 22) as i8,
-            *s.as_ptr().offset(i as isize + 23) as i8,
-            *s.as_ptr().offset(i as isize + 24) as i8,
-            *s.as_ptr().offset(i as isize + 25) as i8,
-            *s.as_ptr().offset(i as isize + 26) as i8,
-            *s.as_ptr().offset(i as isize + 27) as i8,
-            0,
-            0,
-            0,
-            0,
-        ),
-        27 => mm256_setr_epi8(
-            *s.as_ptr().offset(i as isize + 0) as i8,
-            *s.as_ptr().offset(i as isize + 1) as i8,
-            *s.as_ptr().offset(i as isize + 2) as i8,
-            *s.as_ptr().offset(i as isize + 3) as i8,
-            *s.as_ptr().offset(i as isize + 4) as i8,
-            *s.as_ptr().offset(i as isize + 5) as i8,
-            *s.as_ptr().offset(i as isize + 6) as i8,
-            *s.as_ptr().offset(i as isize + 7) as i8,
-            *s.as_ptr().offset(i as isize + 8) as i8,
-            *s.as_ptr().offset(i as isize + 9) as i8,
-            *s.as_ptr().offset(i as isize + 10) as i8,
-            *s.as_ptr().offset(i as isize + 11) as i8,
-            *s.as_ptr().offset(i as isize + 12) as i8,
-            *s.as_ptr().offset(i as isize + 13) as i8,
-            *s.as_ptr().offset(i as isize + 14) as i8,
-            *s.as_ptr().offset(i as isize + 15) as i8,
-            *s.as_ptr().offset(i as isize + 16) as i8,
-            *s.as_ptr().offset(i as isize + 17) as i8,
-            *s.as_ptr().offset(i as isize + 18) as i8,
-            *s.as_ptr().offset(i as isize + 19) as i8,
-            *s.as_ptr().offset(i as isize + 20) as i8,
-            *s.as_ptr().offset(i as isize + 21) as i8,
-            *s.as_ptr().offset(i as isize + 22) as i8,
-            *s.as_ptr().offset(i as isize + 23) as i8,
-            *s.as_ptr().offset(i as isize + 24) as i8,
-            *s.as_ptr().offset(i as isize + 25) as i8,
-            *s.as_ptr().offset(i as isize + 26) as i8,
-            0,
-            0,
-            0,
-            0,
-            0,
-        ),
-        26 => mm256_setr_epi8(
-            *s.as_ptr().offset(i as isize + 0) as i8,
-            *s.as_ptr().offset(i as isize + 1) as i8,
-            *s.as_ptr().offset(i as isize + 2) as i8,
-            *s.as_ptr().offset(i as isize + 3) as i8,
-            *s.as_ptr().offset(i as isize + 4) as i8,
-            *s.as_ptr().offset(i as isize + 5) as i8,
-            *s.as_ptr().offset(i as isize + 6) as i8,
-            *s.as_ptr().offset(i as isize + 7) as i8,
-            *s.as_ptr().offset(i as isize + 8) as i8,
-            *s.as_ptr().offset(i as isize + 9) as i8,
-            *s.as_ptr().offset(i as isize + 10) as i8,
-            *s.as_ptr().offset(i as isize + 11) as i8,
-            *s.as_ptr().offset(i as isize + 12) as i8,
-            *s.as_ptr().offset(i as isize + 13) as i8,
-            *s.as_ptr().offset(i as isize + 14) as i8,
-            *s.as_ptr().offset(i as isize + 15) as i8,
-            *s.as_ptr().offset(i as isize + 16) as i8,
-            *s.as_ptr().offset(i as isize + 17) as i8,
-            *s.as_ptr().offset(i as isize + 18) as i8,
-            *s.as_ptr().offset(i as isize + 19) as i8,
-            *s.as_ptr().offset(i as isize + 20) as i8,
-            *s.as_ptr().offset(i as isize + 21) as i8,
-            *s.as_ptr().offset(i as isize + 22) as i8,
-            *s.as_ptr().offset(i as isize + 23) as i8,
-            *s.as_ptr().offset(i as isize + 24) as i8,
-            *s.as_ptr().offset(i as isize + 25) as i8,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-        ),
-        25 => mm256_setr_epi8(
-            *s.as_ptr().offset(i as isize + 0) as i8,
-            *s.as_ptr().offset(i as isize + 1) as i8,
-            *s.as_ptr().offset(i as isize + 2) as i8,
-            *s.as_ptr().offset(i as isize + 3) as i8,
-            *s.as_ptr().offset(i as isize + 4) as i8,
-            *s.as_ptr().offset(i as isize + 5) as i8,
-            *s.as_ptr().offset(i as isize + 6) as i8,
-            *s.as_ptr().offset(i as isize + 7) as i8,
-            *s.as_ptr().offset(i as isize + 8) as i8,
-            *s.as_ptr().offset(i as isize + 9) as i8,
-            *s.as_ptr().offset(i as isize + 10) as i8,
-            *s.as_ptr().offset(i as isize + 11) as i8,
-            *s.as_ptr().offset(i as isize + 12) as i8,
-            *s.as_ptr().offset(i as isize + 13) as i8,
-            *s.as_ptr().offset(i as isize + 14) as i8,
-            *s.as_ptr().offset(i as isize + 15) as i8,
-            *s.as_ptr().offset(i as isize + 16) as i8,
-            *s.as_ptr().offset(i as isize + 17) as i8,
-            *s.as_ptr().offset(i as isize + 18) as i8,
-            *s.as_ptr().offset(i as isize + 19) as i8,
-            *s.as_ptr().offset(i as isize + 20) as i8,
-            *s.as_ptr().offset(i as isize + 21) as i8,
-            *s.as_ptr().offset(i as isize + 22) as i8,
-            *s.as_ptr().offset(i as isize + 23) as i8,
-            *s.as_ptr().offset(i as isize + 24) as i8,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-        ),
-        24 => mm256_setr_epi8(
-            *s.as_ptr().offset(i as isize + 0) as i8,
-            *s.as_ptr().offset(i as isize + 1) as i8,
-            *s.as_ptr().offset(i as isize + 2) as i8,
-            *s.as_ptr().offset(i as isize + 3) as i8,
-            *s.as_ptr().offset(i as isize + 4) as i8,
-            *s.as_ptr().offset(i as isize + 5) as i8,
-            *s.as_ptr().offset(i as isize + 6) as i8,
-            *s.as_ptr().offset(i as isize + 7) as i8,
-            *s.as_ptr().offset(i as isize + 8) as i8,
-            *s.as_ptr().offset(i as isize + 9) as i8,
-            *s.as_ptr().offset(i as isize + 10) as i8,
-            *s.as_ptr().offset(i as isize + 11) as i8,
-            *s.as_ptr().offset(i as isize + 12) as i8,
-            *s.as_ptr().offset(i as isize + 13) as i8,
-            *s.as_ptr().offset(i as isize + 14) as i8,
-            *s.as_ptr().offset(i as isize + 15) as i8,
-            *s.as_ptr().offset(i as isize + 16) as i8,
-            *s.as_ptr().offset(i as isize + 17) as i8,
-            *s.as_ptr().offset(i as isize + 18) as i8,
-            *s.as_ptr().offset(i as isize + 19) as i8,
-            *s.as_ptr().offset(i as isize + 20) as i8,
-            *s.as_ptr().offset(i as isize + 21) as i8,
-            *s.as_ptr().offset(i as isize + 22) as i8,
-            *s.as_ptr().offset(i as isize + 23) as i8,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-        ),
-        23 => mm256_setr_epi8(
-            *s.as_ptr().offset(i as isize + 0) as i8,
-            *s.as_ptr().offset(i as isize + 1) as i8,
-            *s.as_ptr().offset(i as isize + 2) as i8,
-            *s.as_ptr().offset(i as isize + 3) as i8,
-            *s.as_ptr().offset(i as isize + 4) as i8,
-            *s.as_ptr().offset(i as isize + 5) as i8,
-            *s.as_ptr().offset(i as isize + 6) as i8,
-            *s.as_ptr().offset(i as isize + 7) as i8,
-            *s.as_ptr().offset(i as isize + 8) as i8,
-            *s.as_ptr().offset(i as isize + 9) as i8,
-            *s.as_ptr().offset(i as isize + 10) as i8,
-            *s.as_ptr().offset(i as isize + 11) as i8,
-            *s.as_ptr().offset(i as isize + 12) as i8,
-            *s.as_ptr().offset(i as isize + 13) as i8,
-            *s.as_ptr().offset(i as isize + 14) as i8,
-            *s.as_ptr().offset(i as isize + 15) as i8,
-            *s.as_ptr().offset(i as isize + 16) as i8,
-            *s.as_ptr().offset(i as isize + 17) as i8,
-            *s.as_ptr().offset(i as isize + 18) as i8,
-            *s.as_ptr().offset(i as isize + 19) as i8,
-            *s.as_ptr().offset(i as isize + 20) as i8,
-            *s.as_ptr().offset(i as isize + 21) as i8,
-            *s.as_ptr().offset(i as isize + 22) as i8,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-        ),
-        22 => mm256_setr_epi8(
-            *s.as_ptr().offset(i as isize + 0) as i8,
-            *s.as_ptr().offset(i as isize + 1) as i8,
-            *s.as_ptr().offset(i as isize + 2) as i8,
-            *s.as_ptr().offset(i as isize + 3) as i8,
-            *s.as_ptr().offset(i as isize + 4) as i8,
-            *s.as_ptr().offset(i as isize + 5) as i8,
-            *s.as_ptr().offset(i as isize + 6) as i8,
-            *s.as_ptr().offset(i as isize + 7) as i8,
-            *s.as_ptr().offset(i as isize + 8) as i8,
-            *s.as_ptr().offset(i as isize + 9) as i8,
-            *s.as_ptr().offset(i as isize + 10) as i8,
-            *s.as_ptr().offset(i as isize + 11) as i8,
-            *s.as_ptr().offset(i as isize + 12) as i8,
-            *s.as_ptr().offset(i as isize + 13) as i8,
-            *s.as_ptr().offset(i as isize + 14) as i8,
-            *s.as_ptr().offset(i as isize + 15) as i8,
-            *s.as_ptr().offset(i as isize + 16) as i8,
-            *s.as_ptr().offset(i as isize + 17) as i8,
-            *s.as_ptr().offset(i as isize + 18) as i8,
-            *s.as_ptr().offset(i as isize + 19) as i8,
-            *s.as_ptr().offset(i as isize + 20) as i8,
-            *s.as_ptr().offset(i as isize + 21) as i8,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-        ),
-        21 => mm256_setr_epi8(
-            *s.as_ptr().offset(i as isize + 0) as i8,
-            *s.as_ptr().offset(i as isize + 1) as i8,
-            *s.as_ptr().offset(i as isize + 2) as i8,
-            *s.as_ptr().offset(i as isize + 3) as i8,
-            *s.as_ptr().offset(i as isize + 4) as i8,
-            *s.as_ptr().offset(i as isize + 5) as i8,
-            *s.as_ptr().offset(i as isize + 6) as i8,
-            *s.as_ptr().offset(i as isize + 7) as i8,
-            *s.as_ptr().offset(i as isize + 8) as i8,
-            *s.as_ptr().offset(i as isize + 9) as i8,
-            *s.as_ptr().offset(i as isize + 10) as i8,
-            *s.as_ptr().offset(i as isize + 11) as i8,
-            *s.as_ptr().offset(i as isize + 12) as i8,
-            *s.as_ptr().offset(i as isize + 13) as i8,
-            *s.as_ptr().offset(i as isize + 14) as i8,
-            *s.as_ptr().offset(i as isize + 15) as i8,
-            *s.as_ptr().offset(i as isize + 16) as i8,
-            *s.as_ptr().offset(i as isize + 17) as i8,
-            *s.as_ptr().offset(i as isize + 18) as i8,
-            *s.as_ptr().offset(i as isize + 19) as i8,
-            *s.as_ptr().offset(i as isize + 20) as i8,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-        ),
-        20 => mm256_setr_epi8(
-            *s.as_ptr().offset(i as isize + 0) as i8,
-            *s.as_ptr().offset(i as isize + 1) as i8,
-            *s.as_ptr().offset(i as isize + 2) as i8,
-            *s.as_ptr().offset(i as isize + 3) as i8,
-            *s.as_ptr().offset(i as isize + 4) as i8,
-            *s.as_ptr().offset(i as isize + 5) as i8,
-            *s.as_ptr().offset(i as isize + 6) as i8,
-            *s.as_ptr().offset(i as isize + 7) as i8,
-            *s.as_ptr().offset(i as isize + 8) as i8,
-            *s.as_ptr().offset(i as isize + 9) as i8,
-            *s.as_ptr().offset(i as isize + 10) as i8,
-            *s.as_ptr().offset(i as isize + 11) as i8,
-            *s.as_ptr().offset(i as isize + 12) as i8,
-            *s.as_ptr().offset(i as isize + 13) as i8,
-            *s.as_ptr().offset(i as isize + 14) as i8,
-            *s.as_ptr().offset(i as isize + 15) as i8,
-            *s.as_ptr().offset(i as isize + 16) as i8,
-            *s.as_ptr().offset(i as isize + 17) as i8,
-            *s.as_ptr().offset(i as isize + 18) as i8,
-            *s.as_ptr().offset(i as isize + 19) as i8,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-        ),
-        19 => mm256_setr_epi8(
-            *s.as_ptr().offset(i as isize + 0) as i8,
-            *s.as_ptr().offset(i as isize + 1) as i8,
-            *s.as_ptr().offset(i as isize + 2) as i8,
-            *s.as_ptr().offset(i as isize + 3) as i8,
-            *s.as_ptr().offset(i as isize + 4) as i8,
-            *s.as_ptr().offset(i as isize + 5) as i8,
-            *s.as_ptr().offset(i as isize + 6) as i8,
-            *s.as_ptr().offset(i as isize + 7) as i8,
-            *s.as_ptr().offset(i as isize + 8) as i8,
-            *s.as_ptr().offset(i as isize + 9) as i8,
-            *s.as_ptr().offset(i as isize + 10) as i8,
-            *s.as_ptr().offset(i as isize + 11) as i8,
-            *s.as_ptr().offset(i as isize + 12) as i8,
-            *s.as_ptr().offset(i as isize + 13) as i8,
-            *s.as_ptr().offset(i as isize + 14) as i8,
-            *s.as_ptr().offset(i as isize + 15) as i8,
-            *s.as_ptr().offset(i as isize + 16) as i8,
-            *s.as_ptr().offset(i as isize + 17) as i8,
-            *s.as_ptr().offset(i as isize + 18) as i8,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-        ),
-        18 => mm256_setr_epi8(
-            *s.as_ptr().offset(i as isize + 0) as i8,
-            *s.as_ptr().offset(i as isize + 1) as i8,
-            *s.as_ptr().offset(i as isize + 2) as i8,
-            *s.as_ptr().offset(i as isize + 3) as i8,
-            *s.as_ptr().offset(i as isize + 4) as i8,
-            *s.as_ptr().offset(i as isize + 5) as i8,
-            *s.as_ptr().offset(i as isize + 6) as i8,
-            *s.as_ptr().offset(i as isize + 7) as i8,
-            *s.as_ptr().offset(i as isize + 8) as i8,
-            *s.as_ptr().offset(i as isize + 9) as i8,
-            *s.as_ptr().offset(i as isize + 10) as i8,
-            *s.as_ptr().offset(i as isize + 11) as i8,
-            *s.as_ptr().offset(i as isize + 12) as i8,
-            *s.as_ptr().offset(i as isize + 13) as i8,
-            *s.as_ptr().offset(i as isize + 14) as i8,
-            *s.as_ptr().offset(i as isize + 15) as i8,
-            *s.as_ptr().offset(i as isize + 16) as i8,
-            *s.as_ptr().offset(i as isize + 17) as i8,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-        ),
-        17 => mm256_setr_epi8(
-            *s.as_ptr().offset(i as isize + 0) as i8,
-            *s.as_ptr().offset(i as isize + 1) as i8,
-            *s.as_ptr().offset(i as isize + 2) as i8,
-            *s.as_ptr().offset(i as isize + 3) as i8,
-            *s.as_ptr().offset(i as isize + 4) as i8,
-            *s.as_ptr().offset(i as isize + 5) as i8,
-            *s.as_ptr().offset(i as isize + 6) as i8,
-            *s.as_ptr().offset(i as isize + 7) as i8,
-            *s.as_ptr().offset(i as isize + 8) as i8,
-            *s.as_ptr().offset(i as isize + 9) as i8,
-            *s.as_ptr().offset(i as isize + 10) as i8,
-            *s.as_ptr().offset(i as isize + 11) as i8,
-            *s.as_ptr().offset(i as isize + 12) as i8,
-            *s.as_ptr().offset(i as isize + 13) as i8,
-            *s.as_ptr().offset(i as isize + 14) as i8,
-            *s.as_ptr().offset(i as isize + 15) as i8,
-            *s.as_ptr().offset(i as isize + 16) as i8,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-        ),
-        16 => mm256_setr_epi8(
-            *s.as_ptr().offset(i as isize + 0) as i8,
-            *s.as_ptr().offset(i as isize + 1) as i8,
-            *s.as_ptr().offset(i as isize + 2) as i8,
-            *s.as_ptr().offset(i as isize + 3) as i8,
-            *s.as_ptr().offset(i as isize + 4) as i8,
-            *s.as_ptr().offset(i as isize + 5) as i8,
-            *s.as_ptr().offset(i as isize + 6) as i8,
-            *s.as_ptr().offset(i as isize + 7) as i8,
-            *s.as_ptr().offset(i as isize + 8) as i8,
-            *s.as_ptr().offset(i as isize + 9) as i8,
-            *s.as_ptr().offset(i as isize + 10) as i8,
-            *s.as_ptr().offset(i as isize + 11) as i8,
-            *s.as_ptr().offset(i as isize + 12) as i8,
-            *s.as_ptr().offset(i as isize + 13) as i8,
-            *s.as_ptr().offset(i as isize + 14) as i8,
-            *s.as_ptr().offset(i as isize + 15) as i8,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-        ),
-        15 => mm256_setr_epi8(
-            *s.as_ptr().offset(i as isize + 0) as i8,
-            *s.as_ptr().offset(i as isize + 1) as i8,
-            *s.as_ptr().offset(i as isize + 2) as i8,
-            *s.as_ptr().offset(i as isize + 3) as i8,
-            *s.as_ptr().offset(i as isize + 4) as i8,
-            *s.as_ptr().offset(i as isize + 5) as i8,
-            *s.as_ptr().offset(i as isize + 6) as i8,
-            *s.as_ptr().offset(i as isize + 7) as i8,
-            *s.as_ptr().offset(i as isize + 8) as i8,
-            *s.as_ptr().offset(i as isize + 9) as i8,
-            *s.as_ptr().offset(i as isize + 10) as i8,
-            *s.as_ptr().offset(i as isize + 11) as i8,
-            *s.as_ptr().offset(i as isize + 12) as i8,
-            *s.as_ptr().offset(i as isize + 13) as i8,
-            *s.as_ptr().offset(i as isize + 14) as i8,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-        ),
-        14 => mm256_setr_epi8(
-            *s.as_ptr().offset(i as isize + 0) as i8,
-            *s.as_ptr().offset(i as isize + 1) as i8,
-            *s.as_ptr().offset(i as isize + 2) as i8,
-            *s.as_ptr().offset(i as isize + 3) as i8,
-            *s.as_ptr().offset(i as isize + 4) as i8,
-            *s.as_ptr().offset(i as isize + 5) as i8,
-            *s.as_ptr().offset(i as isize + 6) as i8,
-            *s.as_ptr().offset(i as isize + 7) as i8,
-            *s.as_ptr().offset(i as isize + 8) as i8,
-            *s.as_ptr().offset(i as isize + 9) as i8,
-            *s.as_ptr().offset(i as isize + 10) as i8,
-            *s.as_ptr().offset(i as isize + 11) as i8,
-            *s.as_ptr().offset(i as isize + 12) as i8,
-            *s.as_ptr().offset(i as isize + 13) as i8,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-        ),
-        13 => mm256_setr_epi8(
-            *s.as_ptr().offset(i as isize + 0) as i8,
-            *s.as_ptr().offset(i as isize + 1) as i8,
-            *s.as_ptr().offset(i as isize + 2) as i8,
-            *s.as_ptr().offset(i as isize + 3) as i8,
-            *s.as_ptr().offset(i as isize + 4) as i8,
-            *s.as_ptr().offset(i as isize + 5) as i8,
-            *s.as_ptr().offset(i as isize + 6) as i8,
-            *s.as_ptr().offset(i as isize + 7) as i8,
-            *s.as_ptr().offset(i as isize + 8) as i8,
-            *s.as_ptr().offset(i as isize + 9) as i8,
-            *s.as_ptr().offset(i as isize + 10) as i8,
-            *s.as_ptr().offset(i as isize + 11) as i8,
-            *s.as_ptr().offset(i as isize + 12) as i8,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-        ),
-        12 => mm256_setr_epi8(
-            *s.as_ptr().offset(i as isize + 0) as i8,
-            *s.as_ptr().offset(i as isize + 1) as i8,
-            *s.as_ptr().offset(i as isize + 2) as i8,
-            *s.as_ptr().offset(i as isize + 3) as i8,
-            *s.as_ptr().offset(i as isize + 4) as i8,
-            *s.as_ptr().offset(i as isize + 5) as i8,
-            *s.as_ptr().offset(i as isize + 6) as i8,
-            *s.as_ptr().offset(i as isize + 7) as i8,
-            *s.as_ptr().offset(i as isize + 8) as i8,
-            *s.as_ptr().offset(i as isize + 9) as i8,
-            *s.as_ptr().offset(i as isize + 10) as i8,
-            *s.as_ptr().offset(i as isize + 11) as i8,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-        ),
-        11 => mm256_setr_epi8(
-            *s.as_ptr().offset(i as isize + 0) as i8,
-            *s.as_ptr().offset(i as isize + 1) as i8,
-            *s.as_ptr().offset(i as isize + 2) as i8,
-            *s.as_ptr().offset(i as isize + 3) as i8,
-            *s.as_ptr().offset(i as isize + 4) as i8,
-            *s.as_ptr().offset(i as isize + 5) as i8,
-            *s.as_ptr().offset(i as isize + 6) as i8,
-            *s.as_ptr().offset(i as isize + 7) as i8,
-            *s.as_ptr().offset(i as isize + 8) as i8,
-            *s.as_ptr().offset(i as isize + 9) as i8,
-            *s.as_ptr().offset(i as isize + 10) as i8,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-        ),
-        10 => mm256_setr_epi8(
-            *s.as_ptr().offset(i as isize + 0) as i8,
-            *s.as_ptr().offset(i as isize + 1) as i8,
-            *s.as_ptr().offset(i as isize + 2) as i8,
-            *s.as_ptr().offset(i as isize + 3) as i8,
-            *s.as_ptr().offset(i as isize + 4) as i8,
-            *s.as_ptr().offset(i as isize + 5) as i8,
-            *s.as_ptr().offset(i as isize + 6) as i8,
-            *s.as_ptr().offset(i as isize + 7) as i8,
-            *s.as_ptr().offset(i as isize + 8) as i8,
-            *s.as_ptr().offset(i as isize + 9) as i8,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-        ),
-        9 => mm256_setr_epi8(
-            *s.as_ptr().offset(i as isize + 0) as i8,
-            *s.as_ptr().offset(i as isize + 1) as i8,
-            *s.as_ptr().offset(i as isize + 2) as i8,
-            *s.as_ptr().offset(i as isize + 3) as i8,
-            *s.as_ptr().offset(i as isize + 4) as i8,
-            *s.as_ptr().offset(i as isize + 5) as i8,
-            *s.as_ptr().offset(i as isize + 6) as i8,
-            *s.as_ptr().offset(i as isize + 7) as i8,
-            *s.as_ptr().offset(i as isize + 8) as i8,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-        ),
-        8 => mm256_setr_epi8(
-            *s.as_ptr().offset(i as isize + 0) as i8,
-            *s.as_ptr().offset(i as isize + 1) as i8,
-            *s.as_ptr().offset(i as isize + 2) as i8,
-            *s.as_ptr().offset(i as isize + 3) as i8,
-            *s.as_ptr().offset(i as isize + 4) as i8,
-            *s.as_ptr().offset(i as isize + 5) as i8,
-            *s.as_ptr().offset(i as isize + 6) as i8,
-            *s.as_ptr().offset(i as isize + 7) as i8,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-        ),
-        7 => mm256_setr_epi8(
-            *s.as_ptr().offset(i as isize + 0) as i8,
-            *s.as_ptr().offset(i as isize + 1) as i8,
-            *s.as_ptr().offset(i as isize + 2) as i8,
-            *s.as_ptr().offset(i as isize + 3) as i8,
-            *s.as_ptr().offset(i as isize + 4) as i8,
-            *s.as_ptr().offset(i as isize + 5) as i8,
-            *s.as_ptr().offset(i as isize + 6) as i8,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-        ),
-        6 => mm256_setr_epi8(
-            *s.as_ptr().offset(i as isize + 0) as i8,
-            *s.as_ptr().offset(i as isize + 1) as i8,
-            *s.as_ptr().offset(i as isize + 2) as i8,
-            *s.as_ptr().offset(i as isize + 3) as i8,
-            *s.as_ptr().offset(i as isize + 4) as i8,
-            *s.as_ptr().offset(i as isize + 5) as i8,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-        ),
-        5 => mm256_setr_epi8(
-            *s.as_ptr().offset(i as isize + 0) as i8,
-            *s.as_ptr().offset(i as isize + 1) as i8,
-            *s.as_ptr().offset(i as isize + 2) as i8,
-            *s.as_ptr().offset(i as isize + 3) as i8,
-            *s.as_ptr().offset(i as isize + 4) as i8,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-        ),
-        4 => mm256_setr_epi8(
-            *s.as_ptr().offset(i as isize + 0) as i8,
-            *s.as_ptr().offset(i as isize + 1) as i8,
-            *s.as_ptr().offset(i as isize + 2) as i8,
-            *s.as_ptr().offset(i as isize + 3) as i8,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-        ),
-        3 => mm256_setr_epi8(
-            *s.as_ptr().offset(i as isize + 0) as i8,
-            *s.as_ptr().offset(i as isize + 1) as i8,
-            *s.as_ptr().offset(i as isize + 2) as i8,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-        ),
-        2 => mm256_setr_epi8(
-            *s.as_ptr().offset(i as isize + 0) as i8,
-            *s.as_ptr().offset(i as isize + 1) as i8,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-        ),
-        1 => mm256_setr_epi8(
-            *s.as_ptr().offset(i as isize + 0) as i8,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-        ),
-        _ => mm256_setr_epi8(
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-        ),
-    }
+#[target_feature(enable = "avx2")]
+unsafe fn mbitmap(x1: __m256i, x2: __m256i, y: __m256i) -> u64 {
+    let i1 = _mm256_movemask_epi8(_mm256_cmpeq_epi8(x1, y));
+    let i2 = _mm256_movemask_epi8(_mm256_cmpeq_epi8(x2, y));
+    u64::from(i1 as u32) | (u64::from(i2 as u32) << 32)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    struct TestCase {
+        input: Vec<u8>,
+        fs: u8,
+        qt: Option<u8>,
+        want: (Vec<u64>, Vec<u64>, Vec<u64>),
+    }
+
+    fn test_cases() -> Vec<TestCase> {
+        vec![
+            TestCase {
+                input: vec![],
+                fs: b',',
+                qt: Some(b'"'),
+                want: (vec![], vec![], vec![]),
+            },
+            TestCase {
+                input: b"a,\"b\"\n".to_vec(),
+                fs: b',',
+                qt: Some(b'"'),
+                want: (vec![0x2], vec![0x20], vec![0x14]),
+            },
+            TestCase {
+                input: b"a,b\n".repeat(20),
+                fs: b',',
+                qt: Some(b'"'),
+                want: (
+                    vec![0x2222_2222_2222_2222, 0x2222],
+                    vec![0x8888_8888_8888_8888, 0x8888],
+                    vec![0, 0],
+                ),
+            },
+            TestCase {
+                input: b"a,\"b\"\n".to_vec(),
+                fs: b',',
+                qt: None,
+                want: (vec![0x2], vec![0x20], vec![]),
+            },
+            TestCase {
+                input: vec![0xfe, b'a', 0xfe],
+                fs: 0xfe,
+                qt: None,
+                want: (vec![0b101], vec![0], vec![]),
+            },
+        ]
+    }
+
     #[test]
-    fn test_mm256i() {
-        let test_cases = vec![0, 1, 2, 3];
-        for i in test_cases {
-            let want = mm256_setr_epi8(
-                i,
-                i,
-                i,
-                i,
-                i,
-                i,
-                i,
-                i,
-                i,
-                i,
-                i,
-                i,
-                i,
-                i,
-                i,
-                i,
-                i,
-                i,
-                i,
-                i,
-                i,
-                i,
-                i,
-                i,
-                i,
-                i,
-                i,
-                i,
-                i,
-                i,
-                i,
-                i,
-            );
-            let got = mm256i(i);
-            assert_eq!(want.as_u8x32().as_array(), got.as_u8x32().as_array());
+    fn test_mbitmap() {
+        if !is_x86_feature_detected!("avx2") {
+            return;
+        }
+        let test_cases = vec![(0, 0), (1, 0xffff_ffff), (2, 0xffff_ffff_0000_0000)];
+        for (y, want) in test_cases {
+            let got = unsafe {
+                mbitmap(_mm256_set1_epi8(1), _mm256_set1_epi8(2), _mm256_set1_epi8(y))
+            };
+            assert_eq!(got, want);
+        }
+    }
+
+    #[test]
+    fn test_build_structural_character_bitmap() {
+        if !is_x86_feature_detected!("avx2") {
+            return;
+        }
+        for t in test_cases() {
+            let (mut b_fs, mut b_rt, mut b_qt) = (vec![], vec![], vec![]);
+            unsafe { build_structural_character_bitmap(&t.input, &mut b_fs, &mut b_rt, &mut b_qt, t.fs, b'\n', t.qt); }
+            assert_eq!((b_fs, b_rt, b_qt), t.want);
         }
     }
 }
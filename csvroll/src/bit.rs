@@ -10,7 +10,6 @@ pub fn e(x: u64) -> u64 {
     x & x.wrapping_neg()
 }
 
-#[allow(dead_code)]
 #[inline]
 pub fn s(x: u64) -> u64 {
     x ^ x.saturating_sub(1)
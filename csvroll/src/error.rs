@@ -0,0 +1,263 @@
+use std::error;
+use std::fmt;
+use std::io;
+
+/// The input, where the error occurred.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Input {
+    Left,
+    Right,
+    /// The input number `n` of any number of inputs, numbered from zero.
+    Nth(usize),
+}
+
+impl fmt::Display for Input {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Input::Left => write!(f, "left input"),
+            Input::Right => write!(f, "right input"),
+            Input::Nth(n) => write!(f, "input {}", n + 1),
+        }
+    }
+}
+
+/// The position of a record in the input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Position {
+    /// The number of the record, numbered from one.
+    pub record: u64,
+    /// The line of the beginning of the record, numbered from one.
+    pub line: u64,
+    /// The byte offset of the contents of the first field of the record, i.e. after the opening
+    /// quote, if the field is quoted.
+    pub byte: u64,
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "the record number {} (line {}, byte {})", self.record, self.line, self.byte)
+    }
+}
+
+/// The class of the error.
+#[derive(Debug)]
+pub enum ErrorKind {
+    /// Reading an input or writing the output failed.
+    Io(io::Error),
+    /// The record has the key out of the sort order of the preceding record.
+    Unsorted,
+    /// The record has less fields than the key.
+    ShortRecord,
+    /// The record, or the group of the records with the same key, does not fit into the buffer of
+    /// the maximum `capacity`.
+    BufferOverflow { group: bool, capacity: usize },
+    /// The arguments are not valid, e.g. a key field is not in the header.
+    InvalidArgument(String),
+}
+
+/// The error of reading and joining the inputs.
+///
+/// The error tells the input and the position of the record which caused it, if they are known.
+#[derive(Debug)]
+pub struct Error {
+    kind: ErrorKind,
+    input: Option<Input>,
+    position: Option<Position>,
+}
+
+impl Error {
+    pub fn new(kind: ErrorKind) -> Self {
+        Error {
+            kind,
+            input: None,
+            position: None,
+        }
+    }
+
+    /// Create an error of the record at `position`.
+    pub fn at(kind: ErrorKind, position: Position) -> Self {
+        Self::new(kind).with_position(position)
+    }
+
+    pub fn invalid_argument<S: Into<String>>(msg: S) -> Self {
+        Self::new(ErrorKind::InvalidArgument(msg.into()))
+    }
+
+    /// Set the input of the error, unless it is already known.
+    pub fn with_input(mut self, input: Input) -> Self {
+        self.input = self.input.or(Some(input));
+        self
+    }
+
+    pub fn with_position(mut self, position: Position) -> Self {
+        self.position = Some(position);
+        self
+    }
+
+    #[inline]
+    pub fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+
+    #[inline]
+    pub fn input(&self) -> Option<Input> {
+        self.input
+    }
+
+    #[inline]
+    pub fn position(&self) -> Option<Position> {
+        self.position
+    }
+
+    /// Return the exit code of the process, which is distinct for each class of the error.
+    pub fn exit_code(&self) -> i32 {
+        match self.kind {
+            ErrorKind::InvalidArgument(_) => 2,
+            ErrorKind::Io(_) => 3,
+            ErrorKind::Unsorted => 4,
+            ErrorKind::ShortRecord => 5,
+            ErrorKind::BufferOverflow { .. } => 6,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(input) = self.input {
+            write!(f, "{}: ", input)?;
+        }
+        let record: &dyn fmt::Display = match self.position {
+            Some(ref p) => p,
+            None => &"the record",
+        };
+        match self.kind {
+            ErrorKind::Io(ref e) => write!(f, "{}", e),
+            ErrorKind::Unsorted => {
+                write!(f, "{} has the key out of the sort order of the preceding record", record)
+            }
+            ErrorKind::ShortRecord => write!(f, "{} has less fields than the key", record),
+            ErrorKind::BufferOverflow { group: false, capacity } => {
+                write!(f, "{} does not fit into the buffer of {} bytes", record, capacity)
+            }
+            ErrorKind::BufferOverflow { group: true, capacity } => write!(
+                f,
+                "the group of the records with the same key starting at {} does not fit into the \
+                 buffer of {} bytes",
+                record,
+                capacity,
+            ),
+            ErrorKind::InvalidArgument(ref msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self.kind {
+            ErrorKind::Io(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::new(ErrorKind::Io(e))
+    }
+}
+
+/// Return the exit code of the process for any error, 1 if its class is not known.
+pub fn exit_code(e: &(dyn error::Error + 'static)) -> i32 {
+    if let Some(e) = e.downcast_ref::<Error>() {
+        e.exit_code()
+    } else if e.is::<io::Error>() {
+        // the same as of `ErrorKind::Io`
+        3
+    } else {
+        1
+    }
+}
+
+/// Test if the error is caused by the reader of the output having gone away, e.g. `head`, which
+/// is not a failure of the process.
+pub fn is_broken_pipe(e: &(dyn error::Error + 'static)) -> bool {
+    let io_err = match e.downcast_ref::<Error>() {
+        Some(&Error { kind: ErrorKind::Io(ref e), .. }) => e,
+        Some(_) => return false,
+        None => match e.downcast_ref::<io::Error>() {
+            Some(e) => e,
+            None => return false,
+        },
+    };
+    io_err.kind() == io::ErrorKind::BrokenPipe
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        struct TestCase {
+            err: Error,
+            want: String,
+            code: i32,
+        }
+
+        let pos = Position { record: 3, line: 4, byte: 17 };
+        let test_cases = vec![
+            TestCase {
+                err: Error::at(ErrorKind::Unsorted, pos).with_input(Input::Left),
+                want: "left input: the record number 3 (line 4, byte 17) has the key out of the \
+                       sort order of the preceding record".to_owned(),
+                code: 4,
+            },
+            TestCase {
+                err: Error::at(ErrorKind::ShortRecord, pos).with_input(Input::Nth(2)).with_input(Input::Right),
+                want: "input 3: the record number 3 (line 4, byte 17) has less fields than the key".to_owned(),
+                code: 5,
+            },
+            TestCase {
+                err: Error::new(ErrorKind::BufferOverflow { group: false, capacity: 16 }),
+                want: "the record does not fit into the buffer of 16 bytes".to_owned(),
+                code: 6,
+            },
+            TestCase {
+                err: Error::at(ErrorKind::BufferOverflow { group: true, capacity: 16 }, pos).with_input(Input::Left),
+                want: "left input: the group of the records with the same key starting at the record number 3 \
+                       (line 4, byte 17) does not fit into the buffer of 16 bytes".to_owned(),
+                code: 6,
+            },
+            TestCase {
+                err: Error::invalid_argument("the field 'id' is not in the header").with_input(Input::Right),
+                want: "right input: the field 'id' is not in the header".to_owned(),
+                code: 2,
+            },
+            TestCase {
+                err: io::Error::new(io::ErrorKind::NotFound, "no such file").into(),
+                want: "no such file".to_owned(),
+                code: 3,
+            },
+        ];
+
+        for t in test_cases {
+            assert_eq!(t.err.to_string(), t.want);
+            assert_eq!(t.err.exit_code(), t.code, "{}", t.want);
+            assert_eq!(exit_code(&t.err), t.code, "{}", t.want);
+        }
+
+        assert_eq!(exit_code(&io::Error::new(io::ErrorKind::Other, "broken pipe")), 3);
+        assert_eq!(exit_code(&*Box::<dyn error::Error>::from("other")), 1);
+    }
+
+    #[test]
+    fn test_is_broken_pipe() {
+        let broken_pipe = || io::Error::new(io::ErrorKind::BrokenPipe, "broken pipe");
+        assert!(is_broken_pipe(&broken_pipe()));
+        assert!(is_broken_pipe(&Error::from(broken_pipe()).with_input(Input::Left)));
+        assert!(is_broken_pipe(&*Box::<dyn error::Error>::from(broken_pipe())));
+        assert!(!is_broken_pipe(&io::Error::new(io::ErrorKind::Other, "other")));
+        assert!(!is_broken_pipe(&Error::new(ErrorKind::Unsorted)));
+        assert!(!is_broken_pipe(&*Box::<dyn error::Error>::from("broken pipe")));
+    }
+}
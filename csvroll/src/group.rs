@@ -1,7 +1,7 @@
 use super::parser::{Parser, Index,};
 use std::cmp::Ordering;
 use std::ops::Range;
-use std::error::Error;
+use super::error::{Error, ErrorKind,};
 use std::io;
 
 pub struct Group<R> {
@@ -14,7 +14,7 @@ pub struct Group<R> {
 }
 
 impl<R: io::Read> Group<R> {
-    pub fn init(mut parser: Parser<R>, key_idx: Vec<usize>) -> Result<Self, Box<Error>> {
+    pub fn init(mut parser: Parser<R>, key_idx: Vec<usize>) -> Result<Self, Error> {
         let is_buf_full = parser.parse()?;
         let first_rec: Range<usize>;
         let rec: Range<usize>;
@@ -48,21 +48,32 @@ impl<R: io::Read> Group<R> {
     }
     
     #[inline]
-    pub fn next_group(&mut self) -> Result<Range<usize>, Box<Error>> {
+    pub fn next_group(&mut self) -> Result<Range<usize>, Error> {
         loop {
             {
                 let (buf, struct_idx) = self.parser.output();
                 let fields = struct_idx.fields();
-                for &re in &struct_idx.records()[self.group.end..] {
+                for (i, &re) in struct_idx.records()[self.group.end..].iter().enumerate() {
                     let rec = self.rec.end..re;
-                    match cmp_records(
+                    let ord = match cmp_records(
                         buf,
                         &fields[self.rec.end..re],
                         &fields[self.first_rec.clone()],
-                        &self.key_idx)? {
-
+                        &self.key_idx) {
+                        Ok(ord) => ord,
+                        Err(0) => {
+                            let pos = self.parser.position(self.group.end + i);
+                            return Err(Error::at(ErrorKind::ShortRecord, pos));
+                        }
+                        Err(_) => {
+                            let pos = self.parser.position(self.group.start);
+                            return Err(Error::at(ErrorKind::ShortRecord, pos));
+                        }
+                    };
+                    match ord {
                         Ordering::Less => {
-                            return Err("The records are not sorted in ascending order".into());
+                            let pos = self.parser.position(self.group.end + i);
+                            return Err(Error::at(ErrorKind::Unsorted, pos));
                         }
                         Ordering::Greater => {
                             let g = self.group.clone();
@@ -102,22 +113,23 @@ impl<R: io::Read> Group<R> {
     }
 }
 
+// returns the number of the record, which has less fields than the key, as the error
 #[inline]
 fn cmp_records(
     buf: &[u8],
     rec_0: &[Range<usize>],
     rec_1: &[Range<usize>],
     key_idx: &[usize],
-) -> Result<Ordering, Box<Error>> {
+) -> Result<Ordering, usize> {
 
     for &k in key_idx {
         let f0 = match rec_0.get(k) {
             Some(f) => f.clone(),
-            None => return Err("The first record is shorter than key fields".into()),
+            None => return Err(0),
         };
         let f1 = match rec_1.get(k) {
             Some(f) => f.clone(),
-            None => return Err("The second record is shorter than key fields".into()),
+            None => return Err(1),
         };
         
         match buf[f0].cmp(&buf[f1]) {
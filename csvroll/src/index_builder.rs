@@ -1,28 +1,119 @@
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
 use super::avx;
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+use super::sse;
+use super::scalar;
 use super::bit;
 use super::parser::Index;
-use x86intrin::{m256i, mm256_cmpeq_epi8, mm256_movemask_epi8};
+use std::borrow::Cow;
+use std::ops::Range;
+
+/// The implementation used to build the structural character bitmaps.
+///
+/// All the backends produce identical output, they differ only in speed.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Backend {
+    Avx2,
+    Sse2,
+    Scalar,
+}
+
+impl Backend {
+    /// Return the fastest backend supported by the running CPU.
+    pub fn detect() -> Self {
+        Self::available()[0]
+    }
+
+    /// Return all the backends supported by the running CPU, the fastest first.
+    pub fn available() -> Vec<Self> {
+        let mut out = Vec::with_capacity(3);
+        for &b in &[Backend::Avx2, Backend::Sse2, Backend::Scalar] {
+            if b.is_supported() {
+                out.push(b);
+            }
+        }
+        out
+    }
+
+    /// Test if the running CPU supports this backend.
+    pub fn is_supported(&self) -> bool {
+        match *self {
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            Backend::Avx2 => is_x86_feature_detected!("avx2"),
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            Backend::Sse2 => is_x86_feature_detected!("sse2"),
+            Backend::Scalar => true,
+            #[allow(unreachable_patterns)]
+            _ => false,
+        }
+    }
+}
 
 #[derive(Debug)]
 pub struct IndexBuilder {
     // field separator
-    m_fs: m256i,
+    fs: u8,
     // record terminator
-    m_rt: m256i,
+    rt: u8,
+    quote: Option<u8>,
+    backend: Backend,
     b_fs: Vec<u64>,
     b_rt: Vec<u64>,
+    b_qt: Vec<u64>,
 }
 
 impl IndexBuilder {
     pub fn new(field_separator: u8, record_terminator: u8) -> Self {
+        Self::from_parts(field_separator, record_terminator, None, Backend::detect())
+    }
+
+    /// Create a quote-aware builder according to RFC 4180.
+    ///
+    /// The field separators and record terminators enclosed in `quote` are not treated as
+    /// structural characters. The enclosing quotes are stripped from the field ranges, escaped
+    /// (doubled) quotes inside are left as they are; use `unescape` to collapse them.
+    pub fn with_quote(field_separator: u8, record_terminator: u8, quote: u8) -> Self {
+        Self::from_parts(field_separator, record_terminator, Some(quote), Backend::detect())
+    }
+
+    /// Create a builder using the specified backend.
+    ///
+    /// # Panics
+    /// Panics if the running CPU does not support the `backend`.
+    pub fn from_parts(
+        field_separator: u8,
+        record_terminator: u8,
+        quote: Option<u8>,
+        backend: Backend,
+    ) -> Self {
+        assert!(backend.is_supported(), "the backend {:?} is not supported by the CPU", backend);
+
         Self {
-            m_fs: avx::mm256i(field_separator as i8),
-            m_rt: avx::mm256i(record_terminator as i8),
+            fs: field_separator,
+            rt: record_terminator,
+            quote,
+            backend,
             b_fs: Vec::new(),
             b_rt: Vec::new(),
+            b_qt: Vec::new(),
         }
     }
 
+    #[inline]
+    pub fn backend(&self) -> Backend {
+        self.backend
+    }
+
+    #[inline]
+    pub fn record_terminator(&self) -> u8 {
+        self.rt
+    }
+
+    #[inline]
+    pub fn quote(&self) -> Option<u8> {
+        self.quote
+    }
+
     #[inline(always)]
     pub fn build(
         &mut self,
@@ -31,83 +122,132 @@ impl IndexBuilder {
         is_buf_full: bool,
         idx: &mut Index,
     ) -> usize {
-        let b_len = (buf.len() + 63) / 64;
+        let b_len = buf.len().div_ceil(64);
         if b_len == 0 {
             return 0;
         }
-        let appendix = 64 - buf.len() % 64;
+        let appendix = (64 - buf.len() % 64) % 64;
 
         self.b_fs.clear();
         self.b_rt.clear();
+        self.b_qt.clear();
 
         if b_len > self.b_fs.capacity() {
             self.b_fs.reserve_exact(b_len);
             self.b_rt.reserve_exact(b_len);
         }
+        if self.quote.is_some() && b_len > self.b_qt.capacity() {
+            self.b_qt.reserve_exact(b_len);
+        }
 
         build_structural_character_bitmap(
+            self.backend,
             buf,
             &mut self.b_fs,
             &mut self.b_rt,
-            &self.m_fs,
-            &self.m_rt
+            &mut self.b_qt,
+            self.fs,
+            self.rt,
+            self.quote,
         );
-        build_main_index(&self.b_fs, &self.b_rt, buf_offset, appendix, is_buf_full, idx)
+
+        // The parser always resumes building at the start of an incomplete field, which can only
+        // begin outside of quotes, so the quote state needs to be carried only between the blocks
+        // of a single call.
+        if self.quote.is_some() {
+            mask_quoted(&mut self.b_fs, &mut self.b_rt, &self.b_qt);
+        }
+
+        let first_field = idx.fields().len();
+        let parsed = build_main_index(&self.b_fs, &self.b_rt, buf_offset, appendix, is_buf_full, idx);
+
+        if let Some(quote) = self.quote {
+            strip_quotes(buf, buf_offset, quote, &mut idx.fields_mut()[first_field..]);
+        }
+        parsed
     }
 }
 
+/// Collapse the escaped (doubled) quotes in the field with stripped enclosing quotes.
+pub fn unescape<'a>(field: &'a [u8], quote: u8) -> Cow<'a, [u8]> {
+    if !field.contains(&quote) {
+        return Cow::Borrowed(field);
+    }
+    let mut out = Vec::with_capacity(field.len());
+    let mut is_prev_quote = false;
+    for &b in field {
+        if b == quote && is_prev_quote {
+            is_prev_quote = false;
+            continue;
+        }
+        is_prev_quote = b == quote;
+        out.push(b);
+    }
+    Cow::Owned(out)
+}
+
 #[inline]
+#[allow(clippy::too_many_arguments)]
 fn build_structural_character_bitmap(
+    backend: Backend,
     buf: &[u8],
     b_fs: &mut Vec<u64>,
     b_rt: &mut Vec<u64>,
-    m_fs: &m256i,
-    m_rt: &m256i,
+    b_qt: &mut Vec<u64>,
+    fs: u8,
+    rt: u8,
+    qt: Option<u8>,
 ) {
-    let b_len = buf.len();
-    let mut i = 0;
-
-    while i + 63 < b_len {
-        let m1 = unsafe { avx::u8_to_m256i(buf, i) };
-        let m2 = unsafe { avx::u8_to_m256i(buf, i + 32) };
-
-        b_fs.push(mbitmap(&m1, &m2, m_fs));
-        b_rt.push(mbitmap(&m1, &m2, m_rt));
-
-        i += 64;
-    }
-    
-    if i + 32 < b_len {
-        let m1 = unsafe { avx::u8_to_m256i(buf, i) };
-        let m2 = unsafe { avx::u8_to_m256i_rest(buf, i + 32) };
-
-        b_fs.push(mbitmap(&m1, &m2, m_fs));
-        b_rt.push(mbitmap(&m1, &m2, m_rt));
-    } else if i + 32 == b_len {
-        let m1 = unsafe { avx::u8_to_m256i(buf, i) };
-        
-        b_fs.push(mbitmap_partial(&m1, m_fs));
-        b_rt.push(mbitmap_partial(&m1, m_rt));
-    } else if i < b_len {
-        let m1 = unsafe { avx::u8_to_m256i_rest(buf, i) };
-
-        b_fs.push(mbitmap_partial(&m1, m_fs));
-        b_rt.push(mbitmap_partial(&m1, m_rt));
+    match backend {
+        // the support of the instructions was checked in `IndexBuilder::from_parts`
+        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+        Backend::Avx2 => unsafe {
+            avx::build_structural_character_bitmap(buf, b_fs, b_rt, b_qt, fs, rt, qt)
+        },
+        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+        Backend::Sse2 => unsafe {
+            sse::build_structural_character_bitmap(buf, b_fs, b_rt, b_qt, fs, rt, qt)
+        },
+        _ => scalar::build_structural_character_bitmap(buf, b_fs, b_rt, b_qt, fs, rt, qt),
     }
 }
 
+/// Clear the structural characters which lie inside the quoted regions.
 #[inline]
-fn mbitmap(x1: &m256i, x2: &m256i, y: &m256i) -> u64 {
-    let i1 = mm256_movemask_epi8(mm256_cmpeq_epi8(*x1, *y));
-    let i2 = mm256_movemask_epi8(mm256_cmpeq_epi8(*x2, *y));
-    u64::from(i1 as u32) | (u64::from(i2 as u32) << 32)
+fn mask_quoted(b_fs: &mut [u64], b_rt: &mut [u64], b_qt: &[u64]) {
+    // all ones if the block starts inside the quotes
+    let mut carry = 0u64;
+    for ((f, r), q) in b_fs.iter_mut().zip(b_rt.iter_mut()).zip(b_qt) {
+        // the bit `i` is set if the number of quotes at the position `i` or higher is odd
+        let mut suffix = 0u64;
+        let mut x = *q;
+        while x != 0 {
+            suffix ^= bit::s(x);
+            x = bit::r(x);
+        }
+        let total = if q.count_ones() % 2 == 1 { !0 } else { 0 };
+        // the bit `i` is set if the number of quotes before the position `i` is odd
+        let inside = suffix ^ total ^ carry;
+        *f &= !inside;
+        *r &= !inside;
+        carry ^= total;
+    }
 }
 
+/// Strip the enclosing quotes from the fields pushed by the last build.
 #[inline]
-fn mbitmap_partial(x: &m256i, y: &m256i) -> u64 {
-    u64::from(mm256_movemask_epi8(mm256_cmpeq_epi8(*x, *y)) as u32)
-}       
-        
+fn strip_quotes(buf: &[u8], buf_offset: usize, quote: u8, fields: &mut [Range<usize>]) {
+    for f in fields {
+        if f.end - f.start >= 2
+            && buf[f.start - buf_offset] == quote
+            && buf[f.end - 1 - buf_offset] == quote
+        {
+            f.start += 1;
+            f.end -= 1;
+        }
+    }
+}
+
 #[inline]
 fn build_main_index(
     b_fs: &[u64],
@@ -169,7 +309,6 @@ mod tests {
     #[test]
     fn test_build_structural_character_bitmap() {
         let c = b',';
-        let m = avx::mm256i(c as i8);
 
         macro_rules! s {
             ($( [ $c:expr ; $n:expr ] ),*) => {{
@@ -380,20 +519,27 @@ mod tests {
                 ],
             },
         ];
-        for t in test_cases {
-            let mut d = Vec::with_capacity(t.s.len() / 64 + 1);
-            build_structural_character_bitmap(
-                &t.s,
-                &mut d,
-                &mut vec![],
-                &m,
-                &m,
-            );
-            assert_eq!(t.d, d);
+        for backend in Backend::available() {
+            for t in &test_cases {
+                let mut d = Vec::with_capacity(t.s.len() / 64 + 1);
+                let mut q = Vec::with_capacity(t.s.len() / 64 + 1);
+                build_structural_character_bitmap(
+                    backend,
+                    &t.s,
+                    &mut d,
+                    &mut vec![],
+                    &mut q,
+                    c,
+                    b'\n',
+                    Some(c),
+                );
+                assert_eq!((&t.d, &t.d), (&d, &q));
+            }
         }
     }
 
     #[test]
+    #[allow(clippy::single_range_in_vec_init)]
     fn test_build_main_index() {
         struct TestCase {
             memo: String,
@@ -638,6 +784,164 @@ mod tests {
             assert_eq!((idx, p), want);
         }
     }
+
+    #[test]
+    fn test_mask_quoted() {
+        struct TestCase {
+            memo: String,
+            b_fs: Vec<u64>,
+            b_rt: Vec<u64>,
+            b_qt: Vec<u64>,
+            want: (Vec<u64>, Vec<u64>),
+        }
+        let test_cases = vec![
+            TestCase {
+                memo: "no quotes".to_owned(),
+                b_fs: vec![0b00100100],
+                b_rt: vec![0b10000000],
+                b_qt: vec![0b00000000],
+                want: (vec![0b00100100], vec![0b10000000]),
+            },
+            TestCase {
+                memo: "separator inside quotes".to_owned(),
+                b_fs: vec![0b00100100],
+                b_rt: vec![0b10000000],
+                b_qt: vec![0b01010000],
+                want: (vec![0b00000100], vec![0b10000000]),
+            },
+            TestCase {
+                memo: "terminator inside quotes".to_owned(),
+                b_fs: vec![0b00000100],
+                b_rt: vec![0b10100000],
+                b_qt: vec![0b01001000],
+                want: (vec![0b00000100], vec![0b10000000]),
+            },
+            TestCase {
+                memo: "escaped quotes".to_owned(),
+                b_fs: vec![0b10_10010100],
+                b_rt: vec![0b00_00000000],
+                b_qt: vec![0b01_01101000],
+                want: (vec![0b10_00000100], vec![0b00_00000000]),
+            },
+            TestCase {
+                memo: "quoted region spanning the blocks".to_owned(),
+                b_fs: vec![
+                    0b00000001_00000000_00000000_00000000_00000000_00000000_00000000_00000001,
+                    0b00000000_00000000_00000000_00000000_00000000_00000000_00000000_00010001,
+                ],
+                b_rt: vec![
+                    0b10000000_00000000_00000000_00000000_00000000_00000000_00000000_00000000,
+                    0b00000000_00000000_00000000_00000000_00000000_00000000_00000000_00100000,
+                ],
+                b_qt: vec![
+                    0b00000000_00000000_00000000_00000000_00000000_00000000_00000000_00000010,
+                    0b00000000_00000000_00000000_00000000_00000000_00000000_00000000_00001000,
+                ],
+                want: (
+                    vec![
+                        0b00000000_00000000_00000000_00000000_00000000_00000000_00000000_00000001,
+                        0b00000000_00000000_00000000_00000000_00000000_00000000_00000000_00010000,
+                    ],
+                    vec![
+                        0b00000000_00000000_00000000_00000000_00000000_00000000_00000000_00000000,
+                        0b00000000_00000000_00000000_00000000_00000000_00000000_00000000_00100000,
+                    ],
+                ),
+            },
+        ];
+        for t in test_cases {
+            let TestCase { memo, mut b_fs, mut b_rt, b_qt, want } = t;
+            println!("{}", memo);
+            mask_quoted(&mut b_fs, &mut b_rt, &b_qt);
+            assert_eq!((b_fs, b_rt), want);
+        }
+    }
+
+    #[test]
+    #[allow(clippy::single_range_in_vec_init)]
+    fn test_build_quoted() {
+        struct TestCase {
+            memo: String,
+            input: String,
+            is_buf_full: bool,
+            want: (Index, usize),
+        }
+        let test_cases = vec![
+            TestCase {
+                memo: "quoted field with separator".to_owned(),
+                input: "a,\"b,c\"\nd,e".to_owned(),
+                is_buf_full: false,
+                want: (Index::from_parts(vec![0..1, 3..6, 8..9, 10..11], vec![2, 4]), 11),
+            },
+            TestCase {
+                memo: "quoted field with terminator and escaped quotes".to_owned(),
+                input: "\"a\n\"\"b\"\"\",c\nd,e".to_owned(),
+                is_buf_full: false,
+                want: (Index::from_parts(vec![1..8, 10..11, 12..13, 14..15], vec![2, 4]), 15),
+            },
+            TestCase {
+                memo: "empty quoted field".to_owned(),
+                input: "\"\",a\n".to_owned(),
+                is_buf_full: true,
+                want: (Index::from_parts(vec![1..1, 3..4], vec![2]), 5),
+            },
+            TestCase {
+                memo: "incomplete quoted field".to_owned(),
+                input: "a,\"b,c\nd".to_owned(),
+                is_buf_full: true,
+                want: (Index::from_parts(vec![0..1], vec![]), 2),
+            },
+        ];
+        for backend in Backend::available() {
+            for t in &test_cases {
+                println!("{} ({:?})", t.memo, backend);
+                let mut builder = IndexBuilder::from_parts(b',', b'\n', Some(b'"'), backend);
+                let mut idx = Index::new();
+                let p = builder.build(t.input.as_bytes(), 0, t.is_buf_full, &mut idx);
+                assert_eq!((&idx, p), (&t.want.0, t.want.1));
+            }
+        }
+    }
+
+    #[test]
+    fn test_backends_identical() {
+        // pseudo-random input composed mostly of the structural characters
+        let alphabet = b"ab,,\n\"";
+        let mut x: u32 = 42;
+        let input: Vec<u8> = (0..1000).map(|_| {
+            x = x.wrapping_mul(1103515245).wrapping_add(12345);
+            alphabet[(x >> 16) as usize % alphabet.len()]
+        }).collect();
+
+        for &quote in &[None, Some(b'"')] {
+            for &len in &[0, 1, 31, 32, 33, 63, 64, 65, 127, 128, 500, 1000] {
+                let mut want = Index::new();
+                let mut builder = IndexBuilder::from_parts(b',', b'\n', quote, Backend::Scalar);
+                let want_p = builder.build(&input[..len], 0, false, &mut want);
+
+                for backend in Backend::available() {
+                    println!("quote: {:?}, len: {}, backend: {:?}", quote, len, backend);
+                    let mut idx = Index::new();
+                    let mut builder = IndexBuilder::from_parts(b',', b'\n', quote, backend);
+                    let p = builder.build(&input[..len], 0, false, &mut idx);
+                    assert_eq!((&idx, p), (&want, want_p));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_unescape() {
+        let test_cases: Vec<(&[u8], &[u8])> = vec![
+            (b"abc", b"abc"),
+            (b"", b""),
+            (b"a\"\"b", b"a\"b"),
+            (b"\"\"\"\"", b"\"\""),
+        ];
+        for (field, want) in test_cases {
+            assert_eq!(unescape(field, b'"').as_ref(), want);
+        }
+    }
 }
 
 
@@ -1,8 +1,16 @@
-extern crate x86intrin;
 extern crate rollbuf;
+extern crate memmap2;
 
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
 pub(crate) mod avx;
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+pub(crate) mod sse;
+pub(crate) mod scalar;
 pub(crate) mod bit;
+pub mod error;
 pub mod index_builder;
+pub mod mmap;
 pub mod parser;
+pub mod pipe;
 pub mod group;
+pub mod writer;
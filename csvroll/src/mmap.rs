@@ -0,0 +1,159 @@
+use memmap2::Mmap;
+use std::cmp;
+
+/// A window into a memory-mapped file, which replaces `RollBuf` for the regular files.
+///
+/// The window has the same semantics as the buffer of `RollBuf`, but nothing is copied: consuming
+/// moves the start of the window and rolling without consuming anything extends the window up to
+/// `max_cap`.
+#[derive(Debug)]
+pub struct MmapBuf {
+    map: Mmap,
+    pos: usize,
+    end: usize,
+    // the start of the window at the last fill
+    start: usize,
+    cap: usize,
+    max_cap: usize,
+    is_rolled: bool,
+}
+
+impl MmapBuf {
+    /// Create a window of the size `cap` into `map`, which grows up to `max_cap`.
+    pub fn with_max_capacity(cap: usize, max_cap: usize, map: Mmap) -> Self {
+        MmapBuf {
+            map,
+            pos: 0,
+            end: 0,
+            start: 0,
+            cap: cmp::max(cmp::min(cap, max_cap), 1),
+            max_cap,
+            is_rolled: true,
+        }
+    }
+
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.cap
+    }
+
+    #[inline]
+    pub fn max_capacity(&self) -> usize {
+        self.max_cap
+    }
+
+    #[inline]
+    pub fn can_grow(&self) -> bool {
+        self.cap < self.max_cap
+    }
+
+    /// Return the offset of the window in the file.
+    #[inline]
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    /// Return the whole mapped file.
+    #[inline]
+    pub fn as_slice(&self) -> &[u8] {
+        &self.map
+    }
+
+    /// Move the window to the offset `pos` in the file, the window is filled on the next
+    /// `fill_buf`.
+    pub fn seek(&mut self, pos: usize) {
+        self.pos = cmp::min(pos, self.map.len());
+        self.start = self.pos;
+        self.end = self.pos;
+        self.is_rolled = true;
+    }
+
+    /// Move the window past the consumed bytes, returns `true` if the file continues past it.
+    pub fn fill_buf(&mut self) -> bool {
+        if self.is_rolled || self.pos >= self.end {
+            self.start = self.pos;
+            self.end = cmp::min(self.pos + self.cap, self.map.len());
+            self.is_rolled = false;
+        }
+        self.is_full()
+    }
+
+    #[inline]
+    pub fn consume(&mut self, n: usize) {
+        self.pos = cmp::min(self.pos + n, self.end);
+    }
+
+    /// Prepare the window for the next fill. If nothing was consumed since the last fill, the
+    /// window grows.
+    pub fn roll(&mut self) {
+        if self.pos == self.start {
+            self.cap = cmp::min(self.cap.saturating_mul(2), self.max_cap);
+        }
+        self.is_rolled = true;
+    }
+
+    #[inline]
+    pub fn contents(&self) -> &[u8] {
+        &self.map[self.pos..self.end]
+    }
+
+    #[inline]
+    pub fn is_full(&self) -> bool {
+        self.end < self.map.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use memmap2::MmapMut;
+
+    fn map(data: &[u8]) -> Mmap {
+        let mut m = MmapMut::map_anon(data.len()).unwrap();
+        m.copy_from_slice(data);
+        m.make_read_only().unwrap()
+    }
+
+    #[test]
+    fn test_mmapbuf() {
+        struct TestCase {
+            consume: usize,
+            roll: bool,
+            want: (Vec<u8>, bool),
+        }
+
+        let mut b = MmapBuf::with_max_capacity(3, 5, map(&[1, 2, 3, 4, 5, 6, 7, 8]));
+        let test_cases = vec![
+            TestCase { consume: 0, roll: false, want: (vec![1, 2, 3],        true)  },
+            TestCase { consume: 0, roll: true,  want: (vec![1, 2, 3, 4, 5],  true)  },
+            // the window does not grow beyond the maximum capacity
+            TestCase { consume: 0, roll: true,  want: (vec![1, 2, 3, 4, 5],  true)  },
+            TestCase { consume: 4, roll: true,  want: (vec![5, 6, 7, 8],     false) },
+            TestCase { consume: 4, roll: true,  want: (vec![],               false) },
+        ];
+
+        for t in test_cases {
+            b.consume(t.consume);
+            if t.roll {
+                b.roll();
+            }
+            let is_full = b.fill_buf();
+            assert_eq!((b.contents(), is_full), (t.want.0.as_slice(), t.want.1));
+        }
+        assert!(!b.can_grow());
+    }
+
+    #[test]
+    fn test_mmapbuf_seek() {
+        let mut b = MmapBuf::with_max_capacity(3, 3, map(&[1, 2, 3, 4, 5, 6, 7, 8]));
+        assert!(b.fill_buf());
+        b.seek(4);
+        assert_eq!(b.position(), 4);
+        assert!(b.fill_buf());
+        assert_eq!(b.contents(), &[5, 6, 7]);
+        b.seek(100);
+        assert!(!b.fill_buf());
+        assert_eq!(b.contents(), &[]);
+        assert_eq!(b.as_slice().len(), 8);
+    }
+}
@@ -1,7 +1,9 @@
 use rollbuf::RollBuf;
 use super::index_builder::IndexBuilder;
+use super::error::{Error, ErrorKind, Position,};
+use super::mmap::MmapBuf;
+use super::pipe::{self, Pipe,};
 
-use std::error::Error;
 use std::cmp;
 use std::ops::Range;
 use std::io;
@@ -12,6 +14,12 @@ pub struct Index {
     records: Vec<usize>,
 }
 
+impl Default for Index {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Index {
     pub fn new() -> Self {
         Self::with_capacity(0)
@@ -41,6 +49,11 @@ impl Index {
         &self.records
     }
 
+    #[inline]
+    pub(crate) fn fields_mut(&mut self) -> &mut [Range<usize>] {
+        &mut self.fields
+    }
+
     #[inline]
     pub fn push_field(&mut self, f: Range<usize>) {
         self.fields.push(f);
@@ -51,6 +64,18 @@ impl Index {
         self.records.push(r);
     }
 
+    /// Append the first `records` records of `other`, whose fields lie in the buffer at
+    /// `buf_offset`.
+    pub(crate) fn append(&mut self, other: &Index, records: usize, buf_offset: usize) {
+        let field_end = match records.checked_sub(1) {
+            Some(i) => other.records[i],
+            None => 0,
+        };
+        let field_offset = self.fields.len();
+        self.fields.extend(other.fields[..field_end].iter().map(|f| (f.start + buf_offset)..(f.end + buf_offset)));
+        self.records.extend(other.records[..records].iter().map(|r| r + field_offset));
+    }
+
     #[inline]
     pub fn get_record(&self, n: usize) -> Option<Range<usize>> {
         if n >= self.records.len() {
@@ -71,41 +96,86 @@ impl Index {
 
 
 pub struct Parser<R> {
-    buf: RollBuf<R>,
-    idx_builder: IndexBuilder,
+    src: Source<R>,
     idx: Index,
     // the number of consumed records
     consumed: Option<usize>,
     parsed: usize,
     aux: Index,
+    // the records, the bytes and the line breaks rolled out of the buffer
+    rolled_records: u64,
+    rolled_bytes: u64,
+    rolled_lines: u64,
+    // the byte ranges of the input skipped by `seek_while`, their records and lines are counted
+    // only when a position is needed
+    skipped: Vec<Range<usize>>,
+    // the quote stripped from the fields, so a quoted record starts before its first field
+    quote: Option<u8>,
+}
+
+// where the parser takes the records from
+enum Source<R> {
+    // the records are parsed from the buffer
+    Buf(RollBuf<R>, IndexBuilder),
+    // the records are parsed from the memory-mapped file
+    Mmap(MmapBuf, IndexBuilder),
+    // the records were parsed on another thread
+    Pipe(Pipe),
 }
 
 impl<R> Parser<R> {
     pub fn from_parts(buf: RollBuf<R>, idx_builder: IndexBuilder) -> Self {
+        Self::from_source(Source::Buf(buf, idx_builder))
+    }
+
+    /// Create a parser of the memory-mapped file, the fields point directly into the mapping.
+    pub fn from_mmap(buf: MmapBuf, idx_builder: IndexBuilder) -> Self {
+        Self::from_source(Source::Mmap(buf, idx_builder))
+    }
+
+    /// Create a parser of the records received from `pipe`, see `Parser::spawn`.
+    pub fn from_pipe(pipe: Pipe) -> Self {
+        Self::from_source(Source::Pipe(pipe))
+    }
+
+    fn from_source(src: Source<R>) -> Self {
+        let quote = src.quote();
         Self {
-            buf ,
-            idx_builder ,
+            src,
             idx: Index::new(),
             consumed: None,
             parsed: 0,
             aux: Index::new(),
+            rolled_records: 0,
+            rolled_bytes: 0,
+            rolled_lines: 0,
+            skipped: Vec::new(),
+            quote,
         }
     }
 }
 
+impl<R: io::Read + Send + 'static> Parser<R> {
+    /// Parse the records on a new thread. The returned parser receives them in chunks over a
+    /// channel of at most `bound` chunks, the records and their positions are the same as if they
+    /// were parsed by this parser.
+    pub fn spawn<S>(self, bound: usize) -> Parser<S> {
+        Parser::from_pipe(pipe::spawn(self, bound))
+    }
+}
+
 impl<R: io::Read> Parser<R> {
-    pub fn parse(&mut self) -> Result<bool, Box<Error>> {
+    pub fn parse(&mut self) -> Result<bool, Error> {
         if let Some(consumed) = self.consumed {
             if consumed > 0 {
                 let record_offset = cmp::min(consumed, self.idx.records.len());
                 let field_offset = *self.idx.records.get(consumed - 1)
                                                     .unwrap_or(&self.idx.fields.len());
-                let buf_offset = match self.idx.fields.get(field_offset) {
-                    Some(f) => f.start,
-                    None => self.parsed,
-                };
-                self.buf.consume(buf_offset);
-                self.buf.roll();
+                let buf_offset = self.record_start(field_offset);
+                self.rolled_records += record_offset as u64;
+                self.rolled_bytes += buf_offset as u64;
+                self.rolled_lines += count_lines(&self.src.contents()[..buf_offset]);
+                self.src.consume(buf_offset);
                 roll_index(
                     &mut self.idx,
                     &mut self.aux,
@@ -116,30 +186,202 @@ impl<R: io::Read> Parser<R> {
                 self.parsed -= buf_offset;
             } else {
                 // in this case the consumer did not consume anything, so we tell the buffer to be
-                // extended on the next call of fill_buf()
-                self.buf.roll();
+                // extended on the next call of fill_buf(), unless it is at the maximum capacity
+                if self.src.is_full() && !self.src.can_grow() {
+                    // the complete records in the buffer belong to a group, which is not over yet,
+                    // a single one is kept only to compare its key with the following record
+                    let n = self.idx.records.len();
+                    let kind = ErrorKind::BufferOverflow {
+                        group: n > 1,
+                        capacity: self.src.max_capacity(),
+                    };
+                    let pos = if n > 1 { self.position(0) } else { self.position(n) };
+                    return Err(Error::at(kind, pos));
+                }
+                match self.src {
+                    Source::Buf(ref mut buf, _) => buf.roll(),
+                    Source::Mmap(ref mut buf, _) => buf.roll(),
+                    Source::Pipe(_) => {}
+                }
+            }
+        }
+        match self.src {
+            Source::Buf(ref mut buf, ref mut idx_builder) => {
+                let is_buf_full = buf.fill_buf()?;
+                let s = buf.contents();
+                self.parsed += idx_builder.build(
+                    &s[self.parsed..],
+                    self.parsed,
+                    is_buf_full,
+                    &mut self.idx
+                );
+                Ok(is_buf_full)
+            }
+            Source::Mmap(ref mut buf, ref mut idx_builder) => {
+                let is_buf_full = buf.fill_buf();
+                let s = buf.contents();
+                self.parsed += idx_builder.build(
+                    &s[self.parsed..],
+                    self.parsed,
+                    is_buf_full,
+                    &mut self.idx
+                );
+                Ok(is_buf_full)
+            }
+            Source::Pipe(ref mut pipe) => {
+                let is_full = pipe.fill(&mut self.idx)?;
+                self.parsed = pipe.contents().len();
+                Ok(is_full)
             }
         }
-        let is_buf_full = self.buf.fill_buf()?;
-        let s = self.buf.contents();
-        self.parsed += self.idx_builder.build(
-            &s[self.parsed..],
-            self.parsed,
-            is_buf_full,
-            &mut self.idx
-        );
-
-        Ok(is_buf_full)
     }
 
     #[inline]
     pub fn output(&self) -> (&[u8], &Index) {
-        (self.buf.contents(), &self.idx)
+        (self.src.contents(), &self.idx)
+    }
+
+    /// Return the position of the record number `n` of the index in the input.
+    pub fn position(&self, n: usize) -> Position {
+        let contents = self.src.contents();
+        // the record may be incomplete, i.e. not in the index yet
+        let first_field = match n.checked_sub(1) {
+            Some(i) => self.idx.records.get(i).cloned(),
+            None => Some(0),
+        };
+        let start = match first_field {
+            Some(i) => self.record_start(i),
+            None => self.parsed,
+        };
+        let (skipped_records, skipped_lines) = self.skipped_counts();
+        Position {
+            record: self.rolled_records + skipped_records + n as u64 + 1,
+            line: self.rolled_lines + skipped_lines + count_lines(&contents[..start]) + 1,
+            byte: self.rolled_bytes + start as u64,
+        }
+    }
+
+    /// Skip the records of the input, for which `is_before` returns `true`, by bisecting the input
+    /// past the buffer instead of parsing it. Returns `false`, if the input cannot be bisected,
+    /// i.e. it is not memory-mapped or it is quoted, so a record terminator may not end a record.
+    ///
+    /// The records must be ordered, so that `is_before` is `true` for a prefix of them, and all
+    /// the complete records in the buffer must be before. The parser stops at most the window
+    /// capacity ahead of the first record, which is not before, and it is filled by the next
+    /// `parse`. The skipped records are not parsed, so they are not checked in any way.
+    pub fn seek_while<F>(&mut self, mut is_before: F) -> bool
+        where F: FnMut(&[u8], &[Range<usize>]) -> bool,
+    {
+        let n = self.idx.records.len();
+        let end = self.records_end(n);
+        let (buf, idx_builder) = match self.src {
+            Source::Mmap(ref mut buf, ref mut idx_builder) if idx_builder.quote().is_none() => {
+                (buf, idx_builder)
+            }
+            _ => return false,
+        };
+        let start = buf.position() + end;
+        let rt = idx_builder.record_terminator();
+        let lo = {
+            let data = buf.as_slice();
+            // the records before `lo` are before, the records starting at `hi` or later are not
+            let (mut lo, mut hi) = (start, data.len());
+            while hi - lo > buf.capacity() {
+                let mid = lo + (hi - lo) / 2;
+                // re-sync on the first record starting at `mid` or later
+                let rec_start = match data[(mid - 1)..(hi - 1)].iter().position(|&b| b == rt) {
+                    Some(i) => mid + i,
+                    None => {
+                        hi = mid;
+                        continue;
+                    }
+                };
+                let (rec_end, is_terminated) = match data[rec_start..hi].iter().position(|&b| b == rt) {
+                    Some(i) => (rec_start + i + 1, true),
+                    None => (hi, false),
+                };
+                self.aux.fields.clear();
+                self.aux.records.clear();
+                idx_builder.build(&data[rec_start..rec_end], rec_start, is_terminated, &mut self.aux);
+                let fields = match self.aux.records.first() {
+                    Some(&r) => &self.aux.fields[..r],
+                    None => &[],
+                };
+                if is_before(data, fields) {
+                    lo = rec_end;
+                } else {
+                    hi = rec_start;
+                }
+            }
+            lo
+        };
+
+        self.rolled_records += n as u64;
+        self.rolled_lines += count_lines(&buf.contents()[..end]);
+        self.rolled_bytes = lo as u64;
+        if lo > start {
+            self.skipped.push(start..lo);
+        }
+        buf.seek(lo);
+        self.idx.fields.clear();
+        self.idx.records.clear();
+        self.parsed = 0;
+        self.consumed = None;
+        true
+    }
+
+    // the records and the lines skipped by `seek_while`
+    fn skipped_counts(&self) -> (u64, u64) {
+        let (buf, idx_builder) = match self.src {
+            Source::Mmap(ref buf, ref idx_builder) if !self.skipped.is_empty() => (buf, idx_builder),
+            _ => return (0, 0),
+        };
+        let rt = idx_builder.record_terminator();
+        let data = buf.as_slice();
+        self.skipped.iter().fold((0, 0), |(records, lines), r| {
+            let s = &data[r.clone()];
+            (records + s.iter().filter(|&&b| b == rt).count() as u64, lines + count_lines(s))
+        })
+    }
+
+    // the maximum capacity of the buffer
+    #[inline]
+    pub(crate) fn max_capacity(&self) -> usize {
+        self.src.max_capacity()
+    }
+
+    // the end of the first `n` records in the buffer, i.e. the start of the following one
+    #[inline]
+    pub(crate) fn records_end(&self, n: usize) -> usize {
+        match n.checked_sub(1) {
+            Some(i) => self.record_start(self.idx.records[i]),
+            None => 0,
+        }
+    }
+
+    // the start of the record beginning with the field `field_offset`, or the end of the parsed
+    // input if there is no such field yet
+    fn record_start(&self, field_offset: usize) -> usize {
+        let start = match self.idx.fields.get(field_offset) {
+            Some(f) => f.start,
+            None => return self.parsed,
+        };
+        // the enclosing quote is stripped from the range of the field
+        match (self.quote, start.checked_sub(1)) {
+            (Some(quote), Some(i)) if self.src.contents()[i] == quote => i,
+            _ => start,
+        }
+    }
+
+    /// Return the quote of the input, if it is quoted.
+    #[inline]
+    pub fn quote(&self) -> Option<u8> {
+        self.quote
     }
 
     #[inline]
     pub fn is_buf_full(&self) -> bool {
-        self.buf.is_full()
+        self.src.is_full()
     }
 
     #[inline]
@@ -152,6 +394,77 @@ impl<R: io::Read> Parser<R> {
         self.consumed = Some(n);
     }
 }
+
+impl<R> Source<R> {
+    #[inline]
+    fn quote(&self) -> Option<u8> {
+        match *self {
+            Source::Buf(_, ref idx_builder) => idx_builder.quote(),
+            Source::Mmap(_, ref idx_builder) => idx_builder.quote(),
+            Source::Pipe(ref pipe) => pipe.quote(),
+        }
+    }
+}
+
+impl<R: io::Read> Source<R> {
+    #[inline]
+    fn contents(&self) -> &[u8] {
+        match *self {
+            Source::Buf(ref buf, _) => buf.contents(),
+            Source::Mmap(ref buf, _) => buf.contents(),
+            Source::Pipe(ref pipe) => pipe.contents(),
+        }
+    }
+
+    // consume `n` bytes and roll the rest to the beginning of the buffer
+    #[inline]
+    fn consume(&mut self, n: usize) {
+        match *self {
+            Source::Buf(ref mut buf, _) => {
+                buf.consume(n);
+                buf.roll();
+            }
+            Source::Mmap(ref mut buf, _) => {
+                buf.consume(n);
+                buf.roll();
+            }
+            Source::Pipe(ref mut pipe) => pipe.consume(n),
+        }
+    }
+
+    #[inline]
+    fn is_full(&self) -> bool {
+        match *self {
+            Source::Buf(ref buf, _) => buf.is_full(),
+            Source::Mmap(ref buf, _) => buf.is_full(),
+            Source::Pipe(ref pipe) => pipe.is_full(),
+        }
+    }
+
+    #[inline]
+    fn can_grow(&self) -> bool {
+        match *self {
+            Source::Buf(ref buf, _) => buf.can_grow(),
+            Source::Mmap(ref buf, _) => buf.can_grow(),
+            Source::Pipe(ref pipe) => pipe.can_grow(),
+        }
+    }
+
+    #[inline]
+    fn max_capacity(&self) -> usize {
+        match *self {
+            Source::Buf(ref buf, _) => buf.max_capacity(),
+            Source::Mmap(ref buf, _) => buf.max_capacity(),
+            Source::Pipe(ref pipe) => pipe.max_capacity(),
+        }
+    }
+}
+
+// count the line breaks
+#[inline]
+fn count_lines(s: &[u8]) -> u64 {
+    s.iter().filter(|&&b| b == b'\n').count() as u64
+}
         
 #[inline]
 fn roll_index(
@@ -186,6 +499,7 @@ mod tests {
     use super::*;
     
     #[test]
+    #[allow(clippy::single_range_in_vec_init)]
     fn test_index_get_record() {
         
         struct TestCase {
@@ -203,12 +517,12 @@ mod tests {
             TestCase {
                 idx: Index::from_parts(vec![0..1, 2..3, 4..5], vec![2, 3]),
                 n: 0,
-                want: Some(0..2),
+                want: Some(vec![0..1, 2..3]),
             },
             TestCase {
                 idx: Index::from_parts(vec![0..1, 2..3, 4..5], vec![2, 3]),
                 n: 1,
-                want: Some(2..3),
+                want: Some(vec![4..5]),
             },
             TestCase {
                 idx: Index::from_parts(vec![0..1, 2..3, 4..5], vec![2, 3]),
@@ -217,11 +531,13 @@ mod tests {
             },
         ];
         for t in test_cases {
-            assert_eq!(t.idx.get_record(t.n), t.want);
+            // compare the fields of the record
+            assert_eq!(t.idx.get_record(t.n).map(|r| t.idx.fields()[r].to_vec()), t.want);
         }
     }
 
     #[test]
+    #[allow(clippy::single_range_in_vec_init)]
     fn test_roll_index() {
         struct TestCase {
             idx: Index,
@@ -271,6 +587,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::single_range_in_vec_init)]
     fn test_parser() {
         use rollbuf::RollBuf;
 
@@ -352,5 +669,237 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_parser_quoted() {
+        use rollbuf::RollBuf;
+
+        let input = "\"a\nb\",c\nd,e";
+        let consume = [0, 1, 1, 1];
+        let want = vec![
+            ("\"a\nb", Index::from_parts(vec![], vec![])),
+            ("\"a\nb\",c\n", Index::from_parts(vec![1..4, 6..7], vec![2])),
+            ("d,e", Index::from_parts(vec![0..1, 2..3], vec![2])),
+            ("", Index::from_parts(vec![], vec![])),
+        ];
+        let buf = RollBuf::with_capacity(4, input.as_bytes());
+        let idx_builder = IndexBuilder::with_quote(b',', b'\n', b'"');
+        let mut parser = Parser::from_parts(buf, idx_builder);
+
+        for (i, (c, w)) in consume.iter().zip(&want).enumerate() {
+            println!("parse: {}", i);
+            parser.parse().unwrap();
+            assert_eq!(parser.output(), (w.0.as_bytes(), &w.1));
+            parser.consume(*c);
+        }
+    }
+    #[test]
+    fn test_parser_position() {
+        use rollbuf::RollBuf;
+
+        // the second record spans two lines, the quoted records start at their opening quote
+        let input = "\"a\",0\n\"b\nc\",1\nd,2\n\"e\",3";
+        let buf = RollBuf::with_capacity(8, input.as_bytes());
+        let idx_builder = IndexBuilder::with_quote(b',', b'\n', b'"');
+        let mut parser = Parser::from_parts(buf, idx_builder);
+
+        let mut positions = Vec::new();
+        loop {
+            let is_buf_full = parser.parse().unwrap();
+            let n = parser.output().1.records().len();
+            for i in 0..n {
+                positions.push(parser.position(i));
+            }
+            parser.consume(n);
+            if !is_buf_full {
+                break;
+            }
+        }
+        let want: Vec<Position> = vec![(1, 1, 0), (2, 2, 6), (3, 4, 14), (4, 5, 18)]
+            .into_iter()
+            .map(|(record, line, byte)| Position { record, line, byte })
+            .collect();
+        assert_eq!(positions, want);
+    }
+
+    #[test]
+    fn test_parser_overflow() {
+        use rollbuf::RollBuf;
+
+        struct TestCase {
+            input: String,
+            consume: usize,
+            want: String,
+        }
+
+        let test_cases = vec![
+            TestCase {
+                input: "a,0\nbbbbbbbbbb,1\n".to_owned(),
+                consume: 1,
+                want: "the record number 2 (line 2, byte 4) does not fit into the buffer of 8 bytes".to_owned(),
+            },
+            TestCase {
+                input: "a,0\nbbbbbbbbbb,1\n".to_owned(),
+                consume: 0,
+                want: "the record number 2 (line 2, byte 4) does not fit into the buffer of 8 bytes".to_owned(),
+            },
+            TestCase {
+                input: "a,0\na,1\na,2\n".to_owned(),
+                consume: 0,
+                want: "the group of the records with the same key starting at the record number 1 \
+                       (line 1, byte 0) does not fit into the buffer of 8 bytes".to_owned(),
+            },
+        ];
+
+        for t in test_cases {
+            let buf = RollBuf::with_max_capacity(4, 8, t.input.as_bytes());
+            let mut parser = Parser::from_parts(buf, IndexBuilder::new(b',', b'\n'));
+            let err = loop {
+                match parser.parse() {
+                    Ok(_) => {
+                        let n = parser.output().1.records().len();
+                        parser.consume(cmp::min(n, t.consume));
+                    }
+                    Err(e) => break e,
+                }
+            };
+            assert_eq!(err.to_string(), t.want);
+        }
+    }
+
+    #[test]
+    fn test_parser_mmap() {
+        use memmap2::MmapMut;
+        use mmap::MmapBuf;
+        use rollbuf::RollBuf;
+
+        // the records, their positions and the overflow errors, consuming a record at a time
+        fn records<R: io::Read>(mut parser: Parser<R>) -> Vec<String> {
+            let mut out = Vec::new();
+            loop {
+                let is_buf_full = match parser.parse() {
+                    Ok(is_buf_full) => is_buf_full,
+                    Err(e) => {
+                        out.push(e.to_string());
+                        return out;
+                    }
+                };
+                let n = parser.output().1.records().len();
+                if n > 0 {
+                    let (buf, idx) = parser.output();
+                    let r = idx.get_record(0).unwrap();
+                    let f = &idx.fields()[r];
+                    let rec = String::from_utf8_lossy(&buf[f[0].start..f[f.len() - 1].end]);
+                    out.push(format!("{} {}", rec, parser.position(0)));
+                    parser.consume(1);
+                } else {
+                    parser.consume(0);
+                }
+                if !is_buf_full && n <= 1 {
+                    return out;
+                }
+            }
+        }
+
+        for &(input, max_cap) in &[
+            ("a,0\n\"b\nc\",1\nd,2\ne,3", 64),
+            ("a,0\n\"b\nc\",1\nd,2\ne,3\n", 64),
+            ("a,0\nbbbbbbbbbb,1\n", 8),
+        ] {
+            let buf = RollBuf::with_max_capacity(4, max_cap, input.as_bytes());
+            let want = records(Parser::from_parts(buf, IndexBuilder::with_quote(b',', b'\n', b'"')));
+
+            let mut m = MmapMut::map_anon(input.len()).unwrap();
+            m.copy_from_slice(input.as_bytes());
+            let buf = MmapBuf::with_max_capacity(4, max_cap, m.make_read_only().unwrap());
+            let parser: Parser<io::Empty> = Parser::from_mmap(buf, IndexBuilder::with_quote(b',', b'\n', b'"'));
+            assert_eq!(records(parser), want, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_parser_seek() {
+        use memmap2::MmapMut;
+        use mmap::MmapBuf;
+        use index_builder::Backend;
+
+        struct TestCase {
+            rt: u8,
+            quote: Option<u8>,
+            target: usize,
+            // the record number and the line of the first record not before the target
+            want: Option<(u64, u64)>,
+            want_seek: bool,
+        }
+
+        let test_cases = vec![
+            TestCase { rt: b'\n', quote: None, target: 0, want: Some((1, 1)), want_seek: true },
+            TestCase { rt: b'\n', quote: None, target: 1, want: Some((2, 2)), want_seek: true },
+            TestCase { rt: b'\n', quote: None, target: 777, want: Some((778, 778)), want_seek: true },
+            TestCase { rt: b'\n', quote: None, target: 999, want: Some((1000, 1000)), want_seek: true },
+            TestCase { rt: b'\n', quote: None, target: 1000, want: None, want_seek: true },
+            // the fields contain line breaks
+            TestCase { rt: b';', quote: None, target: 555, want: Some((556, 1111)), want_seek: true },
+            TestCase { rt: b'\n', quote: Some(b'"'), target: 555, want: Some((556, 556)), want_seek: false },
+        ];
+
+        for t in test_cases {
+            let rec_len = 10;
+            let mut input = String::new();
+            for i in 0..1000 {
+                let sep = if t.rt == b'\n' { ',' } else { '\n' };
+                input.push_str(&format!("{:04},x{}y{}{}", i, sep, sep, t.rt as char));
+            }
+            // the last record is not terminated
+            input.pop();
+            let key = |buf: &[u8], fields: &[Range<usize>]| -> usize {
+                String::from_utf8_lossy(&buf[fields[0].clone()]).parse().unwrap()
+            };
+
+            let mut m = MmapMut::map_anon(input.len()).unwrap();
+            m.copy_from_slice(input.as_bytes());
+            let buf = MmapBuf::with_max_capacity(32, 64, m.make_read_only().unwrap());
+            let idx_builder = IndexBuilder::from_parts(b',', t.rt, t.quote, Backend::detect());
+            let mut parser: Parser<io::Empty> = Parser::from_mmap(buf, idx_builder);
+
+            let mut is_buf_full = parser.parse().unwrap();
+            let n = parser.output().1.records().len();
+            let is_before = {
+                let (buf, idx) = parser.output();
+                key(buf, &idx.fields()[idx.get_record(n - 1).unwrap()]) < t.target
+            };
+            if is_before {
+                assert_eq!(parser.seek_while(|buf, fields| key(buf, fields) < t.target), t.want_seek);
+                if !t.want_seek {
+                    parser.consume(n);
+                }
+                // the records far enough past the window are bisected
+                assert_eq!(!parser.skipped.is_empty(), t.want_seek && t.target > 100);
+                is_buf_full = parser.parse().unwrap();
+            }
+
+            // scan the rest of the way
+            let mut got = None;
+            loop {
+                let found = {
+                    let (buf, idx) = parser.output();
+                    (0..idx.records().len())
+                        .find(|&i| key(buf, &idx.fields()[idx.get_record(i).unwrap()]) >= t.target)
+                };
+                if let Some(i) = found {
+                    let pos = parser.position(i);
+                    assert_eq!(pos.byte, (pos.record - 1) * rec_len);
+                    got = Some((pos.record, pos.line));
+                    break;
+                }
+                if !is_buf_full {
+                    break;
+                }
+                let n = parser.output().1.records().len();
+                parser.consume(n);
+                is_buf_full = parser.parse().unwrap();
+            }
+            assert_eq!(got, t.want, "{}", t.target);
+        }
+    }
 }
-            
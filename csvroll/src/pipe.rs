@@ -0,0 +1,189 @@
+use super::parser::{Parser, Index,};
+use super::error::Error;
+use std::io;
+use std::sync::mpsc::{self, Receiver,};
+use std::thread;
+
+// the complete records parsed on the thread of the pipe
+struct Chunk {
+    buf: Vec<u8>,
+    idx: Index,
+    is_last: bool,
+}
+
+/// The receiving end of the records parsed on another thread.
+///
+/// The chunks are joined into a buffer, which holds the records not consumed yet, the same way
+/// `RollBuf` does.
+pub struct Pipe {
+    rx: Receiver<Result<Chunk, Error>>,
+    buf: Vec<u8>,
+    max_cap: usize,
+    is_last: bool,
+    quote: Option<u8>,
+}
+
+/// Parse the records by `parser` on a new thread and send them over a channel of at most `bound`
+/// chunks. The thread stops once the pipe is dropped.
+pub(crate) fn spawn<R: io::Read + Send + 'static>(mut parser: Parser<R>, bound: usize) -> Pipe {
+    let max_cap = parser.max_capacity();
+    let quote = parser.quote();
+    let (tx, rx) = mpsc::sync_channel(bound);
+    thread::spawn(move || loop {
+        let res = parser.parse().map(|is_buf_full| {
+            let n = parser.output().1.records().len();
+            let end = parser.records_end(n);
+            let (buf, idx) = parser.output();
+            let mut chunk_idx = Index::new();
+            chunk_idx.append(idx, n, 0);
+            let chunk = Chunk {
+                buf: buf[..end].to_vec(),
+                idx: chunk_idx,
+                is_last: !is_buf_full,
+            };
+            parser.consume(n);
+            chunk
+        });
+        let is_last = match res {
+            Ok(ref chunk) if !chunk.is_last && chunk.idx.records().is_empty() => {
+                // the record does not fit into the buffer yet
+                continue;
+            }
+            Ok(ref chunk) => chunk.is_last,
+            Err(_) => true,
+        };
+        if tx.send(res).is_err() || is_last {
+            return;
+        }
+    });
+    Pipe {
+        rx,
+        buf: Vec::new(),
+        max_cap,
+        is_last: false,
+        quote,
+    }
+}
+
+impl Pipe {
+    #[inline]
+    pub(crate) fn contents(&self) -> &[u8] {
+        &self.buf
+    }
+
+    #[inline]
+    pub(crate) fn consume(&mut self, n: usize) {
+        self.buf.drain(..n);
+    }
+
+    /// Test if more records may follow, like a full `RollBuf`.
+    #[inline]
+    pub(crate) fn is_full(&self) -> bool {
+        !self.is_last
+    }
+
+    #[inline]
+    pub(crate) fn can_grow(&self) -> bool {
+        self.buf.len() < self.max_cap
+    }
+
+    #[inline]
+    pub(crate) fn max_capacity(&self) -> usize {
+        self.max_cap
+    }
+
+    #[inline]
+    pub(crate) fn quote(&self) -> Option<u8> {
+        self.quote
+    }
+
+    /// Receive the next chunk and append its records to `idx`. Returns `false` if it was the last
+    /// one.
+    pub(crate) fn fill(&mut self, idx: &mut Index) -> Result<bool, Error> {
+        if self.is_last {
+            return Ok(false);
+        }
+        match self.rx.recv() {
+            Ok(Ok(chunk)) => {
+                idx.append(&chunk.idx, chunk.idx.records().len(), self.buf.len());
+                self.buf.extend_from_slice(&chunk.buf);
+                self.is_last = chunk.is_last;
+                Ok(!self.is_last)
+            }
+            Ok(Err(e)) => {
+                self.is_last = true;
+                Err(e)
+            }
+            Err(_) => {
+                self.is_last = true;
+                Err(io::Error::new(io::ErrorKind::Other, "the parser thread stopped unexpectedly").into())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use index_builder::IndexBuilder;
+    use rollbuf::RollBuf;
+
+    // the records with their positions, consuming at most `consume` records after each parse
+    fn records<R: io::Read>(mut parser: Parser<R>, consume: usize) -> Result<Vec<(String, u64, u64, u64)>, Error> {
+        let mut out = Vec::new();
+        loop {
+            let is_buf_full = parser.parse()?;
+            let (n, is_all) = {
+                let (buf, idx) = parser.output();
+                let n = ::std::cmp::min(idx.records().len(), consume);
+                for i in 0..n {
+                    let r = idx.get_record(i).unwrap();
+                    let rec: Vec<String> = idx.fields()[r].iter()
+                        .map(|f| String::from_utf8(buf[f.clone()].to_vec()).unwrap())
+                        .collect();
+                    let pos = parser.position(i);
+                    out.push((rec.join("|"), pos.record, pos.line, pos.byte));
+                }
+                (n, n == idx.records().len())
+            };
+            parser.consume(n);
+            if !is_buf_full && is_all {
+                return Ok(out);
+            }
+        }
+    }
+
+    #[test]
+    fn test_pipe() {
+        struct TestCase {
+            input: String,
+            max_cap: usize,
+            consume: usize,
+        }
+
+        let quoted: String = (0..200).map(|i| format!("\"a\n{}\",b,{}\n", i, i * i)).collect();
+        let test_cases = vec![
+            TestCase { input: "".to_owned(), max_cap: 64, consume: 1 },
+            TestCase { input: "a,0\nb,1\nc".to_owned(), max_cap: 64, consume: 1 },
+            TestCase { input: quoted.clone(), max_cap: 64, consume: 1 },
+            TestCase { input: quoted.clone(), max_cap: 64, consume: 3 },
+            TestCase { input: quoted, max_cap: 1024, consume: 100 },
+            TestCase { input: "a,0\nbbbbbbbbbbbb,1\n".to_owned(), max_cap: 8, consume: 1 },
+        ];
+
+        for t in test_cases {
+            let parser = |input: String| {
+                let buf = RollBuf::with_max_capacity(8, t.max_cap, io::Cursor::new(input));
+                Parser::from_parts(buf, IndexBuilder::with_quote(b',', b'\n', b'"'))
+            };
+            let want = records(parser(t.input.clone()), t.consume);
+            let piped: Parser<io::Empty> = parser(t.input.clone()).spawn(2);
+            let got = records(piped, t.consume);
+            match (got, want) {
+                (Ok(got), Ok(want)) => assert_eq!(got, want, "{}", t.input),
+                (Err(got), Err(want)) => assert_eq!(got.to_string(), want.to_string(), "{}", t.input),
+                (got, want) => panic!("{:?} != {:?}", got.map(|_| ()), want.map(|_| ())),
+            }
+        }
+    }
+}
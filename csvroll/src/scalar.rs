@@ -0,0 +1,88 @@
+/// Build the bitmaps of the field separators, record terminators and optionally quotes in `buf`
+/// without any SIMD instructions.
+pub fn build_structural_character_bitmap(
+    buf: &[u8],
+    b_fs: &mut Vec<u64>,
+    b_rt: &mut Vec<u64>,
+    b_qt: &mut Vec<u64>,
+    fs: u8,
+    rt: u8,
+    qt: Option<u8>,
+) {
+    for block in buf.chunks(64) {
+        let mut f = 0u64;
+        let mut r = 0u64;
+        let mut q = 0u64;
+        for (i, &c) in block.iter().enumerate() {
+            f |= u64::from(c == fs) << i;
+            r |= u64::from(c == rt) << i;
+            q |= u64::from(Some(c) == qt) << i;
+        }
+
+        b_fs.push(f);
+        b_rt.push(r);
+        if qt.is_some() {
+            b_qt.push(q);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct TestCase {
+        input: Vec<u8>,
+        fs: u8,
+        qt: Option<u8>,
+        want: (Vec<u64>, Vec<u64>, Vec<u64>),
+    }
+
+    fn test_cases() -> Vec<TestCase> {
+        vec![
+            TestCase {
+                input: vec![],
+                fs: b',',
+                qt: Some(b'"'),
+                want: (vec![], vec![], vec![]),
+            },
+            TestCase {
+                input: b"a,\"b\"\n".to_vec(),
+                fs: b',',
+                qt: Some(b'"'),
+                want: (vec![0x2], vec![0x20], vec![0x14]),
+            },
+            TestCase {
+                input: b"a,b\n".repeat(20),
+                fs: b',',
+                qt: Some(b'"'),
+                want: (
+                    vec![0x2222_2222_2222_2222, 0x2222],
+                    vec![0x8888_8888_8888_8888, 0x8888],
+                    vec![0, 0],
+                ),
+            },
+            TestCase {
+                input: b"a,\"b\"\n".to_vec(),
+                fs: b',',
+                qt: None,
+                want: (vec![0x2], vec![0x20], vec![]),
+            },
+            TestCase {
+                input: vec![0xfe, b'a', 0xfe],
+                fs: 0xfe,
+                qt: None,
+                want: (vec![0b101], vec![0], vec![]),
+            },
+        ]
+    }
+
+    #[test]
+    fn test_build_structural_character_bitmap() {
+        for t in test_cases() {
+            let (mut b_fs, mut b_rt, mut b_qt) = (vec![], vec![], vec![]);
+            build_structural_character_bitmap(&t.input, &mut b_fs, &mut b_rt, &mut b_qt, t.fs, b'\n', t.qt);
+            assert_eq!((b_fs, b_rt, b_qt), t.want);
+        }
+    }
+}
@@ -0,0 +1,148 @@
+#[cfg(target_arch = "x86")]
+use std::arch::x86::*;
+#[cfg(target_arch = "x86_64")]
+use std::arch::x86_64::*;
+
+/// Build the bitmaps of the field separators, record terminators and optionally quotes in `buf`
+/// using SSE2 instructions.
+///
+/// # Safety
+/// The caller must ensure that the CPU supports SSE2.
+#[target_feature(enable = "sse2")]
+pub unsafe fn build_structural_character_bitmap(
+    buf: &[u8],
+    b_fs: &mut Vec<u64>,
+    b_rt: &mut Vec<u64>,
+    b_qt: &mut Vec<u64>,
+    fs: u8,
+    rt: u8,
+    qt: Option<u8>,
+) {
+    let m_fs = _mm_set1_epi8(fs as i8);
+    let m_rt = _mm_set1_epi8(rt as i8);
+    let m_qt = _mm_set1_epi8(qt.unwrap_or(0) as i8);
+    let b_len = buf.len();
+    let mut i = 0;
+
+    while i + 64 <= b_len {
+        let m = load(buf.as_ptr().add(i));
+
+        b_fs.push(mbitmap(&m, m_fs));
+        b_rt.push(mbitmap(&m, m_rt));
+        if qt.is_some() {
+            b_qt.push(mbitmap(&m, m_qt));
+        }
+
+        i += 64;
+    }
+
+    if i < b_len {
+        let rest = b_len - i;
+        let mut block = [0u8; 64];
+        block[..rest].copy_from_slice(&buf[i..]);
+        let m = load(block.as_ptr());
+        let valid = (1u64 << rest) - 1;
+
+        b_fs.push(mbitmap(&m, m_fs) & valid);
+        b_rt.push(mbitmap(&m, m_rt) & valid);
+        if qt.is_some() {
+            b_qt.push(mbitmap(&m, m_qt) & valid);
+        }
+    }
+}
+
+#[inline]
+#[target_feature(enable = "sse2")]
+unsafe fn load(p: *const u8) -> [__m128i; 4] {
+    [
+        _mm_loadu_si128(p as *const __m128i),
+        _mm_loadu_si128(p.add(16) as *const __m128i),
+        _mm_loadu_si128(p.add(32) as *const __m128i),
+        _mm_loadu_si128(p.add(48) as *const __m128i),
+    ]
+}
+
+#[inline]
+#[target_feature(enable = "sse2")]
+unsafe fn mbitmap(x: &[__m128i; 4], y: __m128i) -> u64 {
+    let i0 = _mm_movemask_epi8(_mm_cmpeq_epi8(x[0], y));
+    let i1 = _mm_movemask_epi8(_mm_cmpeq_epi8(x[1], y));
+    let i2 = _mm_movemask_epi8(_mm_cmpeq_epi8(x[2], y));
+    let i3 = _mm_movemask_epi8(_mm_cmpeq_epi8(x[3], y));
+    u64::from(i0 as u16)
+        | (u64::from(i1 as u16) << 16)
+        | (u64::from(i2 as u16) << 32)
+        | (u64::from(i3 as u16) << 48)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct TestCase {
+        input: Vec<u8>,
+        fs: u8,
+        qt: Option<u8>,
+        want: (Vec<u64>, Vec<u64>, Vec<u64>),
+    }
+
+    fn test_cases() -> Vec<TestCase> {
+        vec![
+            TestCase {
+                input: vec![],
+                fs: b',',
+                qt: Some(b'"'),
+                want: (vec![], vec![], vec![]),
+            },
+            TestCase {
+                input: b"a,\"b\"\n".to_vec(),
+                fs: b',',
+                qt: Some(b'"'),
+                want: (vec![0x2], vec![0x20], vec![0x14]),
+            },
+            TestCase {
+                input: b"a,b\n".repeat(20),
+                fs: b',',
+                qt: Some(b'"'),
+                want: (
+                    vec![0x2222_2222_2222_2222, 0x2222],
+                    vec![0x8888_8888_8888_8888, 0x8888],
+                    vec![0, 0],
+                ),
+            },
+            TestCase {
+                input: b"a,\"b\"\n".to_vec(),
+                fs: b',',
+                qt: None,
+                want: (vec![0x2], vec![0x20], vec![]),
+            },
+            TestCase {
+                input: vec![0xfe, b'a', 0xfe],
+                fs: 0xfe,
+                qt: None,
+                want: (vec![0b101], vec![0], vec![]),
+            },
+        ]
+    }
+
+    #[test]
+    fn test_mbitmap() {
+        let test_cases = vec![(0, 0), (1, 0xffff), (3, 0xffff_0000_0000), (4, 0xffff_0000_0000_0000)];
+        for (y, want) in test_cases {
+            let got = unsafe {
+                let x = [_mm_set1_epi8(1), _mm_set1_epi8(2), _mm_set1_epi8(3), _mm_set1_epi8(4)];
+                mbitmap(&x, _mm_set1_epi8(y))
+            };
+            assert_eq!(got, want);
+        }
+    }
+
+    #[test]
+    fn test_build_structural_character_bitmap() {
+        for t in test_cases() {
+            let (mut b_fs, mut b_rt, mut b_qt) = (vec![], vec![], vec![]);
+            unsafe { build_structural_character_bitmap(&t.input, &mut b_fs, &mut b_rt, &mut b_qt, t.fs, b'\n', t.qt); }
+            assert_eq!((b_fs, b_rt, b_qt), t.want);
+        }
+    }
+}
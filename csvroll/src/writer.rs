@@ -0,0 +1,257 @@
+use index_builder::unescape;
+use std::io;
+
+/// When the fields are enclosed in quotes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum QuoteStyle {
+    /// Quote the fields containing the delimiter, the terminator, the quote, the escape character
+    /// or a line break.
+    #[default]
+    Necessary,
+    /// Quote all the fields.
+    Always,
+    /// Never quote the fields, they are written as they are.
+    Never,
+    /// Quote all the fields which are not numbers and the fields where it is necessary.
+    NonNumeric,
+}
+
+/// A writer of the CSV fields according to RFC 4180.
+///
+/// The quotes inside the quoted fields are doubled, or preceded by the `escape` character, if it
+/// is set. The fields read by a parser with a quote contain the escaped (doubled) quotes of the
+/// input, they are collapsed before the field is written, if `in_quote` is set.
+#[derive(Debug, Clone)]
+pub struct Writer {
+    delimiter: u8,
+    terminator: u8,
+    quote: u8,
+    style: QuoteStyle,
+    escape: Option<u8>,
+    in_quote: Option<u8>,
+}
+
+impl Writer {
+    /// Create a writer, which quotes the fields with `"` where it is necessary.
+    pub fn new(delimiter: u8, terminator: u8) -> Self {
+        Self::from_parts(delimiter, terminator, b'"', QuoteStyle::Necessary, None, None)
+    }
+
+    /// Create a writer, which quotes the fields with `quote` according to `style`. The fields are
+    /// unescaped with `in_quote` first, if it is set.
+    pub fn from_parts(
+        delimiter: u8,
+        terminator: u8,
+        quote: u8,
+        style: QuoteStyle,
+        escape: Option<u8>,
+        in_quote: Option<u8>,
+    ) -> Self {
+        Writer {
+            delimiter,
+            terminator,
+            quote,
+            style,
+            escape,
+            in_quote,
+        }
+    }
+
+    #[inline]
+    pub fn delimiter(&self) -> u8 {
+        self.delimiter
+    }
+
+    #[inline]
+    pub fn terminator(&self) -> u8 {
+        self.terminator
+    }
+
+    /// Write the `field` into `w`, quoted if required by the quote style.
+    #[inline]
+    pub fn write_field<W: io::Write>(&self, w: &mut W, field: &[u8]) -> io::Result<()> {
+        let field = match self.in_quote {
+            Some(q) => unescape(field, q),
+            None => field.into(),
+        };
+        let is_quoted = match self.style {
+            QuoteStyle::Never => false,
+            QuoteStyle::Always => true,
+            QuoteStyle::Necessary => self.is_necessary(&field),
+            QuoteStyle::NonNumeric => !is_numeric(&field) || self.is_necessary(&field),
+        };
+        if !is_quoted {
+            return w.write_all(&field);
+        }
+
+        w.write_all(&[self.quote])?;
+        let mut start = 0;
+        for (i, &b) in field.iter().enumerate() {
+            if b == self.quote || Some(b) == self.escape {
+                w.write_all(&field[start..i])?;
+                w.write_all(&[self.escape.unwrap_or(self.quote)])?;
+                start = i;
+            }
+        }
+        w.write_all(&field[start..])?;
+        w.write_all(&[self.quote])
+    }
+
+    #[inline]
+    fn is_necessary(&self, field: &[u8]) -> bool {
+        field.iter().any(|&b| {
+            b == self.delimiter
+                || b == self.terminator
+                || b == self.quote
+                || b == b'\n'
+                || b == b'\r'
+                || Some(b) == self.escape
+        })
+    }
+}
+
+/// Test if the `field` is a decimal number, e.g. `-1.5` or `2e10`.
+fn is_numeric(field: &[u8]) -> bool {
+    let mut i = 0;
+    let digits = |i: &mut usize| {
+        let start = *i;
+        while *i < field.len() && field[*i].is_ascii_digit() {
+            *i += 1;
+        }
+        *i - start
+    };
+    if i < field.len() && (field[i] == b'-' || field[i] == b'+') {
+        i += 1;
+    }
+    let mut n = digits(&mut i);
+    if i < field.len() && field[i] == b'.' {
+        i += 1;
+        n += digits(&mut i);
+    }
+    if n == 0 {
+        return false;
+    }
+    if i < field.len() && (field[i] == b'e' || field[i] == b'E') {
+        i += 1;
+        if i < field.len() && (field[i] == b'-' || field[i] == b'+') {
+            i += 1;
+        }
+        if digits(&mut i) == 0 {
+            return false;
+        }
+    }
+    i == field.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_field() {
+        struct TestCase {
+            memo: String,
+            writer: Writer,
+            field: String,
+            want: String,
+        }
+
+        let test_cases = vec![
+            TestCase {
+                memo: "plain field".to_owned(),
+                writer: Writer::new(b',', b'\n'),
+                field: "abc".to_owned(),
+                want: "abc".to_owned(),
+            },
+            TestCase {
+                memo: "delimiter".to_owned(),
+                writer: Writer::new(b',', b'\n'),
+                field: "a,b".to_owned(),
+                want: "\"a,b\"".to_owned(),
+            },
+            TestCase {
+                memo: "other delimiter".to_owned(),
+                writer: Writer::new(b';', b'\n'),
+                field: "a,b".to_owned(),
+                want: "a,b".to_owned(),
+            },
+            TestCase {
+                memo: "line break".to_owned(),
+                writer: Writer::new(b',', b'|'),
+                field: "a\r\nb".to_owned(),
+                want: "\"a\r\nb\"".to_owned(),
+            },
+            TestCase {
+                memo: "terminator".to_owned(),
+                writer: Writer::new(b',', b'|'),
+                field: "a|b".to_owned(),
+                want: "\"a|b\"".to_owned(),
+            },
+            TestCase {
+                memo: "doubled quotes".to_owned(),
+                writer: Writer::new(b',', b'\n'),
+                field: "say \"hi\"".to_owned(),
+                want: "\"say \"\"hi\"\"\"".to_owned(),
+            },
+            TestCase {
+                memo: "escape character".to_owned(),
+                writer: Writer::from_parts(b',', b'\n', b'"', QuoteStyle::Necessary, Some(b'\\'), None),
+                field: "a\"b\\c".to_owned(),
+                want: "\"a\\\"b\\\\c\"".to_owned(),
+            },
+            TestCase {
+                memo: "always".to_owned(),
+                writer: Writer::from_parts(b',', b'\n', b'\'', QuoteStyle::Always, None, None),
+                field: "1".to_owned(),
+                want: "'1'".to_owned(),
+            },
+            TestCase {
+                memo: "never".to_owned(),
+                writer: Writer::from_parts(b',', b'\n', b'"', QuoteStyle::Never, None, None),
+                field: "a,\"b".to_owned(),
+                want: "a,\"b".to_owned(),
+            },
+            TestCase {
+                memo: "non-numeric number".to_owned(),
+                writer: Writer::from_parts(b',', b'\n', b'"', QuoteStyle::NonNumeric, None, None),
+                field: "-1.5e3".to_owned(),
+                want: "-1.5e3".to_owned(),
+            },
+            TestCase {
+                memo: "non-numeric text".to_owned(),
+                writer: Writer::from_parts(b',', b'\n', b'"', QuoteStyle::NonNumeric, None, None),
+                field: "1a".to_owned(),
+                want: "\"1a\"".to_owned(),
+            },
+            TestCase {
+                memo: "escaped quotes of the input".to_owned(),
+                writer: Writer::from_parts(b',', b'\n', b'"', QuoteStyle::Necessary, None, Some(b'"')),
+                field: "a\"\"b".to_owned(),
+                want: "\"a\"\"b\"".to_owned(),
+            },
+            TestCase {
+                memo: "escaped quotes of the input with the escape character".to_owned(),
+                writer: Writer::from_parts(b',', b'\n', b'"', QuoteStyle::Necessary, Some(b'\\'), Some(b'"')),
+                field: "a\"\"b".to_owned(),
+                want: "\"a\\\"b\"".to_owned(),
+            },
+        ];
+
+        for t in test_cases {
+            let TestCase { memo, writer, field, want } = t;
+            let mut out = Vec::new();
+            writer.write_field(&mut out, field.as_bytes()).unwrap();
+            assert_eq!(String::from_utf8(out).unwrap(), want, "{}", memo);
+        }
+    }
+
+    #[test]
+    fn test_is_numeric() {
+        for s in &["0", "-1", "+2.5", ".5", "5.", "1e3", "1E-3"] {
+            assert!(is_numeric(s.as_bytes()), "{}", s);
+        }
+        for s in &["", "-", ".", "e3", "1e", "1.2.3", "0x10", " 1"] {
+            assert!(!is_numeric(s.as_bytes()), "{}", s);
+        }
+    }
+}
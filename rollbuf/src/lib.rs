@@ -1,8 +1,9 @@
 use std::io;
-use std::error::Error;
 use std::cmp;
 
 const DAFAULT_BUF_SIZE: usize = 8 * 1024;
+/// The default maximum capacity, up to which the buffer grows.
+pub const DEFAULT_MAX_CAPACITY: usize = 1 << 24;
 
 #[derive(Debug)]
 pub struct RollBuf<R>  {
@@ -21,6 +22,13 @@ impl<R: io::Read> RollBuf<R> {
     }
 
     pub fn with_capacity(cap: usize, inner: R) -> RollBuf<R> {
+        Self::with_max_capacity(cap, DEFAULT_MAX_CAPACITY, inner)
+    }
+
+    /// Create a buffer of the capacity `cap`, which grows up to `max_cap`, if nothing is consumed
+    /// before it is rolled. The capacity is at most `max_cap`.
+    pub fn with_max_capacity(cap: usize, max_cap: usize, inner: R) -> RollBuf<R> {
+        let cap = cmp::min(cap, max_cap);
         RollBuf {
             inner ,
             buf: vec![0; cap],
@@ -28,7 +36,7 @@ impl<R: io::Read> RollBuf<R> {
             end: 0,
             aux: vec![0; cap],
             is_rolled: false,
-            max_cap: 1 << 24,
+            max_cap,
         }
     }
 
@@ -37,20 +45,32 @@ impl<R: io::Read> RollBuf<R> {
         self.buf.len()
     }
 
+    #[inline]
+    pub fn max_capacity(&self) -> usize {
+        self.max_cap
+    }
+
+    /// Test if the buffer can grow, i.e. it has not reached the maximum capacity yet.
+    #[inline]
+    pub fn can_grow(&self) -> bool {
+        self.buf.len() < self.max_cap
+    }
+
     #[inline]
     pub fn end_position(&self) -> usize {
         self.end
     }
 
-    pub fn fill_buf(&mut self) -> Result<bool, Box<Error>> {
+    pub fn fill_buf(&mut self) -> io::Result<bool> {
         if self.pos >= self.end {
             debug_assert!(self.pos == self.end);
-            self.end = self.inner.read(&mut self.buf)?;
             self.pos = 0;
+            self.end = 0;
+            self.read_to_end_of_buf()?;
         }
 
         if self.is_rolled {
-            self.end += self.inner.read(&mut self.buf[self.end..])?;
+            self.read_to_end_of_buf()?;
             self.is_rolled = false;
         }
 
@@ -59,6 +79,20 @@ impl<R: io::Read> RollBuf<R> {
         Ok(is_full)
     }
 
+    // A short read does not mean the end of the input, e.g. a pipe returns the data as they
+    // arrive. Read until the buffer is full or the inner reader returns zero bytes.
+    fn read_to_end_of_buf(&mut self) -> io::Result<()> {
+        while self.end < self.buf.len() {
+            match self.inner.read(&mut self.buf[self.end..]) {
+                Ok(0) => break,
+                Ok(n) => self.end += n,
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
     pub fn consume(&mut self, n: usize) {
         self.pos = cmp::min(self.pos + n, self.end);
     }
@@ -73,7 +107,7 @@ impl<R: io::Read> RollBuf<R> {
             self.end = new_end;
         } else {
             let old_len = self.buf.len();
-            let reserved = cmp::min(old_len, self.max_cap - old_len);
+            let reserved = cmp::min(old_len, self.max_cap.saturating_sub(old_len));
             self.buf.reserve(reserved);
             self.buf.extend((0..reserved).map(|_| 0));
         }
@@ -93,6 +127,28 @@ impl<R: io::Read> RollBuf<R> {
 #[cfg(test)]
 mod tests {
     use super::{RollBuf};
+    use std::io;
+
+    // returns at most one byte per read and an interrupt before each byte
+    struct Trickle<'a> {
+        data: &'a [u8],
+        interrupt: bool,
+    }
+
+    impl<'a> io::Read for Trickle<'a> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.interrupt = !self.interrupt;
+            if self.interrupt {
+                return Err(io::Error::new(io::ErrorKind::Interrupted, "interrupted"));
+            }
+            if self.data.is_empty() || buf.is_empty() {
+                return Ok(0);
+            }
+            buf[0] = self.data[0];
+            self.data = &self.data[1..];
+            Ok(1)
+        }
+    }
 
     #[test]
     fn test_rollbuf_no_extend() {
@@ -153,6 +209,53 @@ mod tests {
             assert_eq!((contents, is_full), (t.want.0.as_slice(), t.want.1));
         }
     }
-}
 
+    #[test]
+    fn test_rollbuf_max_capacity() {
+        let inner: &[u8] = &[1, 2, 3, 4, 5, 6, 7];
+        let mut b = RollBuf::with_max_capacity(3, 5, inner);
+
+        assert!(b.fill_buf().unwrap());
+        assert!(b.can_grow());
+        b.roll();
+        assert!(b.fill_buf().unwrap());
+        assert_eq!((b.contents(), b.capacity()), (&[1, 2, 3, 4, 5][..], 5));
+        assert!(!b.can_grow());
+        // the buffer does not grow beyond the maximum capacity
+        b.roll();
+        assert!(b.fill_buf().unwrap());
+        assert_eq!((b.contents(), b.capacity()), (&[1, 2, 3, 4, 5][..], 5));
+
+        let b = RollBuf::with_max_capacity(8, 5, inner);
+        assert_eq!(b.capacity(), 5);
+    }
 
+    #[test]
+    fn test_rollbuf_short_reads() {
+        let inner = Trickle { data: &[1, 2, 3, 4, 5, 6, 7], interrupt: false };
+        let mut b = RollBuf::with_capacity(3, inner);
+
+        struct TestCase {
+            consume: usize,
+            roll: bool,
+            want: (Vec<u8>, bool),
+        }
+
+        let test_cases = vec![
+            TestCase { consume: 0, roll: false, want: (vec![1, 2, 3],           true)  },
+            TestCase { consume: 0, roll: true,  want: (vec![1, 2, 3, 4, 5, 6],  true)  },
+            TestCase { consume: 6, roll: false, want: (vec![7],                 false) },
+            TestCase { consume: 1, roll: false, want: (vec![],                  false) },
+        ];
+
+        for t in test_cases {
+            b.consume(t.consume);
+            if t.roll {
+                b.roll();
+            }
+            let is_full = b.fill_buf().unwrap();
+            let contents = b.contents();
+            assert_eq!((contents, is_full), (t.want.0.as_slice(), t.want.1));
+        }
+    }
+}
@@ -1,12 +1,25 @@
 use std::path::{Path, PathBuf,};
 use std::error::Error;
-use clap::{App, Arg, ArgGroup, };
+use clap::{App, Arg, ArgGroup, ArgMatches, };
+use rjoin::join::{AsofOptions, BuildSide, Direction, JoinOptions,};
+use rjoin::printer::{Column, Indicator, parse_columns,};
+use rjoin::csv::compare::{Compare, Kind, parse_compare,};
+use rjoin::csv::basic::Field;
+use rjoin::sort::DEFAULT_MEM_LIMIT;
+use rjoin::spill::DEFAULT_SPILL_THRESHOLD;
+use rollbuf::DEFAULT_MAX_CAPACITY;
+use rjoin::compress::{Compression, parse_compression,};
+use csvroll::writer::QuoteStyle;
+use csvroll::error::Error as CsvError;
+use std::cmp;
+use std::env;
+use std::process;
 
 pub fn app() -> App<'static, 'static> {
     App::new("rjoin")
         .author(crate_authors!())
         .version(crate_version!())
-        .about("joins lines of two files with identical join fields.")
+        .about("joins lines of two or more files with identical join fields.")
         .arg(Arg::with_name("show_left")
                  .short("l")
                  .long("show-left")
@@ -22,6 +35,69 @@ pub fn app() -> App<'static, 'static> {
         .group(ArgGroup::with_name("show_any")
                         .args(&["show_left", "show_right", "show_both"])
                         .multiple(true))
+        .arg(Arg::with_name("semi")
+                 .long("semi")
+                 .takes_value(true)
+                 .value_name("SIDE")
+                 .possible_values(&["left", "right"])
+                 .conflicts_with_all(&["show_any", "anti", "indicator"])
+                 .help("print the lines from the SIDE file which have a match, each once")
+                 .long_help(
+"print the lines from the SIDE file which have a match in the other file, i.e. 'left' or
+'right'. Each line is printed once as it is, no matter how many lines of the other file
+match it, unless '--columns' is given."))
+        .arg(Arg::with_name("anti")
+                 .long("anti")
+                 .takes_value(true)
+                 .value_name("SIDE")
+                 .possible_values(&["left", "right"])
+                 .conflicts_with("show_any")
+                 .help("print the lines from the SIDE file which have no match, as they are")
+                 .long_help(
+"print the lines from the SIDE file which have no match in the other file, i.e. 'left' or
+'right'. Unlike '--show-left' or '--show-right', the lines are printed as they are, the key
+fields are not moved first, unless '--columns' is given."))
+        .arg(Arg::with_name("asof")
+                 .long("asof")
+                 .takes_value(true)
+                 .min_values(0)
+                 .require_equals(true)
+                 .value_name("DIRECTION")
+                 .possible_values(&["backward", "forward", "nearest"])
+                 .conflicts_with_all(&["show_right", "semi", "anti", "unsorted"])
+                 .help("pair each left line with the right line with the nearest last key field")
+                 .long_help(
+"pair each left line with one right line, whose last key field is the nearest to the left
+one in DIRECTION, i.e. an as-of join. The preceding key fields must be equal. DIRECTION is
+one of:
+    backward    the last right line with the key not after the left key, the default
+    forward     the first right line with the key not before the left key
+    nearest     the nearer one of both, the backward one on a tie
+The last key field of the right line is printed with its other fields. The unmatched left
+lines are printed with '--show-left'."))
+        .arg(Arg::with_name("tolerance")
+                 .long("tolerance")
+                 .takes_value(true)
+                 .value_name("DISTANCE")
+                 .requires("asof")
+                 .help("pair only the lines with the last key fields at most DISTANCE apart")
+                 .long_help(
+"pair only the lines, whose last key fields are at most DISTANCE apart in the as-of join.
+The last key field must be compared as a number, e.g. '-k id,time:n'."))
+        .arg(Arg::with_name("interval")
+                 .long("interval")
+                 .takes_value(true)
+                 .value_delimiter(",")
+                 .value_name("START,END")
+                 .conflicts_with_all(&["right_key", "asof", "unsorted"])
+                 .help("join the left key with the right lines, whose fields START to END contain it")
+                 .long_help(
+"join each left line with the right lines, whose interval from the field START to the field
+END contains the left key, both ends included. The left key must be a single field and the
+right file must be sorted by START. The right lines are printed whole, START included, after
+the left key and the other left fields. START and END are
+given like the key fields, e.g. '2n,3n', they are compared the same way as the left key. The
+intervals may overlap, the unmatched right lines are printed once their interval ends."))
         .arg(Arg::with_name("header")
                  .long("header")
                  .help("treat the first line in each file as field headers, print them without trying to pair them"))
@@ -44,7 +120,15 @@ pub fn app() -> App<'static, 'static> {
                  .help("join on these comma-separated fields in the left file")
                  .long_help(
 "join on these comma-separated fields in the left file. The index 
-starts with one and must not contain duplicates. The default is 1."))
+starts with one and must not contain duplicates. The default is 1.
+A field may be followed by 'n' to compare it as a decimal number, or
+by 'g' to compare it as a floating-point number, e.g. '1n,2'. Add 'f'
+to ignore the case of ASCII letters, 'u' to ignore the case of all
+Unicode letters and 't' to ignore the leading and trailing whitespace,
+e.g. '1ft'. The records are printed unchanged. Add 'r' if the
+file is sorted by the field in descending order, e.g. '1r,2'.
+With '--header', the fields may be given by their names in the
+header, the suffix follows a colon, e.g. 'customer_id,amount:n'."))
         .arg(Arg::with_name("right_key")
                  .long("right-key")
                  .requires("left_key")
@@ -55,7 +139,15 @@ starts with one and must not contain duplicates. The default is 1."))
                  .help("join on these comma-separated fields in the right file")
                  .long_help(
 "join on these comma-separated fields in the right file. The index 
-starts with one and must not contain duplicates. The default is 1."))
+starts with one and must not contain duplicates. The default is 1.
+A field may be followed by 'n' to compare it as a decimal number, or
+by 'g' to compare it as a floating-point number, e.g. '1n,2'. Add 'f'
+to ignore the case of ASCII letters, 'u' to ignore the case of all
+Unicode letters and 't' to ignore the leading and trailing whitespace,
+e.g. '1ft'. The records are printed unchanged. Add 'r' if the
+file is sorted by the field in descending order, e.g. '1r,2'.
+With '--header', the fields may be given by their names in the
+header, the suffix follows a colon, e.g. 'customer_id,amount:n'."))
         .arg(Arg::with_name("delimiter")
                  .long("delimiter")
                  .short("d")
@@ -130,69 +222,524 @@ starts with one and must not contain duplicates. The default is 1."))
                  .help("use CHAR as output record terminator")
                  .long_help(
 "use CHAR as output record terminator. It must be 1 byte long in utf-8."))
+        .arg(Arg::with_name("quote")
+                 .long("quote")
+                 .short("q")
+                 .takes_value(true)
+                 .value_name("CHAR")
+                 .help("treat the fields enclosed in CHAR as quoted")
+                 .long_help(
+"treat the fields enclosed in CHAR as quoted according to RFC 4180. The field delimiters
+and the record terminators inside the quoted fields are not interpreted and the enclosing
+quotes are stripped. It must be 1 byte long in utf-8."))
+        .arg(Arg::with_name("out_quote")
+                 .long("out-quote")
+                 .takes_value(true)
+                 .value_name("CHAR")
+                 .help("enclose the output fields in CHAR where required")
+                 .long_help(
+"enclose the output fields in CHAR where required by '--quote-style'. The default is the
+quote given by '--quote' or '\"'. It must be 1 byte long in utf-8."))
+        .arg(Arg::with_name("quote_style")
+                 .long("quote-style")
+                 .takes_value(true)
+                 .value_name("STYLE")
+                 .possible_values(&["necessary", "always", "never", "non-numeric"])
+                 .help("quote the output fields according to STYLE")
+                 .long_help(
+"quote the output fields according to STYLE, one of:
+    necessary    the fields containing the delimiter, the terminator, the quote or a line
+                 break (the default)
+    always       all the fields
+    never        no fields, they are printed as they are
+    non-numeric  the fields which are not numbers and the necessary fields
+The quotes inside the quoted fields are doubled, unless '--escape' is given."))
+        .arg(Arg::with_name("escape")
+                 .long("escape")
+                 .takes_value(true)
+                 .value_name("CHAR")
+                 .help("escape the quotes inside the output fields with CHAR")
+                 .long_help(
+"escape the quotes and CHAR itself inside the quoted output fields with CHAR instead of
+doubling the quotes, e.g. '--escape=\\'. It must be 1 byte long in utf-8."))
+        .arg(Arg::with_name("unsorted")
+                 .long("unsorted")
+                 .help("do not require the inputs to be sorted by the key")
+                 .long_help(
+"do not require the inputs to be sorted by the key. The records of one input are loaded
+into the memory and the records of the other input are streamed through them. By default,
+the smaller file is loaded, see also '--build-side'."))
+        .arg(Arg::with_name("build_side")
+                 .long("build-side")
+                 .takes_value(true)
+                 .value_name("SIDE")
+                 .possible_values(&["left", "right"])
+                 .requires("unsorted")
+                 .help("load the records of SIDE into the memory when joining unsorted inputs"))
+        .arg(Arg::with_name("columns")
+                 .long("columns")
+                 .short("o")
+                 .takes_value(true)
+                 .value_name("SPEC")
+                 .help("print the columns given by SPEC, e.g. '0,1.3,2.2'")
+                 .long_help(
+"print the comma-separated columns given by SPEC instead of the key fields followed by the
+non-key fields. Each column is one of:
+    0       the key fields
+    F.N     the field N of the file F, e.g. '1.3' is the third field of the left file
+    F.N-M   the fields N through M of the file F
+    F.N-    the fields N through the last field of the file F
+    F.*     the non-key fields of the file F, which are not selected by any other column
+The columns of the missing file in the unmatched records are printed empty."))
+        .arg(Arg::with_name("empty")
+                 .long("empty")
+                 .short("e")
+                 .takes_value(true)
+                 .value_name("VALUE")
+                 .help("pad the unmatched lines with VALUE to the same number of fields")
+                 .long_help(
+"pad the unmatched lines with VALUE, so that they have the same number of fields as the
+matched lines. The number of fields of each file is taken from its first line. With
+'--columns', VALUE also replaces the missing fields."))
+        .arg(Arg::with_name("indicator")
+                 .long("indicator")
+                 .takes_value(true)
+                 .min_values(0)
+                 .require_equals(true)
+                 .value_name("NAME")
+                 .help("add a column telling which file each line comes from")
+                 .long_help(
+"add a column telling which file each line comes from, i.e. 'left_only', 'right_only' or
+'both'. NAME is the name of the column in the header, '_merge' by default. The column is
+the last one, unless '--indicator-first' is given. The fields missing from the unmatched
+lines are empty, unless '--empty' is given."))
+        .arg(Arg::with_name("indicator_labels")
+                 .long("indicator-labels")
+                 .takes_value(true)
+                 .value_name("LEFT,RIGHT,BOTH")
+                 .requires("indicator")
+                 .help("label the lines in the indicator column with LEFT, RIGHT and BOTH"))
+        .arg(Arg::with_name("indicator_first")
+                 .long("indicator-first")
+                 .requires("indicator")
+                 .help("print the indicator column first"))
+        .arg(Arg::with_name("sort")
+                 .long("sort")
+                 .conflicts_with("unsorted")
+                 .help("sort the inputs by the key before joining them")
+                 .long_help(
+"sort the inputs by the key before joining them. The records exceeding the memory limit
+are sorted into temporary files, which are merged afterwards. The key fields are compared
+the same way the join compares them."))
+        .arg(Arg::with_name("sort_memory")
+                 .long("sort-memory")
+                 .takes_value(true)
+                 .value_name("SIZE")
+                 .requires("sort")
+                 .help("use at most SIZE bytes of memory per input when sorting")
+                 .long_help(
+"use at most SIZE bytes of memory per input when sorting. SIZE may be followed by one of
+the suffixes K, M or G. The default is 256M."))
+        .arg(Arg::with_name("temp_dir")
+                 .long("temp-dir")
+                 .takes_value(true)
+                 .value_name("DIR")
+                 .help("write the temporary files into DIR")
+                 .long_help(
+"write the temporary files of the sort and of the spilled groups into DIR. The default is
+the system temporary directory."))
+        .arg(Arg::with_name("group_memory")
+                 .long("group-memory")
+                 .takes_value(true)
+                 .value_name("SIZE")
+                 .help("spill the groups of the records with the same key larger than SIZE bytes to the disk")
+                 .long_help(
+"spill the groups of the records with the same key larger than SIZE bytes into temporary
+files, which are replayed for each matching record of the other input. SIZE may be followed
+by one of the suffixes K, M or G and must be less than '--max-buffer'. The default is 4M."))
+        .arg(Arg::with_name("max_buffer")
+                 .long("max-buffer")
+                 .takes_value(true)
+                 .value_name("SIZE")
+                 .help("buffer at most SIZE bytes of an input")
+                 .long_help(
+"buffer at most SIZE bytes of an input. A record, or a group of the records with the same
+key, larger than SIZE is an error. SIZE may be followed by one of the suffixes K, M or G.
+The default is 16M."))
+        .arg(Arg::with_name("no_mmap")
+                 .long("no-mmap")
+                 .help("read the input files instead of mapping them into the memory")
+                 .long_help(
+"read the input files instead of mapping them into the memory. The regular uncompressed
+files are mapped by default, which saves copying their contents, but the result is undefined
+if a file is modified during the join."))
+        .arg(Arg::with_name("threads")
+                 .long("threads")
+                 .takes_value(true)
+                 .value_name("N")
+                 .help("use up to N threads")
+                 .long_help(
+"use up to N threads. The inputs are parsed on threads of their own, in their order, while
+there are threads left, then the output is formatted, written and compressed on another one.
+The join always runs on the main thread and the output is the same as with one thread. The
+default is 1."))
+        .arg(Arg::with_name("output_format")
+                 .long("output-format")
+                 .takes_value(true)
+                 .value_name("FORMAT")
+                 .possible_values(&["csv", "jsonl"])
+                 .conflicts_with("columns")
+                 .help("print the output in FORMAT, one of 'csv' or 'jsonl'")
+                 .long_help(
+"print the output in FORMAT, one of 'csv' or 'jsonl'. The default is csv. The jsonl
+format prints one JSON object per line, the fields are named by the header, if it is
+given by '--header', or by the file and the field number, e.g. 'left_2'. The fields
+of the missing file in the outer join are null."))
+        .arg(Arg::with_name("output_compression")
+                 .long("output-compression")
+                 .takes_value(true)
+                 .value_name("FORMAT")
+                 .help("compress the output using FORMAT, one of 'gzip', 'zstd' or 'bzip2'")
+                 .long_help(
+"compress the output using FORMAT, one of 'gzip', 'zstd' or 'bzip2'. The compressed
+inputs are detected and decompressed automatically."))
+        .arg(Arg::with_name("input_key")
+                 .long("input-key")
+                 .takes_value(true)
+                 .multiple(true)
+                 .number_of_values(1)
+                 .value_name("FILE:FIELDS")
+                 .help("join on these comma-separated fields in the FILE-th file")
+                 .long_help(
+"join on these comma-separated fields in the FILE-th file, e.g. '--input-key=3:2,1'
+joins the third file on its second and first field. The files and the fields are
+numbered from one. It may be repeated and it overrides the other key parameters."))
+        .arg(Arg::with_name("require")
+                 .long("require")
+                 .takes_value(true)
+                 .min_values(1)
+                 .value_delimiter(",")
+                 .require_delimiter(true)
+                 .value_name("FILES")
+                 .help("print only the lines present in all of these comma-separated files")
+                 .long_help(
+"print only the lines present in all of these comma-separated files, when joining more
+than two files. The files are numbered from one, e.g. '--require=1' keeps all the lines
+of the first file (left outer join). The default is all the files (inner join). An empty
+list prints all the lines (full outer join)."))
         .arg(Arg::with_name("LEFT_FILE")
-                 .help("the left input file")
+                 .help("the left input file, '-' reads the standard input")
                  .required(true)
                  .index(1))
         .arg(Arg::with_name("RIGHT_FILE")
-                 .help("the right input file")
+                 .help("the right input file, '-' reads the standard input")
                  .required(true)
                  .index(2))
+        .arg(Arg::with_name("MORE_FILES")
+                 .help("more input files joined with the left and the right file")
+                 .multiple(true)
+                 .index(3))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Csv,
+    Jsonl,
 }
 
 pub struct Args {
-    left_path: PathBuf,
-    right_path: PathBuf,
-    show_left: bool,
-    show_right: bool,
-    show_both: bool,
-    left_key: Vec<usize>,
-    right_key: Vec<usize>,
-    in_left_delimiter: u8,
-    in_right_delimiter: u8,
+    paths: Vec<PathBuf>,
+    join_options: JoinOptions,
+    asof: Option<AsofOptions>,
+    interval: bool,
+    required: Vec<bool>,
+    keys: Vec<Vec<Field>>,
+    compare: Vec<Compare>,
+    in_delimiters: Vec<u8>,
     out_delimiter: u8,
-    in_left_terminator: u8,
-    in_right_terminator: u8,
+    in_terminators: Vec<u8>,
     out_terminator: u8,
+    quote: Option<u8>,
+    out_quote: u8,
+    quote_style: QuoteStyle,
+    escape: Option<u8>,
     header: bool,
+    unsorted: bool,
+    build_side: Option<BuildSide>,
+    columns: Option<Vec<Column>>,
+    empty: Option<Vec<u8>>,
+    indicator: Option<Indicator>,
+    sort: bool,
+    sort_memory: usize,
+    temp_dir: PathBuf,
+    group_memory: usize,
+    max_buffer: usize,
+    threads: usize,
+    mmap: bool,
+    output_format: OutputFormat,
+    output_compression: Option<Compression>,
 }
 
 impl Args {
-    pub fn parse() -> Result<Args, Box<Error>> {
-        let matches = app().get_matches();
+    pub fn parse() -> Result<Args, Box<dyn Error>> {
+        let matches = match app().get_matches_safe() {
+            Ok(m) => m,
+            // the help and the version are not errors
+            Err(ref e) if !e.use_stderr() => e.exit(),
+            Err(e) => {
+                eprintln!("{}", e.message);
+                process::exit(CsvError::invalid_argument(e.message).exit_code());
+            }
+        };
+        Self::from_matches(&matches)
+    }
 
-        let left_path = matches.value_of("LEFT_FILE").ok_or("expected LEFT_FILE")?;
-        let right_path = matches.value_of("RIGHT_FILE").ok_or("expected RIGHT_FILE")?;
+    fn from_matches(matches: &ArgMatches) -> Result<Args, Box<dyn Error>> {
+        let left_path = matches.value_of_os("LEFT_FILE").ok_or("expected LEFT_FILE")?;
+        let right_path = matches.value_of_os("RIGHT_FILE").ok_or("expected RIGHT_FILE")?;
+        let mut paths: Vec<PathBuf> = vec![left_path.into(), right_path.into()];
+        if let Some(more) = matches.values_of_os("MORE_FILES") {
+            paths.extend(more.map(|p| p.into()));
+        }
+        // the direction of '--asof' is given after '=', otherwise it is parsed as a file
+        let asof_direction = match matches.value_of("asof") {
+            Some(d) => Some(d.to_owned()),
+            None if paths.len() > 2 => take_asof_direction(&mut paths),
+            None => None,
+        };
+        if paths.iter().filter(|p| is_stdin(p)).count() > 1 {
+            return Err("only one input can be read from the standard input".into());
+        }
 
         let show_left = matches.is_present("show_left");
         let show_right = matches.is_present("show_right");
         let show_both = !matches.is_present("show_any") || matches.is_present("show_both");
+        // the input filtered by a semi-join or an anti-join, which is printed as it is
+        let filtered = match (matches.value_of("semi"), matches.value_of("anti")) {
+            (Some(side), _) | (_, Some(side)) => {
+                if paths.len() > 2 {
+                    return Err("'--semi' and '--anti' are supported only when joining two files".into());
+                }
+                Some(side == "left")
+            }
+            (None, None) => None,
+        };
+        let join_options = match (filtered, matches.is_present("semi")) {
+            (Some(is_left), true) => JoinOptions::semi(is_left),
+            (Some(is_left), false) => JoinOptions::anti(is_left),
+            // the as-of join pairs every left record it can, which the header reflects
+            (None, _) if matches.is_present("asof") => JoinOptions::from_options(show_left, false, true),
+            (None, _) => JoinOptions::from_options(show_left, show_right, show_both),
+        };
+
+        let mut asof = if matches.is_present("asof") {
+            if paths.len() > 2 {
+                return Err("'--asof' is supported only when joining two files, \
+                            the direction is given as '--asof=DIRECTION'".into());
+            }
+            let direction = match asof_direction.as_deref() {
+                Some("forward") => Direction::Forward,
+                Some("nearest") => Direction::Nearest,
+                _ => Direction::Backward,
+            };
+            Some(AsofOptions::new(direction).with_show_left(show_left))
+        } else {
+            None
+        };
+
+        if paths.len() > 2 && matches.is_present("show_any") {
+            return Err("'--show-left', '--show-right' and '--show-both' are supported only when \
+                        joining two files, use '--require' instead".into());
+        }
+        let required = match matches.values_of("require") {
+            Some(v) => {
+                if paths.len() <= 2 {
+                    return Err("'--require' is supported only when joining more than two files, \
+                                use '--show-left', '--show-right' and '--show-both' instead".into());
+                }
+                let mut required = vec![false; paths.len()];
+                for f in v.filter(|f| !f.is_empty()) {
+                    match f.parse::<usize>() {
+                        Ok(i) if i >= 1 && i <= paths.len() => required[i - 1] = true,
+                        _ => return Err(format!("could not parse the required file '{}'", f).into()),
+                    }
+                }
+                required
+            }
+            None => vec![true; paths.len()],
+        };
 
         let header = matches.is_present("header");
 
-        let key: Vec<usize> = match matches.values_of("key").map(|it| it.collect::<Vec<_>>()) {
+        let unsorted = matches.is_present("unsorted");
+        let build_side = match matches.value_of("build_side") {
+            Some("left") => Some(BuildSide::Left),
+            Some("right") => Some(BuildSide::Right),
+            Some(_) => return Err("the build side must be either 'left' or 'right'".into()),
+            None => None,
+        };
+
+        let columns = match (matches.value_of("columns"), filtered) {
+            (Some(spec), _) => Some(parse_columns(spec, paths.len())?),
+            (None, Some(is_left)) => {
+                let input = if is_left { 0 } else { 1 };
+                Some(vec![Column::Fields { input, start: 0, end: None }])
+            }
+            (None, None) => None,
+        };
+
+        let empty = matches.value_of("empty").map(|s| s.as_bytes().to_owned());
+        let indicator = if matches.is_present("indicator") {
+            let name = matches.value_of("indicator").unwrap_or("_merge");
+            let mut indicator = Indicator::new(name.as_bytes().to_owned())
+                .with_first(matches.is_present("indicator_first"));
+            if let Some(labels) = matches.value_of("indicator_labels") {
+                let labels: Vec<&str> = labels.split(',').collect();
+                if labels.len() != 3 {
+                    return Err("expected three comma-separated indicator labels LEFT,RIGHT,BOTH".into());
+                }
+                indicator = indicator.with_labels(
+                    labels[0].as_bytes().to_owned(),
+                    labels[1].as_bytes().to_owned(),
+                    labels[2].as_bytes().to_owned(),
+                );
+            }
+            Some(indicator)
+        } else {
+            None
+        };
+
+        let sort = matches.is_present("sort");
+        let sort_memory = match matches.value_of("sort_memory") {
+            Some(s) => parse_size(s)?,
+            None => DEFAULT_MEM_LIMIT,
+        };
+        let temp_dir = match matches.value_of_os("temp_dir") {
+            Some(d) => d.into(),
+            None => env::temp_dir(),
+        };
+        let max_buffer = match matches.value_of("max_buffer") {
+            Some(s) => parse_size(s)?,
+            None => DEFAULT_MAX_CAPACITY,
+        };
+        if max_buffer == 0 {
+            return Err("the maximum buffer size must be greater than zero".into());
+        }
+        let group_memory = match matches.value_of("group_memory") {
+            Some(s) => parse_size(s)?,
+            None => cmp::min(DEFAULT_SPILL_THRESHOLD, max_buffer / 2),
+        };
+        if group_memory >= max_buffer {
+            return Err("the group memory size must be less than the maximum buffer size".into());
+        }
+        let threads = match matches.value_of("threads") {
+            Some(s) => s.parse::<usize>()
+                        .ok()
+                        .filter(|&n| n > 0)
+                        .ok_or_else(|| format!("the number of threads must be a positive integer, got '{}'", s))?,
+            None => 1,
+        };
+        let output_format = match matches.value_of("output_format") {
+            Some("jsonl") => OutputFormat::Jsonl,
+            _ => OutputFormat::Csv,
+        };
+        // the filtered lines are printed as they are, which only the csv format does
+        if output_format == OutputFormat::Jsonl && filtered.is_some() {
+            return Err("'--semi' and '--anti' cannot be used with '--output-format jsonl'".into());
+        }
+        let output_compression = match matches.value_of("output_compression") {
+            Some(name) => Some(parse_compression(name)?),
+            None => None,
+        };
+
+        let key: (Vec<Field>, Vec<Compare>) = match matches.values_of("key")
+                                                           .map(|it| it.collect::<Vec<_>>()) {
             Some(v) => validate_key(v, "")?,
-            None => vec![0],
+            None => (vec![Field::Index(0)], vec![Compare::default()]),
         };
-        let left_key: Vec<usize> = match matches.values_of("left_key")
-                                                .map(|it| it.collect::<Vec<_>>()) {
+        let left_key: (Vec<Field>, Vec<Compare>) = match matches.values_of("left_key")
+                                                                .map(|it| it.collect::<Vec<_>>()) {
             Some(v) => validate_key(v, "left ")?,
             None => key.clone(),
         };
-        let right_key: Vec<usize> = match matches.values_of("right_key")
-                                                 .map(|it| it.collect::<Vec<_>>()) {
+        let right_key: (Vec<Field>, Vec<Compare>) = match matches.values_of("right_key")
+                                                                 .map(|it| it.collect::<Vec<_>>()) {
             Some(v) => validate_key(v, "right ")?,
             None => key.clone(),
         };
 
-        if left_key.len() != right_key.len() {
-            return Err("the left key and the right key parameters have different lenght".into());
+        let mut keys: Vec<(Vec<Field>, Vec<Compare>)> = vec![key.clone(); paths.len()];
+        keys[0] = left_key;
+        keys[1] = right_key;
+        if let Some(v) = matches.values_of("input_key") {
+            for k in v {
+                let mut it = k.splitn(2, ':');
+                let i = match it.next().and_then(|i| i.parse::<usize>().ok()) {
+                    Some(i) if i >= 1 && i <= keys.len() => i - 1,
+                    _ => return Err(format!("could not parse the input key parameter '{}'", k).into()),
+                };
+                let fields = it.next().ok_or_else(|| {
+                    format!("could not parse the input key parameter '{}'", k)
+                })?;
+                keys[i] = validate_key(fields.split(',').collect(), "input ")?;
+            }
+        }
+
+        let interval = matches.is_present("interval");
+        let (keys, compare) = match matches.values_of("interval").map(|it| it.collect::<Vec<_>>()) {
+            // the interval is the key of the right file, its ends are compared like the left key
+            Some(v) => {
+                if paths.len() > 2 {
+                    return Err("'--interval' is supported only when joining two files".into());
+                }
+                let (fields, cmp) = validate_key(v, "interval ")?;
+                if fields.len() != 2 {
+                    return Err("the interval must be given by the start field and the end field".into());
+                }
+                if keys[0].0.len() != 1 {
+                    return Err("the left key must be a single field with '--interval'".into());
+                }
+                let compare = merge_compare(&[keys[0].1.clone(), cmp[..1].to_vec(), cmp[1..].to_vec()])?;
+                (vec![keys[0].0.clone(), fields], compare)
+            }
+            None => {
+                if keys[0].0.len() != keys[1].0.len() {
+                    return Err("the left key and the right key parameters have different lenght".into());
+                }
+                if keys.iter().any(|k| k.0.len() != keys[0].0.len()) {
+                    return Err("the key parameters of the input files have different length".into());
+                }
+                let (keys, compares): (Vec<_>, Vec<_>) = keys.into_iter().unzip();
+                let compare = merge_compare(&compares)?;
+                (keys, compare)
+            }
+        };
+        // the distance of the as-of keys is known only for the numbers
+        let is_numeric = matches!(compare.last().map(|c| c.kind()), Some(Kind::Numeric) | Some(Kind::General));
+        if let (Some(a), Some(tolerance)) = (asof.as_mut(), matches.value_of("tolerance")) {
+            if !is_numeric {
+                return Err("'--tolerance' requires the last key field to be compared as a number".into());
+            }
+            match tolerance.parse::<f64>() {
+                Ok(t) if t >= 0.0 => *a = a.with_tolerance(t),
+                _ => return Err(format!("could not parse the tolerance '{}'", tolerance).into()),
+            }
+        }
+        if matches.value_of("asof") == Some("nearest") && !is_numeric {
+            return Err("'--asof=nearest' requires the last key field to be compared as a number".into());
+        }
+        let is_name = |f: &Field| match *f {
+            Field::Name(_) => true,
+            Field::Index(_) => false,
+        };
+        if !header && keys.iter().flatten().any(is_name) {
+            return Err("the key fields can be given by name only with '--header'".into());
         }
 
         let delimiter = match matches.value_of("delimiter")
                                      .map(|s| s.as_bytes()) {
             Some(b) => {
-                if b.len() > 1 {
+                if b.len() != 1 {
                     return Err("the field delimiter must be 1 byte long in utf8".into());
                 }
                 b[0]
@@ -202,7 +749,7 @@ impl Args {
         let in_delimiter = match matches.value_of("in_delimiter")
                                         .map(|s| s.as_bytes()) {
             Some(b) => {
-                if b.len() > 1 {
+                if b.len() != 1 {
                     return Err("the input field delimiter must be 1 byte long in utf8".into());
                 }
                 b[0]
@@ -212,7 +759,7 @@ impl Args {
         let out_delimiter = match matches.value_of("out_delimiter")
                                          .map(|s| s.as_bytes()) {
             Some(b) => {
-                if b.len() > 1 {
+                if b.len() != 1 {
                     return Err("the output field delimiter must be 1 byte long in utf8".into());
                 }
                 b[0]
@@ -222,7 +769,7 @@ impl Args {
         let in_left_delimiter = match matches.value_of("in_left_delimiter")
                                              .map(|s| s.as_bytes()) {
             Some(b) => {
-                if b.len() > 1 {
+                if b.len() != 1 {
                     return Err("the left input field delimiter must be 1 byte long in utf8".into());
                 }
                 b[0]
@@ -232,7 +779,7 @@ impl Args {
         let in_right_delimiter = match matches.value_of("in_right_delimiter")
                                              .map(|s| s.as_bytes()) {
             Some(b) => {
-                if b.len() > 1 {
+                if b.len() != 1 {
                     return Err("the right input field delimiter must be 1 byte long in utf8".into());
                 }
                 b[0]
@@ -243,7 +790,7 @@ impl Args {
         let terminator = match matches.value_of("terminator")
                                    .map(|s| s.as_bytes()) {
             Some(b) => {
-                if b.len() > 1 {
+                if b.len() != 1 {
                     return Err("the record terminator must be 1 byte long in utf8".into());
                 }
                 b[0]
@@ -253,7 +800,7 @@ impl Args {
         let in_terminator = match matches.value_of("in_terminator")
                                       .map(|s| s.as_bytes()) {
             Some(b) => {
-                if b.len() > 1 {
+                if b.len() != 1 {
                     return Err("the input record terminator must be 1 byte long in utf8".into());
                 }
                 b[0]
@@ -263,7 +810,7 @@ impl Args {
         let out_terminator = match matches.value_of("out_terminator")
                                        .map(|s| s.as_bytes()) {
             Some(b) => {
-                if b.len() > 1 {
+                if b.len() != 1 {
                     return Err("the output record terminator must be 1 byte long in utf8".into());
                 }
                 b[0]
@@ -273,7 +820,7 @@ impl Args {
         let in_left_terminator = match matches.value_of("in_left_terminator")
                                            .map(|s| s.as_bytes()) {
             Some(b) => {
-                if b.len() > 1 {
+                if b.len() != 1 {
                     return Err("the left input record terminator must be 1 byte long in \
                     utf8".into());
                 }
@@ -284,7 +831,7 @@ impl Args {
         let in_right_terminator = match matches.value_of("in_right_terminator")
                                             .map(|s| s.as_bytes()) {
             Some(b) => {
-                if b.len() > 1 {
+                if b.len() != 1 {
                     return Err("the right input record terminator must be 1 byte long in \
                     utf8".into());
                 }
@@ -293,93 +840,415 @@ impl Args {
             None => in_terminator
         };
 
+        let mut in_delimiters = vec![in_delimiter; paths.len()];
+        in_delimiters[0] = in_left_delimiter;
+        in_delimiters[1] = in_right_delimiter;
+        let mut in_terminators = vec![in_terminator; paths.len()];
+        in_terminators[0] = in_left_terminator;
+        in_terminators[1] = in_right_terminator;
+
+        let quote = match matches.value_of("quote")
+                                 .map(|s| s.as_bytes()) {
+            Some(b) => {
+                if b.len() != 1 {
+                    return Err("the quote must be 1 byte long in utf8".into());
+                }
+                Some(b[0])
+            }
+            None => None
+        };
+        let out_quote = match matches.value_of("out_quote")
+                                     .map(|s| s.as_bytes()) {
+            Some(b) => {
+                if b.len() != 1 {
+                    return Err("the output quote must be 1 byte long in utf8".into());
+                }
+                b[0]
+            }
+            None => quote.unwrap_or(b'"'),
+        };
+        let quote_style = match matches.value_of("quote_style") {
+            Some("always") => QuoteStyle::Always,
+            Some("never") => QuoteStyle::Never,
+            Some("non-numeric") => QuoteStyle::NonNumeric,
+            _ => QuoteStyle::Necessary,
+        };
+        let escape = match matches.value_of("escape")
+                                  .map(|s| s.as_bytes()) {
+            Some(b) => {
+                if b.len() != 1 {
+                    return Err("the escape character must be 1 byte long in utf8".into());
+                }
+                Some(b[0])
+            }
+            None => None
+        };
+
+        if paths.len() > 2 && indicator.is_some() {
+            return Err("'--indicator' is not supported when joining more than two files".into());
+        }
+        if paths.len() > 2 && unsorted {
+            return Err("'--unsorted' is not supported when joining more than two files, \
+                        use '--sort' instead".into());
+        }
 
         let args = Args { 
-            left_path: left_path.into(),
-            right_path: right_path.into(),
-            show_left: show_left,
-            show_right: show_right,
-            show_both: show_both,
-            left_key: left_key,
-            right_key: right_key,
-            in_left_delimiter: in_left_delimiter,
-            in_right_delimiter: in_right_delimiter,
-            out_delimiter: out_delimiter,
-            in_left_terminator: in_left_terminator,
-            in_right_terminator: in_right_terminator,
-            out_terminator: out_terminator,
-            header: header,
+            paths,
+            join_options,
+            asof,
+            interval,
+            required,
+            keys,
+            compare,
+            in_delimiters,
+            out_delimiter,
+            in_terminators,
+            out_terminator,
+            quote,
+            out_quote,
+            quote_style,
+            escape,
+            header,
+            unsorted,
+            build_side,
+            columns,
+            empty,
+            indicator,
+            sort,
+            sort_memory,
+            temp_dir,
+            group_memory,
+            max_buffer,
+            threads,
+            mmap: !matches.is_present("no_mmap"),
+            output_format,
+            output_compression,
         };
         Ok(args)
     }
+    pub fn paths(&self) -> &[PathBuf] {
+        &self.paths
+    }
     pub fn left_path(&self) -> &Path {
-        &self.left_path
+        &self.paths[0]
     }
     pub fn right_path(&self) -> &Path {
-        &self.right_path
+        &self.paths[1]
     }
-    pub fn show_left(&self) -> bool {
-        self.show_left
+    pub fn join_options(&self) -> JoinOptions {
+        self.join_options
     }
-    pub fn show_right(&self) -> bool {
-        self.show_right
+    pub fn asof(&self) -> Option<AsofOptions> {
+        self.asof
     }
-    pub fn show_both(&self) -> bool {
-        self.show_both
+    pub fn interval(&self) -> bool {
+        self.interval
     }
-    pub fn left_key(&self) -> &[usize] {
-        &self.left_key
+    pub fn required(&self) -> &[bool] {
+        &self.required
     }
-    pub fn right_key(&self) -> &[usize] {
-        &self.right_key
+    pub fn keys(&self) -> &[Vec<Field>] {
+        &self.keys
     }
-    pub fn in_left_delimiter(&self) -> u8 {
-        self.in_left_delimiter
+    pub fn compare(&self) -> &[Compare] {
+        &self.compare
     }
-    pub fn in_right_delimiter(&self) -> u8 {
-        self.in_right_delimiter
+    pub fn in_delimiters(&self) -> &[u8] {
+        &self.in_delimiters
     }
     pub fn out_delimiter(&self) -> u8 {
         self.out_delimiter
     }
-    pub fn in_left_terminator(&self) -> u8 {
-        self.in_left_terminator
-    }
-    pub fn in_right_terminator(&self) -> u8 {
-        self.in_right_terminator
+    pub fn in_terminators(&self) -> &[u8] {
+        &self.in_terminators
     }
     pub fn out_terminator(&self) -> u8 {
         self.out_terminator
     }
+    pub fn quote(&self) -> Option<u8> {
+        self.quote
+    }
+    pub fn out_quote(&self) -> u8 {
+        self.out_quote
+    }
+    pub fn quote_style(&self) -> QuoteStyle {
+        self.quote_style
+    }
+    pub fn escape(&self) -> Option<u8> {
+        self.escape
+    }
     pub fn header(&self) -> bool {
         self.header
     }
+    pub fn unsorted(&self) -> bool {
+        self.unsorted
+    }
+    pub fn build_side(&self) -> Option<BuildSide> {
+        self.build_side
+    }
+    pub fn columns(&self) -> Option<&[Column]> {
+        self.columns.as_deref()
+    }
+    pub fn empty(&self) -> Option<&[u8]> {
+        self.empty.as_deref()
+    }
+    pub fn indicator(&self) -> Option<&Indicator> {
+        self.indicator.as_ref()
+    }
+    pub fn sort(&self) -> bool {
+        self.sort
+    }
+    pub fn sort_memory(&self) -> usize {
+        self.sort_memory
+    }
+    pub fn temp_dir(&self) -> &Path {
+        &self.temp_dir
+    }
+    pub fn group_memory(&self) -> usize {
+        self.group_memory
+    }
+    pub fn max_buffer(&self) -> usize {
+        self.max_buffer
+    }
+    pub fn threads(&self) -> usize {
+        self.threads
+    }
+    pub fn mmap(&self) -> bool {
+        self.mmap
+    }
+    pub fn output_format(&self) -> OutputFormat {
+        self.output_format
+    }
+    pub fn output_compression(&self) -> Option<Compression> {
+        self.output_compression
+    }
 }
 
         
-fn validate_key(k: Vec<&str>, which: &str) -> Result<Vec<usize>, Box<Error>> {
-    let out = Ok(k)
-        .map(|v| v.iter().map(|s| s.parse::<usize>())
-                         .collect::<Vec<_>>())
-        .and_then(|v| {
-            let mut out: Vec<usize> = Vec::with_capacity(v.len());
-            for (x, r) in v.iter().enumerate() {
-                match *r {
-                    Ok(i) => out.push(i),
-                    Err(_) => return Err(format!("could not parse the {}key parameter at \
-                                                  the position {}", which, x + 1).into()),
+fn validate_key(k: Vec<&str>, which: &str) -> Result<(Vec<Field>, Vec<Compare>), Box<dyn Error>> {
+    let mut out: Vec<Field> = Vec::with_capacity(k.len());
+    let mut cmp: Vec<Compare> = Vec::with_capacity(k.len());
+    for (x, s) in k.iter().enumerate() {
+        // the field number may be followed by the comparison suffix, e.g. '1n'
+        let digits = s.bytes().take_while(|b| b.is_ascii_digit()).count();
+        if digits > 0 {
+            if let (Ok(i), Some(c)) = (s[..digits].parse::<usize>(), parse_compare(&s[digits..])) {
+                if i < 1 {
+                    return Err("the key fields must use 1-based numbering".into());
                 }
+                out.push(Field::Index(i - 1));
+                cmp.push(c);
+                continue;
             }
-            Ok(out)
-        })
-        .and_then(|mut v| {
-            if v.iter().any(|&i| i < 1) {
-                return Err("the key fields must use 1-based numbering".into());
+        }
+        // otherwise it is a name, the suffix follows the last colon, e.g. 'amount:n'
+        let (name, c) = match s.rfind(':') {
+            Some(p) if p + 1 < s.len() => match parse_compare(&s[(p + 1)..]) {
+                Some(c) => (&s[..p], c),
+                None => (*s, Compare::default()),
+            },
+            _ => (*s, Compare::default()),
+        };
+        if name.is_empty() {
+            return Err(format!("could not parse the {}key parameter at \
+                                the position {}", which, x + 1).into());
+        }
+        out.push(Field::Name(name.to_owned()));
+        cmp.push(c);
+    }
+    Ok((out, cmp))
+}
+
+/// Merge the comparisons of the key fields of all the inputs. The field is compared as bytes,
+/// unless a different kind of comparison is given for any input. The case folding and trimming
+/// given for any input apply to all of them, as well as the descending order.
+fn merge_compare(cmps: &[Vec<Compare>]) -> Result<Vec<Compare>, Box<dyn Error>> {
+    let len = cmps.iter().map(|c| c.len()).max().unwrap_or(0);
+    let mut out = vec![Compare::default(); len];
+    for c in cmps {
+        for (i, c) in c.iter().enumerate() {
+            let kind = match (out[i].kind(), c.kind()) {
+                (k, Kind::Bytes) => k,
+                (Kind::Bytes, k) => k,
+                (k0, k1) if k0 == k1 => k0,
+                _ => return Err(format!("the key parameters have different comparisons at \
+                                         the position {}", i + 1).into()),
+            };
+            out[i] = Compare::new(kind)
+                .with_fold(out[i].fold().max(c.fold()))
+                .with_trim(out[i].trim() || c.trim())
+                .with_reverse(out[i].reverse() || c.reverse());
+        }
+    }
+    Ok(out)
+}
+
+// Take the direction of '--asof' from the files, if it follows the option after a space, e.g.
+// '--asof backward', since the option takes its value only after '='. The as-of join takes two
+// files, so the third one must be the direction.
+fn take_asof_direction(paths: &mut Vec<PathBuf>) -> Option<String> {
+    let directions = ["backward", "forward", "nearest"];
+    let is_direction = |p: &PathBuf| p.to_str().is_some_and(|p| directions.contains(&p));
+    if paths.len() != 3 || paths.iter().filter(|p| is_direction(p)).count() != 1 {
+        return None;
+    }
+    let i = paths.iter().position(is_direction)?;
+    paths.remove(i).into_os_string().into_string().ok()
+}
+
+/// Test if the `path` means the standard input.
+pub fn is_stdin(path: &Path) -> bool {
+    path == Path::new("-")
+}
+
+fn parse_size(s: &str) -> Result<usize, Box<dyn Error>> {
+    let (digits, mul) = match s.as_bytes().last() {
+        Some(b'K') | Some(b'k') => (&s[..s.len() - 1], 1 << 10),
+        Some(b'M') | Some(b'm') => (&s[..s.len() - 1], 1 << 20),
+        Some(b'G') | Some(b'g') => (&s[..s.len() - 1], 1 << 30),
+        _ => (s, 1),
+    };
+    digits.parse::<usize>()
+          .ok()
+          .and_then(|n| n.checked_mul(mul))
+          .ok_or_else(|| format!("could not parse the size '{}'", s).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Args, Box<dyn Error>> {
+        let matches = app().get_matches_from_safe(args)?;
+        Args::from_matches(&matches)
+    }
+
+    #[test]
+    fn test_require() {
+        struct TestCase {
+            args: Vec<&'static str>,
+            want: Vec<bool>,
+        }
+
+        let test_cases = vec![
+            TestCase {
+                args: vec!["rj", "a", "b", "c"],
+                want: vec![true, true, true],
+            },
+            TestCase {
+                args: vec!["rj", "--require", "1", "a", "b", "c"],
+                want: vec![true, false, false],
+            },
+            TestCase {
+                args: vec!["rj", "--require", "1,3", "a", "b", "c"],
+                want: vec![true, false, true],
+            },
+            TestCase {
+                args: vec!["rj", "--require=2", "a", "b", "c"],
+                want: vec![false, true, false],
+            },
+            TestCase {
+                args: vec!["rj", "--require=", "a", "b", "c"],
+                want: vec![false, false, false],
+            },
+        ];
+
+        for t in test_cases {
+            let args = parse(&t.args).unwrap();
+            assert_eq!(args.paths.len(), 3, "{:?}", t.args);
+            assert_eq!(args.required, t.want, "{:?}", t.args);
+        }
+    }
+
+    #[test]
+    fn test_show_many_files() {
+        let test_cases = vec![
+            vec!["rj", "-b", "a", "b", "c"],
+            vec!["rj", "-l", "--require", "1", "a", "b", "c"],
+            vec!["rj", "-b", "--require", "1,2", "a", "b", "c"],
+            vec!["rj", "-r", "--require=", "a", "b", "c"],
+        ];
+
+        for t in test_cases {
+            match parse(&t) {
+                Ok(_) => panic!("expected an error: {:?}", t),
+                Err(e) => assert!(e.to_string().contains("use '--require' instead"), "{:?}: {}", t, e),
             }
-            for i in v.iter_mut() {
-               *i -= 1;
+        }
+    }
+
+    #[test]
+    fn test_filter_output_format() {
+        struct TestCase {
+            args: Vec<&'static str>,
+            want_err: bool,
+        }
+
+        let test_cases = vec![
+            TestCase {
+                args: vec!["rj", "--semi", "left", "--output-format", "csv", "a", "b"],
+                want_err: false,
+            },
+            TestCase {
+                args: vec!["rj", "--anti", "right", "--output-format=csv", "a", "b"],
+                want_err: false,
+            },
+            TestCase {
+                args: vec!["rj", "--semi", "right", "--output-format", "jsonl", "a", "b"],
+                want_err: true,
+            },
+            TestCase {
+                args: vec!["rj", "--anti", "left", "--output-format=jsonl", "a", "b"],
+                want_err: true,
+            },
+        ];
+
+        for t in test_cases {
+            assert_eq!(parse(&t.args).is_err(), t.want_err, "{:?}", t.args);
+        }
+    }
+
+    #[test]
+    fn test_asof_direction() {
+        struct TestCase {
+            args: Vec<&'static str>,
+            want: Option<Direction>,
+        }
+
+        let test_cases = vec![
+            TestCase {
+                args: vec!["rj", "--asof", "a", "b"],
+                want: Some(Direction::Backward),
+            },
+            TestCase {
+                args: vec!["rj", "--asof=forward", "a", "b"],
+                want: Some(Direction::Forward),
+            },
+            TestCase {
+                args: vec!["rj", "--asof", "nearest", "a", "b"],
+                want: Some(Direction::Nearest),
+            },
+            TestCase {
+                args: vec!["rj", "a", "b", "--asof", "forward"],
+                want: Some(Direction::Forward),
+            },
+            TestCase {
+                args: vec!["rj", "--asof", "a", "b", "c"],
+                want: None,
+            },
+        ];
+
+        for t in test_cases {
+            match (parse(&t.args), t.want) {
+                (Ok(args), Some(want)) => {
+                    assert_eq!(args.paths, vec![PathBuf::from("a"), PathBuf::from("b")], "{:?}", t.args);
+                    let want = AsofOptions::new(want);
+                    assert_eq!(format!("{:?}", args.asof), format!("{:?}", Some(want)), "{:?}", t.args);
+                }
+                (Err(e), None) => assert!(e.to_string().contains("'--asof=DIRECTION'"), "{:?}: {}", t.args, e),
+                (Ok(_), None) => panic!("expected an error: {:?}", t.args),
+                (Err(e), Some(_)) => panic!("{:?}: {}", t.args, e),
             }
-            Ok(v)
-        });
-    out
+        }
+    }
 }
@@ -3,61 +3,377 @@ extern crate csvroll;
 extern crate rjoin;
 #[macro_use]
 extern crate clap;
+extern crate memmap2;
 
 mod args;
 
 use std::fs::File;
 use std::error::Error;
-use std::io;
+use std::io::{self, Write,};
+use std::path::{Path, PathBuf,};
+use std::process;
 
-use args::Args;
+use args::{Args, OutputFormat, is_stdin,};
 use rollbuf::RollBuf;
+use memmap2::Mmap;
 use csvroll::index_builder::IndexBuilder;
 use csvroll::parser::Parser;
-use rjoin::join::{JoinOptions, join, head};
-use rjoin::printer::KeyFirst;
-use rjoin::csv::basic::{FirstRec, Group};
+use csvroll::mmap::MmapBuf;
+use csvroll::writer::Writer;
+use csvroll::error::{Error as CsvError, Input, exit_code, is_broken_pipe,};
+use rjoin::join::{MultiJoinOptions, BuildSide, join, join_all, hash_join, asof_join, interval_join, head, head_all,};
+use rjoin::printer::{Print, KeyFirst, Columns, Jsonl,};
+use rjoin::csv::basic::{FirstRec, Group, resolve_key,};
+use rjoin::sort::Sorter;
+use rjoin::spill::Spiller;
+use rjoin::compress::{Encoder, decoder, is_compressed,};
+use rjoin::pipeline::ThreadPrinter;
 
 fn main() {
-    match Args::parse().and_then(run) {
-        Ok(_) => {},
-        Err(e) => eprintln!("error: {}", e),
+    let res = Args::parse()
+        .map_err(|e| CsvError::invalid_argument(e.to_string()).into())
+        .and_then(run);
+    if let Err(e) = res {
+        if is_broken_pipe(&*e) {
+            return;
+        }
+        eprintln!("error: {}", e);
+        process::exit(exit_code(&*e));
     }
 }
 
-fn run(args: Args) -> Result<(), Box<Error>> {
-    const INBUF_CAP: usize = 4 * (1<<12);
-    const OUTBUF_CAP: usize = 4 * (1<<14);
-    let file0 = File::open(args.left_path())?;
-    let file1 = File::open(args.right_path())?;
+const INBUF_CAP: usize = 4 * (1<<12);
+// the initial window into a memory-mapped input, nothing is copied, so it may be larger
+const MMAP_CAP: usize = 1<<20;
+const OUTBUF_CAP: usize = 4 * (1<<14);
+// the number of chunks or blocks in flight between two threads
+const PIPE_BOUND: usize = 4;
 
-    let buf0 = RollBuf::with_capacity(INBUF_CAP, file0);
-    let buf1 = RollBuf::with_capacity(INBUF_CAP, file1);
-    let idx_builder0 = IndexBuilder::new(args.in_left_delimiter(), args.in_left_terminator());
-    let idx_builder1 = IndexBuilder::new(args.in_right_delimiter(), args.in_right_terminator());
-    let parser0 = Parser::from_parts(buf0, idx_builder0);
-    let parser1 = Parser::from_parts(buf1, idx_builder1);
-    let printer = KeyFirst::from_parts(
+type Output = io::BufWriter<Encoder<io::Stdout>>;
+type Reader = Box<dyn io::Read + Send>;
+
+// the opened input, which is either read or mapped into the memory
+enum Opened {
+    Read(Reader),
+    Mmap(Mmap),
+}
+
+fn run(args: Args) -> Result<(), Box<dyn Error>> {
+    let empty = args.empty().map(|e| e.to_owned());
+    let writer = Writer::from_parts(
         args.out_delimiter(),
         args.out_terminator(),
-        args.left_key().to_owned(),
-        args.right_key().to_owned(),
+        args.out_quote(),
+        args.quote_style(),
+        args.escape(),
+        args.quote(),
     );
-    let stdout = io::stdout();
-    let mut out = io::BufWriter::with_capacity(OUTBUF_CAP, stdout.lock());
-    let opts = JoinOptions::from_options(args.show_left(), args.show_right(), args.show_both());
-
-    let (parser0, parser1) = if args.header() {
-        let mut first_rec0 = FirstRec::init(parser0).unwrap();
-        let mut first_rec1 = FirstRec::init(parser1).unwrap();
-        head(&mut first_rec0, &mut first_rec1, &mut out, printer.clone(), opts)?;
-        (first_rec0.into_inner(), first_rec1.into_inner())
+    match (args.output_format(), args.columns()) {
+        (OutputFormat::Jsonl, _) => {
+            let quote = args.quote();
+            let indicator = args.indicator().cloned();
+            print_files(&args, move |keys| {
+                let p = Jsonl::from_keys(keys).with_quote(quote);
+                match indicator {
+                    Some(ind) => p.with_indicator(ind),
+                    None => p,
+                }
+            })?
+        }
+        (OutputFormat::Csv, Some(columns)) => {
+            let columns = columns.to_owned();
+            let indicator = args.indicator().cloned();
+            print_files(&args, move |keys| {
+                let p = Columns::with_writer(writer, keys, columns, empty);
+                match indicator {
+                    Some(ind) => p.with_indicator(ind),
+                    None => p,
+                }
+            })?;
+        }
+        (OutputFormat::Csv, None) => {
+            let indicator = args.indicator().cloned();
+            print_files(&args, move |keys| {
+                let p = KeyFirst::with_writer(writer, keys, empty);
+                match indicator {
+                    Some(ind) => p.with_indicator(ind),
+                    None => p,
+                }
+            })?
+        }
+    }
+    Ok(())
+}
+
+// Join the files and print the output. Once each input has got a thread, the output is formatted
+// and written on another one.
+fn print_files<P, F>(args: &Args, printer: F) -> Result<(), Box<dyn Error>>
+    where P: Print<Output> + Send + 'static,
+          F: FnOnce(Vec<Vec<usize>>) -> P,
+{
+    let encoder = Encoder::new(io::stdout(), args.output_compression())?;
+    let mut out = io::BufWriter::with_capacity(OUTBUF_CAP, encoder);
+    let out = if args.threads() > args.paths().len() + 1 {
+        // the records are only copied on the main thread
+        let printer = join_files(args, &mut io::sink(), |keys| {
+            ThreadPrinter::spawn(printer(keys), out, PIPE_BOUND)
+        })?;
+        printer.finish()?
+    } else {
+        join_files(args, &mut out, printer)?;
+        out
+    };
+    // the compressed output must be finished explicitly
+    out.into_inner().map_err(|e| e.into_error())?.finish()?.flush()?;
+    Ok(())
+}
+
+// The printer is created once the key fields are found in the header, it is returned to be
+// finished.
+fn join_files<W, P, F>(args: &Args, out: &mut W, printer: F) -> Result<P, Box<dyn Error>>
+    where W: io::Write,
+          P: Print<W>,
+          F: FnOnce(Vec<Vec<usize>>) -> P,
+{
+    if args.paths().len() > 2 {
+        return join_many_files(args, out, printer);
+    }
+    let (file0, file0_len) = open(args, args.left_path())?;
+    let (file1, file1_len) = open(args, args.right_path())?;
+
+    let parser0 = parser(args, file0, 0);
+    let parser1 = parser(args, file1, 1);
+    let opts = args.join_options();
+
+    let (parser0, parser1, keys, end1, mut printer) = if args.header() {
+        let mut first_rec0 = FirstRec::init(parser0).map_err(|e| e.with_input(Input::Left))?;
+        let mut first_rec1 = FirstRec::init(parser1).map_err(|e| e.with_input(Input::Right))?;
+        let keys = vec![
+            first_rec0.resolve_key(&args.keys()[0]).map_err(|e| e.with_input(Input::Left))?,
+            first_rec1.resolve_key(&args.keys()[1]).map_err(|e| e.with_input(Input::Right))?,
+        ];
+        let (keys, end1) = split_interval(args, keys);
+        let mut printer = printer(printer_keys(args, &keys));
+        // the printer keeps the number of fields learned from the header
+        head(&mut first_rec0, &mut first_rec1, out, &mut printer, opts)?;
+        (first_rec0.into_inner(), first_rec1.into_inner(), keys, end1, printer)
     } else {
-        (parser0, parser1)
+        let (keys, end1) = split_interval(args, resolve_keys(args)?);
+        let printer = printer(printer_keys(args, &keys));
+        (parser0, parser1, keys, end1, printer)
     };
 
-    let mut group0 = Group::init(parser0, args.left_key().to_owned())?;
-    let mut group1 = Group::init(parser1, args.right_key().to_owned())?;
-    join(&mut group0, &mut group1, &mut out, printer, opts)?;
+    if args.unsorted() {
+        let build = match (args.build_side(), file0_len, file1_len) {
+            (Some(b), _, _) => b,
+            // load the smaller file, the pipes are streamed
+            (None, Some(len0), Some(len1)) if len0 > len1 => BuildSide::Right,
+            (None, None, Some(_)) => BuildSide::Right,
+            (None, _, _) => BuildSide::Left,
+        };
+        hash_join(
+            parser0,
+            parser1,
+            keys[0].clone(),
+            keys[1].clone(),
+            args.compare().to_owned(),
+            build,
+            out,
+            &mut printer,
+            opts,
+        )?;
+        return Ok(printer);
+    }
+
+    if args.sort() {
+        let merge0 = sorter(args, &keys, 0).sort(parser0).map_err(|e| e.with_input(Input::Left))?;
+        let merge1 = sorter(args, &keys, 1).sort(parser1).map_err(|e| e.with_input(Input::Right))?;
+        let parser0 = spawn(args, Parser::from_parts(input_buf(args, merge0), index_builder(args, 0)), 0);
+        let parser1 = spawn(args, Parser::from_parts(input_buf(args, merge1), index_builder(args, 1)), 1);
+        merge_join(parser0, parser1, args, &keys, end1, out, &mut printer)?;
+        return Ok(printer);
+    }
+
+    merge_join(parser0, parser1, args, &keys, end1, out, &mut printer)?;
+    Ok(printer)
+}
+
+fn join_many_files<W, P, F>(args: &Args, out: &mut W, printer: F) -> Result<P, Box<dyn Error>>
+    where W: io::Write,
+          P: Print<W>,
+          F: FnOnce(Vec<Vec<usize>>) -> P,
+{
+    let mut parsers: Vec<Parser<Reader>> = Vec::with_capacity(args.paths().len());
+    for (i, path) in args.paths().iter().enumerate() {
+        let (file, _) = open(args, path)?;
+        parsers.push(parser(args, file, i));
+    }
+    let opts = MultiJoinOptions::from_required(args.required().to_owned());
+
+    let (keys, mut printer) = if args.header() {
+        let mut first_recs = Vec::with_capacity(parsers.len());
+        let mut keys = Vec::with_capacity(parsers.len());
+        for (i, parser) in parsers.into_iter().enumerate() {
+            let mut first_rec = FirstRec::init(parser).map_err(|e| e.with_input(Input::Nth(i)))?;
+            keys.push(first_rec.resolve_key(&args.keys()[i]).map_err(|e| e.with_input(Input::Nth(i)))?);
+            first_recs.push(first_rec);
+        }
+        let mut printer = printer(keys.clone());
+        // the printer keeps the number of fields learned from the header
+        head_all(&mut first_recs, out, &mut printer, &opts)?;
+        parsers = first_recs.into_iter().map(|fr| fr.into_inner()).collect();
+        (keys, printer)
+    } else {
+        let keys = resolve_keys(args)?;
+        (keys.clone(), printer(keys))
+    };
+
+    if args.sort() {
+        let mut sorted: Vec<Parser<Reader>> = Vec::with_capacity(parsers.len());
+        for (i, parser) in parsers.into_iter().enumerate() {
+            let merge = sorter(args, &keys, i).sort(parser).map_err(|e| e.with_input(Input::Nth(i)))?;
+            let merge: Reader = Box::new(merge);
+            sorted.push(spawn(args, Parser::from_parts(input_buf(args, merge), index_builder(args, i)), i));
+        }
+        parsers = sorted;
+    }
+
+    let mut groups = Vec::with_capacity(parsers.len());
+    for (i, parser) in parsers.into_iter().enumerate() {
+        let group = Group::with_compare(parser, keys[i].clone(), args.compare().to_owned())
+            .map_err(|e| e.with_input(Input::Nth(i)))?;
+        groups.push(group);
+    }
+    join_all(&mut groups, out, &mut printer, &opts)?;
+    Ok(printer)
+}
+
+/// Open the input file or the standard input, if the `path` is '-'. The compressed input is
+/// decompressed, the regular files are mapped into the memory, unless disabled. Returns the length
+/// of the input if it is a regular file.
+fn open(args: &Args, path: &Path) -> Result<(Opened, Option<u64>), CsvError> {
+    if is_stdin(path) {
+        return Ok((Opened::Read(decoder(io::stdin())?), None));
+    }
+    let file = File::open(path).map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))?;
+    let meta = file.metadata()?;
+    let len = if meta.is_file() {
+        Some(meta.len())
+    } else {
+        None
+    };
+    if args.mmap() && len.unwrap_or(0) > 0 {
+        // the file must not be modified while it is mapped, which is what '--no-mmap' is for
+        let map = unsafe { Mmap::map(&file)? };
+        if !is_compressed(&map) {
+            return Ok((Opened::Mmap(map), len));
+        }
+    }
+    Ok((Opened::Read(decoder(file)?), len))
+}
+
+// the parser of the input number `input`, see `spawn`
+fn parser(args: &Args, file: Opened, input: usize) -> Parser<Reader> {
+    let parser = match file {
+        Opened::Read(r) => Parser::from_parts(input_buf(args, r), index_builder(args, input)),
+        Opened::Mmap(m) => {
+            let buf = MmapBuf::with_max_capacity(MMAP_CAP, args.max_buffer(), m);
+            Parser::from_mmap(buf, index_builder(args, input))
+        }
+    };
+    spawn(args, parser, input)
+}
+
+// the key fields of the inputs without the header
+fn resolve_keys(args: &Args) -> Result<Vec<Vec<usize>>, CsvError> {
+    args.keys().iter().map(|k| resolve_key(k, None)).collect()
+}
+
+// The right key of an interval join is the start and the end of the interval, only the start is
+// the key field, the end is returned on its own.
+fn split_interval(args: &Args, mut keys: Vec<Vec<usize>>) -> (Vec<Vec<usize>>, Option<usize>) {
+    let end = if args.interval() { keys[1].pop() } else { None };
+    (keys, end)
+}
+
+// The key fields printed first. The right as-of field and the start of the right interval are
+// printed with the other right fields, since they are not equal to the left key.
+fn printer_keys(args: &Args, keys: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    let mut keys = keys.to_vec();
+    if args.asof().is_some() {
+        keys[1].pop();
+    } else if args.interval() {
+        keys[1].clear();
+    }
+    keys
+}
+
+// the buffer of an input, which grows up to the maximum size of a group of records
+fn input_buf<R: io::Read>(args: &Args, inner: R) -> RollBuf<R> {
+    RollBuf::with_max_capacity(INBUF_CAP, args.max_buffer(), inner)
+}
+
+// Parse the input number `input` on a thread of its own, if there are enough threads. The inputs
+// get the threads in their order.
+fn spawn<R: io::Read + Send + 'static>(args: &Args, parser: Parser<R>, input: usize) -> Parser<R> {
+    if input + 1 < args.threads() {
+        parser.spawn(PIPE_BOUND)
+    } else {
+        parser
+    }
+}
+
+fn index_builder(args: &Args, input: usize) -> IndexBuilder {
+    let delimiter = args.in_delimiters()[input];
+    let terminator = args.in_terminators()[input];
+    match args.quote() {
+        Some(q) => IndexBuilder::with_quote(delimiter, terminator, q),
+        None => IndexBuilder::new(delimiter, terminator),
+    }
+}
+
+fn sorter(args: &Args, keys: &[Vec<usize>], input: usize) -> Sorter {
+    Sorter::from_parts(
+        keys[input].clone(),
+        args.compare().to_owned(),
+        args.in_delimiters()[input],
+        args.in_terminators()[input],
+        args.quote(),
+        args.sort_memory(),
+        PathBuf::from(args.temp_dir()),
+    ).with_max_buffer(args.max_buffer())
+}
+
+fn spiller(args: &Args, input: usize) -> Spiller {
+    Spiller::from_parts(
+        args.group_memory(),
+        args.in_delimiters()[input],
+        args.in_terminators()[input],
+        args.quote(),
+        PathBuf::from(args.temp_dir()),
+    ).with_max_buffer(args.max_buffer())
+}
+
+fn merge_join<R0: io::Read, R1: io::Read, W: io::Write, P: Print<W>>(
+    parser0: Parser<R0>,
+    parser1: Parser<R1>,
+    args: &Args,
+    keys: &[Vec<usize>],
+    end1: Option<usize>,
+    out: &mut W,
+    printer: P,
+) -> Result<(), Box<dyn Error>> {
+    let opts = args.join_options();
+    let mut group0 = Group::with_compare(parser0, keys[0].clone(), args.compare().to_owned())
+        .map_err(|e| e.with_input(Input::Left))?
+        .with_spill(spiller(args, 0));
+    let mut group1 = Group::with_compare(parser1, keys[1].clone(), args.compare().to_owned())
+        .map_err(|e| e.with_input(Input::Right))?
+        .with_spill(spiller(args, 1));
+    match (args.asof(), end1) {
+        (Some(asof), _) => asof_join(&mut group0, &mut group1, out, printer, asof)?,
+        (None, Some(end1)) => interval_join(&mut group0, &mut group1, end1, out, printer, opts)?,
+        (None, None) => join(&mut group0, &mut group1, out, printer, opts)?,
+    }
     Ok(())
 }
@@ -0,0 +1,167 @@
+use bzip2;
+use bzip2::read::MultiBzDecoder;
+use bzip2::write::BzEncoder;
+use flate2;
+use flate2::read::MultiGzDecoder;
+use flate2::write::GzEncoder;
+use zstd;
+use std::error::Error;
+use std::io::{self, Read, Write,};
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+// 'BZh' followed by the block size
+const BZIP2_MAGIC: &[u8] = b"BZh";
+// the magic of the first block or of the end of an empty stream
+const BZIP2_BLOCK_MAGIC: &[u8] = &[0x31, 0x41, 0x59, 0x26, 0x53, 0x59];
+const BZIP2_EOS_MAGIC: &[u8] = &[0x17, 0x72, 0x45, 0x38, 0x50, 0x90];
+const MAGIC_LEN: usize = 10;
+
+/// The compression format of an input or the output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    Gzip,
+    Zstd,
+    Bzip2,
+}
+
+impl Compression {
+    /// Detect the compression format by the magic bytes at the beginning of the input.
+    pub fn detect(magic: &[u8]) -> Option<Self> {
+        if magic.starts_with(GZIP_MAGIC) {
+            Some(Compression::Gzip)
+        } else if magic.starts_with(ZSTD_MAGIC) {
+            Some(Compression::Zstd)
+        } else if magic.len() == MAGIC_LEN
+            && magic.starts_with(BZIP2_MAGIC)
+            && (b'1'..=b'9').contains(&magic[3])
+            && (&magic[4..] == BZIP2_BLOCK_MAGIC || &magic[4..] == BZIP2_EOS_MAGIC) {
+            Some(Compression::Bzip2)
+        } else {
+            None
+        }
+    }
+}
+
+/// Test if the `data` begin with the magic bytes of a compression format.
+pub fn is_compressed(data: &[u8]) -> bool {
+    Compression::detect(&data[..data.len().min(MAGIC_LEN)]).is_some()
+}
+
+/// Parse the name of the compression format.
+pub fn parse_compression(name: &str) -> Result<Compression, Box<dyn Error>> {
+    match name {
+        "gzip" | "gz" => Ok(Compression::Gzip),
+        "zstd" | "zst" => Ok(Compression::Zstd),
+        "bzip2" | "bz2" => Ok(Compression::Bzip2),
+        _ => Err(format!("unknown compression '{}', expected one of 'gzip', 'zstd' or 'bzip2'", name).into()),
+    }
+}
+
+/// Wrap the `inner` reader in a streaming decoder, if the input is compressed. Otherwise the
+/// input is read as it is.
+pub fn decoder<R: Read + Send + 'static>(mut inner: R) -> io::Result<Box<dyn Read + Send>> {
+    let mut magic = [0; MAGIC_LEN];
+    let mut n = 0;
+    // the magic may arrive in several reads from a pipe
+    while n < magic.len() {
+        match inner.read(&mut magic[n..]) {
+            Ok(0) => break,
+            Ok(m) => n += m,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    let input = io::Cursor::new(magic[..n].to_vec()).chain(inner);
+    let decoder: Box<dyn Read + Send> = match Compression::detect(&magic[..n]) {
+        Some(Compression::Gzip) => Box::new(MultiGzDecoder::new(input)),
+        Some(Compression::Zstd) => Box::new(zstd::Decoder::new(input)?),
+        Some(Compression::Bzip2) => Box::new(MultiBzDecoder::new(input)),
+        None => Box::new(input),
+    };
+    Ok(decoder)
+}
+
+/// A writer which compresses the output, if required.
+pub enum Encoder<W: Write> {
+    Plain(W),
+    Gzip(GzEncoder<W>),
+    Zstd(zstd::Encoder<'static, W>),
+    Bzip2(BzEncoder<W>),
+}
+
+impl<W: Write> Encoder<W> {
+    pub fn new(inner: W, compression: Option<Compression>) -> io::Result<Self> {
+        let encoder = match compression {
+            None => Encoder::Plain(inner),
+            Some(Compression::Gzip) => Encoder::Gzip(GzEncoder::new(inner, flate2::Compression::default())),
+            Some(Compression::Zstd) => Encoder::Zstd(zstd::Encoder::new(inner, 0)?),
+            Some(Compression::Bzip2) => Encoder::Bzip2(BzEncoder::new(inner, bzip2::Compression::default())),
+        };
+        Ok(encoder)
+    }
+
+    /// Write the end of the compressed stream and return the inner writer.
+    pub fn finish(self) -> io::Result<W> {
+        match self {
+            Encoder::Plain(w) => Ok(w),
+            Encoder::Gzip(e) => e.finish(),
+            Encoder::Zstd(e) => e.finish(),
+            Encoder::Bzip2(e) => e.finish(),
+        }
+    }
+}
+
+impl<W: Write> Write for Encoder<W> {
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match *self {
+            Encoder::Plain(ref mut w) => w.write(buf),
+            Encoder::Gzip(ref mut e) => e.write(buf),
+            Encoder::Zstd(ref mut e) => e.write(buf),
+            Encoder::Bzip2(ref mut e) => e.write(buf),
+        }
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        match *self {
+            Encoder::Plain(ref mut w) => w.flush(),
+            Encoder::Gzip(ref mut e) => e.flush(),
+            Encoder::Zstd(ref mut e) => e.flush(),
+            Encoder::Bzip2(ref mut e) => e.flush(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let data = "a,0\nb,1\nc,2\n".repeat(100);
+        let compressions = [None, Some(Compression::Gzip), Some(Compression::Zstd), Some(Compression::Bzip2)];
+
+        for &c in &compressions {
+            let mut encoder = Encoder::new(Vec::new(), c).unwrap();
+            encoder.write_all(data.as_bytes()).unwrap();
+            let compressed = encoder.finish().unwrap();
+            assert_eq!(Compression::detect(&compressed[..MAGIC_LEN]), c, "{:?}", c);
+
+            let mut out = String::new();
+            decoder(io::Cursor::new(compressed)).unwrap().read_to_string(&mut out).unwrap();
+            assert_eq!(out, data, "{:?}", c);
+        }
+    }
+
+    #[test]
+    fn test_decoder_plain() {
+        let inputs = ["", "a", "BZh9,not bzip2\n", "a,0\nb,1\n"];
+        for input in &inputs {
+            let mut out = String::new();
+            decoder(input.as_bytes()).unwrap().read_to_string(&mut out).unwrap();
+            assert_eq!(&out, input);
+        }
+    }
+}
@@ -1,33 +1,83 @@
+use super::compare::Compare;
+use spill::{Spiller, SpillFile,};
 use csvroll::parser::{Parser, Index,};
+use csvroll::error::{Error, ErrorKind, Position,};
 use std::cmp::Ordering;
 use std::ops::Range;
-use std::error::Error;
 use std::io;
 
 pub struct Group<R> {
     parser: Parser<R>,
     key_idx: Vec<usize>,
+    cmp: Vec<Compare>,
     first_rec: Range<usize>,
     rec: Range<usize>,
     group: Range<usize>,
-    rec_count: usize,
     is_buf_full: bool,
+    spiller: Option<Spiller>,
+    // the spilled records of the group being read
+    spilling: Option<SpillFile>,
+    // the spilled records of the last group returned by `next_group`
+    spilled: Option<SpillFile>,
 }
 
 impl<R> Group<R> {
+    /// Spill the records of a group to the disk, once they exceed the threshold of `spiller`.
+    pub fn with_spill(mut self, spiller: Spiller) -> Self {
+        self.spiller = Some(spiller);
+        self
+    }
+
+    /// Return the file of the records of the last group returned by `next_group`, which were
+    /// spilled to the disk. The spilled records precede the records in the buffer.
+    #[inline]
+    pub fn spilled(&self) -> Option<&SpillFile> {
+        self.spilled.as_ref()
+    }
+
     #[inline]
     pub fn key_idx(&self) -> &[usize] {
         &self.key_idx
     }
+
+    /// Return the comparison of each key field.
+    #[inline]
+    pub fn compare(&self) -> &[Compare] {
+        &self.cmp
+    }
+
+    /// Return the number of fields of the first record of the current group, if any.
+    #[inline]
+    pub fn field_count(&self) -> Option<usize> {
+        if self.group.start == self.group.end {
+            None
+        } else {
+            Some(self.first_rec.end - self.first_rec.start)
+        }
+    }
 }
 
 impl<R: io::Read> Group<R> {
-    pub fn init(mut parser: Parser<R>, key_idx: Vec<usize>) -> Result<Self, Box<Error>> {
-        let is_buf_full = parser.parse()?;
+    pub fn init(parser: Parser<R>, key_idx: Vec<usize>) -> Result<Self, Error> {
+        Self::with_compare(parser, key_idx, Vec::new())
+    }
+
+    /// Create a new instance of `Group` comparing the key fields by `cmp`. The key fields without
+    /// a comparison are compared as bytes.
+    pub fn with_compare(
+        mut parser: Parser<R>,
+        key_idx: Vec<usize>,
+        cmp: Vec<Compare>,
+    ) -> Result<Self, Error> {
+        let mut is_buf_full = parser.parse()?;
+        // the first record may not fit into the initial buffer
+        while is_buf_full && parser.output().1.records().is_empty() {
+            parser.consume(0);
+            is_buf_full = parser.parse()?;
+        }
         let first_rec: Range<usize>;
         let rec: Range<usize>;
         let group: Range<usize>;
-        let rec_count: usize;
 
         {
             let (_, struct_idx) = parser.output();
@@ -37,13 +87,11 @@ impl<R: io::Read> Group<R> {
                     first_rec = 0..re;
                     rec = first_rec.clone();
                     group = 0..1;
-                    rec_count = 1;
                 }
                 None => {
                     first_rec = 0..0;
                     rec = first_rec.clone();
                     group = 0..0;
-                    rec_count = 0;
                 }
             }
         }
@@ -51,21 +99,23 @@ impl<R: io::Read> Group<R> {
         Ok(Self {
             parser ,
             key_idx ,
+            cmp,
             first_rec ,
             rec ,
             group ,
-            rec_count ,
             is_buf_full ,
+            spiller: None,
+            spilling: None,
+            spilled: None,
         })
     }
     
-    pub fn from_initiated(parser: Parser<R>, key_idx: Vec<usize>) -> Result<Self, Box<Error>> {
+    pub fn from_initiated(parser: Parser<R>, key_idx: Vec<usize>) -> Result<Self, Error> {
         let is_buf_full = parser.is_buf_full();
         let consumed = parser.consumed();
         let first_rec: Range<usize>;
         let rec: Range<usize>;
         let group: Range<usize>;
-        let rec_count: usize;
 
         {
             let (_, struct_idx) = parser.output();
@@ -74,14 +124,12 @@ impl<R: io::Read> Group<R> {
                 Some(r) => {
                     first_rec = r.clone();
                     rec = r;
-                    rec_count = consumed + 1;
-                    group = consumed..rec_count;
+                    group = consumed..(consumed + 1);
                 }
                 None => {
                     first_rec = 0..0;
                     rec = first_rec.clone();
                     group = 0..0;
-                    rec_count = 0;
                 }
             }
         }
@@ -89,25 +137,32 @@ impl<R: io::Read> Group<R> {
         Ok(Self {
             parser ,
             key_idx ,
+            cmp: Vec::new(),
             first_rec ,
             rec ,
             group ,
-            rec_count ,
             is_buf_full ,
+            spiller: None,
+            spilling: None,
+            spilled: None,
         })
     }
 
     #[inline]
-    pub fn next_group(&mut self) -> Result<Option<Range<usize>>, Box<Error>> {
+    pub fn next_group(&mut self) -> Result<Option<Range<usize>>, Error> {
+        self.spilled = None;
         loop {
-            let mut rec_count = self.rec_count;
+            if self.group.start == self.group.end {
+                // the first record of the group did not fit into the buffer so far
+                let n = self.group.end;
+                self.start_group(n);
+            }
             {
                 let (buf, struct_idx) = self.parser.output();
                 let fields = struct_idx.fields();
 
-                for &re in &struct_idx.records()[self.group.end..] {
+                for (i, &re) in struct_idx.records()[self.group.end..].iter().enumerate() {
                     let rec = self.rec.end..re;
-                    rec_count += 1;
                     match cmp_records(
                         buf,
                         buf,
@@ -115,20 +170,20 @@ impl<R: io::Read> Group<R> {
                         &fields[self.first_rec.clone()],
                         &self.key_idx,
                         &self.key_idx,
+                        &self.cmp,
                         ) {
 
                         Ok(ord) => match ord {
                             Ordering::Less => {
-                                return Err(format!(
-                                    "the record number {} has the key with lower value than the \
-                                    preceding record", rec_count).into());
+                                let pos = self.parser.position(self.group.end + i);
+                                return Err(Error::at(ErrorKind::Unsorted, pos));
                             }
                             Ordering::Greater => {
                                 let g = self.group.clone();
                                 self.first_rec = rec.clone();
                                 self.rec = rec.clone();
                                 self.group = self.group.end..(self.group.end + 1);
-                                self.rec_count = rec_count;
+                                self.finish_spill()?;
                                 return Ok(Some(g));
                             }
                             Ordering::Equal => {
@@ -137,20 +192,21 @@ impl<R: io::Read> Group<R> {
                             }
                         }
                         Err(e) => {
-                            let c = if e == 0 {
-                                self.rec_count
+                            let n = if e == 0 {
+                                self.group.end + i
                             } else {
-                                rec_count
+                                self.group.start
                             };
-                            return Err(format!(
-                                "the record number {} has less fields than the key", c).into());
+                            return Err(Error::at(ErrorKind::ShortRecord, self.parser.position(n)));
                         }
                     }
                 }
             }
 
-            self.rec_count = rec_count;
             if self.is_buf_full {
+                if self.should_spill() {
+                    self.spill()?;
+                }
                 let field_offset = self.first_rec.start;
                 let rec_offset = self.group.start;
                 self.first_rec = (self.first_rec.start - field_offset)..(self.first_rec.end - field_offset);
@@ -162,6 +218,7 @@ impl<R: io::Read> Group<R> {
                 let g = self.group.clone();
                 if g.start != g.end {
                     self.group = self.group.end..self.group.end;
+                    self.finish_spill()?;
                     return Ok(Some(g));
                 } else {
                     return Ok(None);
@@ -170,12 +227,181 @@ impl<R: io::Read> Group<R> {
         }
     }
 
+    /// Skip the groups with the key less than the key of the record `rec` of `buf`, e.g. of the
+    /// other input, and return the first group, which is not less, like `next_group`.
+    ///
+    /// The records in the buffer are galloped over and the rest of a memory-mapped input is
+    /// bisected, so the skipped records are not compared with each other and their sort order is
+    /// not checked.
+    pub fn skip_to(
+        &mut self,
+        buf: &[u8],
+        rec: &[Range<usize>],
+        key_idx: &[usize],
+    ) -> Result<Option<Range<usize>>, Error> {
+        loop {
+            let n = self.parser.output().1.records().len();
+            if self.group.start == n {
+                if !self.is_buf_full {
+                    return self.next_group();
+                }
+                let consumed = self.group.start;
+                self.refill(consumed)?;
+                continue;
+            }
+            if self.cmp_key(self.group.start, buf, rec, key_idx)? != Ordering::Less {
+                return self.next_group();
+            }
+            if self.cmp_key(n - 1, buf, rec, key_idx)? == Ordering::Less {
+                // all the complete records in the buffer are skipped
+                let is_sought = self.is_buf_full && {
+                    let (key_idx0, cmp) = (&self.key_idx, &self.cmp);
+                    self.parser.seek_while(|b, r| {
+                        cmp_records(b, buf, r, rec, key_idx0, key_idx, cmp) == Ok(Ordering::Less)
+                    })
+                };
+                if is_sought {
+                    self.is_buf_full = self.parser.parse()?;
+                    self.start_group(0);
+                } else {
+                    self.refill(n)?;
+                }
+                continue;
+            }
+
+            // the first record, which is not less, is in (lo, hi]
+            let (mut lo, mut hi) = (self.group.start, n - 1);
+            let mut step = 1;
+            while lo + step < hi {
+                if self.cmp_key(lo + step, buf, rec, key_idx)? == Ordering::Less {
+                    lo += step;
+                    step *= 2;
+                } else {
+                    hi = lo + step;
+                    break;
+                }
+            }
+            while hi - lo > 1 {
+                let mid = lo + (hi - lo) / 2;
+                if self.cmp_key(mid, buf, rec, key_idx)? == Ordering::Less {
+                    lo = mid;
+                } else {
+                    hi = mid;
+                }
+            }
+            self.start_group(hi);
+            return self.next_group();
+        }
+    }
+
     #[inline]
     pub fn buf_index(&self) -> (&[u8], &Index) {
         self.parser.output()
     }
+
+    /// Return the position in the input of the record `n` of the buffer.
+    #[inline]
+    pub fn position(&self, n: usize) -> Position {
+        self.parser.position(n)
+    }
+
+    /// Return the number of the first record of the group following the last one returned by
+    /// `next_group`, if any. The record is in the buffer along with the returned group.
+    #[inline]
+    pub fn peek(&self) -> Option<usize> {
+        if self.group.start == self.group.end {
+            None
+        } else {
+            Some(self.group.start)
+        }
+    }
+
+    // start the current group at the record `n` of the buffer, if it is complete
+    #[inline]
+    fn start_group(&mut self, n: usize) {
+        match self.parser.output().1.get_record(n) {
+            Some(r) => {
+                self.first_rec = r.clone();
+                self.rec = r;
+                self.group = n..(n + 1);
+            }
+            None => {
+                self.first_rec = 0..0;
+                self.rec = 0..0;
+                self.group = n..n;
+            }
+        }
+    }
+
+    // consume the first `n` records of the buffer and start the group at the following one
+    fn refill(&mut self, n: usize) -> Result<(), Error> {
+        self.parser.consume(n);
+        self.is_buf_full = self.parser.parse()?;
+        self.start_group(0);
+        Ok(())
+    }
+
+    // compare the key of the record `n` of the buffer with the key of the record `rec` of `buf`
+    #[inline]
+    fn cmp_key(&self, n: usize, buf: &[u8], rec: &[Range<usize>], key_idx: &[usize]) -> Result<Ordering, Error> {
+        let (buf0, struct_idx) = self.parser.output();
+        let r = struct_idx.get_record(n).unwrap_or(0..0);
+        cmp_records(buf0, buf, &struct_idx.fields()[r], rec, &self.key_idx, key_idx, &self.cmp)
+            .map_err(|_| Error::at(ErrorKind::ShortRecord, self.parser.position(n)))
+    }
+
+    // test if the records of the current group take more of the buffer than the spill threshold
+    #[inline]
+    fn should_spill(&self) -> bool {
+        let threshold = match self.spiller {
+            Some(ref s) => s.threshold(),
+            None => return false,
+        };
+        if self.group.end - self.group.start < 2 {
+            return false;
+        }
+        let fields = self.parser.output().1.fields();
+        let start = fields[self.first_rec.start].start;
+        let end = fields[self.rec.end - 1].end;
+        end - start >= threshold
+    }
+
+    // Write all the records of the current group but the last one into the spill file. The last
+    // record stays in the buffer to compare the keys of the following records.
+    fn spill(&mut self) -> Result<(), Error> {
+        if self.spilling.is_none() {
+            if let Some(ref s) = self.spiller {
+                self.spilling = Some(s.create()?);
+            }
+        }
+        if let Some(ref mut file) = self.spilling {
+            let (buf, struct_idx) = self.parser.output();
+            for i in self.group.start..(self.group.end - 1) {
+                if let Some(r) = struct_idx.get_record(i) {
+                    file.push(buf, &struct_idx.fields()[r])?;
+                }
+            }
+        }
+        self.first_rec = self.rec.clone();
+        self.group.start = self.group.end - 1;
+        Ok(())
+    }
+
+    // the group is complete, so its spill file is handed over to the caller
+    #[inline]
+    fn finish_spill(&mut self) -> Result<(), Error> {
+        if let Some(mut file) = self.spilling.take() {
+            file.finish()?;
+            self.spilled = Some(file);
+        }
+        Ok(())
+    }
 }
 
+/// Compare the key fields of the records `rec_0` and `rec_1` using the comparison `cmp` of each
+/// key field, the key fields without a comparison are compared as bytes.
+///
+/// Returns the number of the record which has less fields than the key as the error.
 #[inline]
 pub fn cmp_records(
     buf0: &[u8],
@@ -184,9 +410,10 @@ pub fn cmp_records(
     rec_1: &[Range<usize>],
     key_idx0: &[usize],
     key_idx1: &[usize],
+    cmp: &[Compare],
 ) -> Result<Ordering, usize> {
 
-    for (&k0, &k1) in key_idx0.iter().zip(key_idx1) {
+    for (i, (&k0, &k1)) in key_idx0.iter().zip(key_idx1).enumerate() {
         let f0 = match rec_0.get(k0) {
             Some(f) => f.clone(),
             None => return Err(0),
@@ -196,7 +423,7 @@ pub fn cmp_records(
             None => return Err(1),
         };
         
-        match buf0[f0].cmp(&buf1[f1]) {
+        match cmp.get(i).cloned().unwrap_or_default().cmp(&buf0[f0], &buf1[f1]) {
             Ordering::Less => return Ok(Ordering::Less),
             Ordering::Greater => return Ok(Ordering::Greater),
             Ordering::Equal => continue,
@@ -206,13 +433,76 @@ pub fn cmp_records(
     Ok(Ordering::Equal)
 }
 
+/// Copy the record `rec` from `buf` to the end of `out_buf` and add its fields to `out_idx`.
+///
+/// The record must have at least one field.
+#[inline]
+pub fn push_record(
+    out_buf: &mut Vec<u8>,
+    out_idx: &mut Index,
+    buf: &[u8],
+    rec: &[Range<usize>],
+) {
+    let start = rec[0].start;
+    let end = rec[rec.len() - 1].end;
+    let offset = out_buf.len();
+    out_buf.extend_from_slice(&buf[start..end]);
+    for f in rec {
+        out_idx.push_field((f.start - start + offset)..(f.end - start + offset));
+    }
+    out_idx.push_record(out_idx.fields().len());
+}
+
+/// A key field given either by its 0-based index or by its name in the header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Field {
+    Index(usize),
+    Name(String),
+}
+
+/// Find the index of each key field, the names are looked up in the `header` record.
+pub fn resolve_key(
+    key: &[Field],
+    header: Option<(&[u8], &[Range<usize>])>,
+) -> Result<Vec<usize>, Error> {
+    let mut out = Vec::with_capacity(key.len());
+    for field in key {
+        let name = match *field {
+            Field::Index(i) => {
+                out.push(i);
+                continue;
+            }
+            Field::Name(ref name) => name,
+        };
+        let (buf, rec) = match header {
+            Some(h) => h,
+            None => {
+                return Err(Error::invalid_argument(
+                    format!("the field '{}' cannot be found without the header", name)));
+            }
+        };
+        let mut found = rec.iter().enumerate().filter(|&(_, f)| &buf[f.clone()] == name.as_bytes());
+        match (found.next(), found.next()) {
+            (Some((i, _)), None) => out.push(i),
+            (Some(_), Some(_)) => {
+                return Err(Error::invalid_argument(
+                    format!("the field '{}' is in the header more than once", name)));
+            }
+            (None, _) => {
+                return Err(Error::invalid_argument(format!("the field '{}' is not in the header", name)));
+            }
+        }
+    }
+    Ok(out)
+}
+
 pub struct FirstRec<R> {
     parser: Parser<R>,
     is_buf_full: bool,
 }
 
 impl<R:io::Read> FirstRec<R> {
-    pub fn init(mut parser: Parser<R>) -> Result<Self, Box<Error>> {
+    pub fn init(mut parser: Parser<R>) -> Result<Self, Error> {
         let is_buf_full = parser.parse()?;
 
         Ok(Self {
@@ -221,7 +511,16 @@ impl<R:io::Read> FirstRec<R> {
         })
     }
 
-    pub fn is_present(&mut self) -> Result<bool, Box<Error>> {
+    pub fn is_present(&mut self) -> Result<bool, Error> {
+        if self.fill()? {
+            self.parser.consume(1);
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    // parse until the first record is complete, without consuming it
+    fn fill(&mut self) -> Result<bool, Error> {
         loop {
             let is_empty = {
                 let (_, struct_idx) = self.parser.output();
@@ -236,12 +535,27 @@ impl<R:io::Read> FirstRec<R> {
                     return Ok(false);
                 }
             } else {
-                self.parser.consume(1);
                 return Ok(true);
             }
         }
     }
 
+    /// Find the index of each key field, the names are looked up in the first record. It must be
+    /// called before `is_present`.
+    pub fn resolve_key(&mut self, key: &[Field]) -> Result<Vec<usize>, Error> {
+        if !self.fill()? {
+            return resolve_key(key, None);
+        }
+        let (buf, idx) = self.parser.output();
+        let end = idx.records()[0];
+        resolve_key(key, Some((buf, &idx.fields()[..end])))
+    }
+
+    /// Return the number of fields of the first record, if it is present.
+    pub fn field_count(&self) -> Option<usize> {
+        self.parser.output().1.records().first().cloned()
+    }
+
     pub fn into_inner(self) -> Parser<R> {
         self.parser
     }
@@ -255,6 +569,7 @@ impl<R:io::Read> FirstRec<R> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use super::super::compare::{Kind, Fold,};
     use rollbuf::RollBuf;
     use csvroll::index_builder::IndexBuilder;
 
@@ -359,6 +674,166 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_group_numeric() {
+        let input = "9,a\n10,b\n010,c\n11,d";
+        let buf = RollBuf::with_capacity(32, input.as_bytes());
+        let parser = Parser::from_parts(buf, IndexBuilder::new(b',', b'\n'));
+        let mut group = Group::with_compare(parser, vec![0], vec![Compare::new(Kind::Numeric)]).unwrap();
+
+        assert_eq!(group.next_group().unwrap(), Some(0..1));
+        assert_eq!(group.next_group().unwrap(), Some(1..3));
+        assert_eq!(group.next_group().unwrap(), Some(3..4));
+        assert_eq!(group.next_group().unwrap(), None);
+
+        let buf = RollBuf::with_capacity(32, "10,a\n9,b".as_bytes());
+        let parser = Parser::from_parts(buf, IndexBuilder::new(b',', b'\n'));
+        let mut group = Group::init(parser, vec![0]).unwrap();
+        assert_eq!(group.next_group().unwrap(), Some(0..1));
+        let buf = RollBuf::with_capacity(32, "10,a\n9,b".as_bytes());
+        let parser = Parser::from_parts(buf, IndexBuilder::new(b',', b'\n'));
+        let mut group = Group::with_compare(parser, vec![0], vec![Compare::new(Kind::Numeric)]).unwrap();
+        let err = group.next_group().unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::Unsorted));
+        assert_eq!(err.to_string(), "the record number 2 (line 2, byte 5) has the key out of the sort order \
+                                     of the preceding record");
+
+        let buf = RollBuf::with_capacity(32, "1,a\n2\n".as_bytes());
+        let parser = Parser::from_parts(buf, IndexBuilder::new(b',', b'\n'));
+        let mut group = Group::init(parser, vec![1]).unwrap();
+        let err = group.next_group().unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::ShortRecord));
+        assert_eq!(err.position().map(|p| p.record), Some(2));
+    }
+
+    #[test]
+    fn test_group_fold() {
+        let cmp = vec![Compare::new(Kind::Bytes).with_fold(Fold::Ascii).with_trim(true)];
+        let input = "a,0\nA,1\nb,2\n B ,3";
+        let buf = RollBuf::with_capacity(32, input.as_bytes());
+        let parser = Parser::from_parts(buf, IndexBuilder::new(b',', b'\n'));
+        let mut group = Group::with_compare(parser, vec![0], cmp.clone()).unwrap();
+
+        assert_eq!(group.next_group().unwrap(), Some(0..2));
+        assert_eq!(group.next_group().unwrap(), Some(2..4));
+        assert_eq!(group.next_group().unwrap(), None);
+
+        // 'B' < 'a' as bytes, but not after folding
+        let buf = RollBuf::with_capacity(32, "B,0\na,1".as_bytes());
+        let parser = Parser::from_parts(buf, IndexBuilder::new(b',', b'\n'));
+        let mut group = Group::with_compare(parser, vec![0], cmp).unwrap();
+        assert!(group.next_group().is_err());
+    }
+
+    #[test]
+    fn test_group_reverse() {
+        let cmp = vec![Compare::new(Kind::Bytes).with_reverse(true), Compare::new(Kind::Numeric)];
+        let input = "b,1\nb,1\nb,2\na,0\na,10";
+        let buf = RollBuf::with_capacity(32, input.as_bytes());
+        let parser = Parser::from_parts(buf, IndexBuilder::new(b',', b'\n'));
+        let mut group = Group::with_compare(parser, vec![0, 1], cmp.clone()).unwrap();
+
+        assert_eq!(group.next_group().unwrap(), Some(0..2));
+        assert_eq!(group.next_group().unwrap(), Some(2..3));
+        assert_eq!(group.next_group().unwrap(), Some(3..4));
+        assert_eq!(group.next_group().unwrap(), Some(4..5));
+        assert_eq!(group.next_group().unwrap(), None);
+
+        let buf = RollBuf::with_capacity(32, "a,0\nb,0".as_bytes());
+        let parser = Parser::from_parts(buf, IndexBuilder::new(b',', b'\n'));
+        let mut group = Group::with_compare(parser, vec![0, 1], cmp).unwrap();
+        assert!(group.next_group().is_err());
+    }
+
+    #[test]
+    #[allow(clippy::single_range_in_vec_init)]
+    fn test_group_skip_to() {
+        use memmap2::MmapMut;
+        use csvroll::mmap::MmapBuf;
+
+        // skip to the key or take the next group, if none, and the first key and the size of the
+        // group
+        type Step = (Option<&'static str>, Option<(&'static str, usize)>);
+
+        struct TestCase {
+            input: String,
+            buf_len: usize,
+            want: Vec<Step>,
+        }
+
+        let input = "a,0\nb,0\nb,1\nc,0\nd,0\ne,0\ne,1\nf,0\ng,0\nh,0\nh,1\n";
+        let test_cases = vec![
+            TestCase {
+                input: input.to_owned(),
+                buf_len: 64,
+                want: vec![
+                    (Some("c"), Some(("c", 1))),
+                    (Some("c"), Some(("d", 1))),
+                    (Some("dd"), Some(("e", 2))),
+                    (None, Some(("f", 1))),
+                    (Some("h"), Some(("h", 2))),
+                    (Some("h"), None),
+                ],
+            },
+            TestCase {
+                input: input.to_owned(),
+                buf_len: 8,
+                want: vec![
+                    (Some("a"), Some(("a", 1))),
+                    (Some("e"), Some(("e", 2))),
+                    (Some("g"), Some(("g", 1))),
+                    (None, Some(("h", 2))),
+                    (None, None),
+                ],
+            },
+            TestCase {
+                input: input.to_owned(),
+                buf_len: 4,
+                want: vec![
+                    (Some("z"), None),
+                ],
+            },
+            // the first record is longer than the buffer
+            TestCase {
+                input: "aaaaaaaaaaaa,0\nb,0\nc,0".to_owned(),
+                buf_len: 4,
+                want: vec![
+                    (None, Some(("aaaaaaaaaaaa", 1))),
+                    (Some("c"), Some(("c", 1))),
+                    (None, None),
+                ],
+            },
+        ];
+
+        for (i, t) in test_cases.into_iter().enumerate() {
+            println!("test case: {}", i);
+            let mut m = MmapMut::map_anon(t.input.len()).unwrap();
+            m.copy_from_slice(t.input.as_bytes());
+            let parsers = vec![
+                Parser::from_parts(RollBuf::with_capacity(t.buf_len, t.input.as_bytes()), IndexBuilder::new(b',', b'\n')),
+                Parser::from_mmap(
+                    MmapBuf::with_max_capacity(t.buf_len, 1 << 10, m.make_read_only().unwrap()),
+                    IndexBuilder::new(b',', b'\n'),
+                ),
+            ];
+            for parser in parsers {
+                let mut group = Group::init(parser, vec![0]).unwrap();
+                for &(key, want) in &t.want {
+                    let rng = match key {
+                        Some(key) => group.skip_to(key.as_bytes(), &[0..key.len()], &[0]).unwrap(),
+                        None => group.next_group().unwrap(),
+                    };
+                    let got = rng.map(|rng| {
+                        let (buf, idx) = group.buf_index();
+                        let f = idx.fields()[idx.get_record(rng.start).unwrap()][0].clone();
+                        (String::from_utf8(buf[f].to_vec()).unwrap(), rng.end - rng.start)
+                    });
+                    assert_eq!(got, want.map(|(k, n)| (k.to_owned(), n)), "{:?}", key);
+                }
+            }
+        }
+    }
+
     #[test]
     fn test_first_rec() {
         struct TestCase {
@@ -401,6 +876,59 @@ mod tests {
             assert_eq!(fr.is_present().unwrap(), want);
         }
     }
+
+    #[test]
+    fn test_resolve_key() {
+        struct TestCase {
+            input: String,
+            key: Vec<Field>,
+            want: Option<Vec<usize>>,
+        }
+
+        let test_cases = vec![
+            TestCase {
+                input: "id,date,amount\n1,2024-01-01,5".to_owned(),
+                key: vec![Field::Name("date".into()), Field::Name("id".into())],
+                want: Some(vec![1, 0]),
+            },
+            TestCase {
+                input: "id,date,amount\n1,2024-01-01,5".to_owned(),
+                key: vec![Field::Index(2), Field::Name("id".into())],
+                want: Some(vec![2, 0]),
+            },
+            TestCase {
+                input: "id,date,amount".to_owned(),
+                key: vec![Field::Name("customer".into())],
+                want: None,
+            },
+            TestCase {
+                input: "id,date,id".to_owned(),
+                key: vec![Field::Name("id".into())],
+                want: None,
+            },
+            TestCase {
+                input: "".to_owned(),
+                key: vec![Field::Index(0)],
+                want: Some(vec![0]),
+            },
+            TestCase {
+                input: "".to_owned(),
+                key: vec![Field::Name("id".into())],
+                want: None,
+            },
+        ];
+
+        for (i, t) in test_cases.into_iter().enumerate() {
+            println!("test case: {}", i);
+            let TestCase { input, key, want } = t;
+            let buf = RollBuf::with_capacity(4, input.as_bytes());
+            let parser = Parser::from_parts(buf, IndexBuilder::new(b',', b'\n'));
+            let mut fr = FirstRec::init(parser).unwrap();
+
+            assert_eq!(fr.resolve_key(&key).ok(), want);
+            assert_eq!(fr.is_present().unwrap(), !input.is_empty());
+        }
+    }
 }
 
                 
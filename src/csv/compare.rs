@@ -0,0 +1,513 @@
+use std::cmp::Ordering;
+use std::str;
+
+/// The kind of the comparison of a key field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Kind {
+    /// Compare the bytes lexicographically.
+    #[default]
+    Bytes,
+    /// Compare the fields as decimal numbers like `sort -n`, e.g. `-1.50`, `007` or `+3`. Only the
+    /// numeric prefix of the field after the leading blanks is taken into account, the fields
+    /// without it are equal to zero.
+    Numeric,
+    /// Compare the fields as floating-point numbers like `sort -g`, e.g. `1e3` or `-0.5`. The
+    /// fields which are not numbers are equal to each other and lower than all the numbers.
+    General,
+}
+
+/// The case folding of a key field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum Fold {
+    #[default]
+    None,
+    /// Fold the ASCII letters to uppercase like `sort -f`, the other bytes are compared as they are.
+    Ascii,
+    /// Apply the Unicode simple case folding, i.e. one character is folded to one character. Only
+    /// the ASCII letters of the fields which are not valid UTF-8 are folded.
+    Unicode,
+}
+
+/// The comparison of a key field.
+///
+/// The field is trimmed and folded first, then it is compared according to its kind. The
+/// normalization is used for matching and ordering only, the records are printed as they are.
+/// The reversed comparison expects the fields in descending order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Compare {
+    kind: Kind,
+    fold: Fold,
+    trim: bool,
+    reverse: bool,
+}
+
+impl Compare {
+    pub fn new(kind: Kind) -> Self {
+        Compare { kind, fold: Fold::None, trim: false, reverse: false }
+    }
+
+    /// Fold the case of the field before comparing it.
+    pub fn with_fold(self, fold: Fold) -> Self {
+        Compare { fold, ..self }
+    }
+
+    /// Ignore the leading and trailing whitespace of the field.
+    pub fn with_trim(self, trim: bool) -> Self {
+        Compare { trim, ..self }
+    }
+
+    /// Reverse the order of the fields.
+    pub fn with_reverse(self, reverse: bool) -> Self {
+        Compare { reverse, ..self }
+    }
+
+    #[inline]
+    pub fn kind(&self) -> Kind {
+        self.kind
+    }
+
+    #[inline]
+    pub fn fold(&self) -> Fold {
+        self.fold
+    }
+
+    #[inline]
+    pub fn trim(&self) -> bool {
+        self.trim
+    }
+
+    #[inline]
+    pub fn reverse(&self) -> bool {
+        self.reverse
+    }
+
+    /// Compare the fields `a` and `b`.
+    #[inline]
+    pub fn cmp(&self, a: &[u8], b: &[u8]) -> Ordering {
+        if self.reverse {
+            self.cmp_fields(a, b).reverse()
+        } else {
+            self.cmp_fields(a, b)
+        }
+    }
+
+    #[inline]
+    fn cmp_fields(&self, a: &[u8], b: &[u8]) -> Ordering {
+        let (a, b) = if self.trim {
+            (trim(a), trim(b))
+        } else {
+            (a, b)
+        };
+        match self.kind {
+            // the numbers have no case
+            Kind::Bytes => match self.fold {
+                Fold::None => a.cmp(b),
+                Fold::Ascii => cmp_ascii_fold(a, b),
+                Fold::Unicode => cmp_unicode_fold(a, b),
+            },
+            Kind::Numeric => cmp_numeric(a, b),
+            Kind::General => cmp_general(a, b),
+        }
+    }
+
+    /// Return the value of the numeric `field`, i.e. of the comparison `Kind::Numeric` or
+    /// `Kind::General`. The fields, which are not numbers, have no value.
+    pub fn value(&self, field: &[u8]) -> Option<f64> {
+        let field = if self.trim {
+            trim(field)
+        } else {
+            field
+        };
+        match self.kind {
+            Kind::Bytes => None,
+            Kind::Numeric => {
+                let (neg, int, frac) = parse_numeric(field);
+                let mut s = String::with_capacity(int.len() + frac.len() + 3);
+                if neg {
+                    s.push('-');
+                }
+                s.push('0');
+                s.push_str(str::from_utf8(int).ok()?);
+                s.push('.');
+                s.push_str(str::from_utf8(frac).ok()?);
+                s.push('0');
+                s.parse().ok()
+            }
+            Kind::General => parse_general(field),
+        }
+    }
+
+    /// Append the encoding of the `field` to `out`, so that equal fields have equal encodings.
+    #[inline]
+    pub fn encode(&self, field: &[u8], out: &mut Vec<u8>) {
+        let field = if self.trim {
+            trim(field)
+        } else {
+            field
+        };
+        match self.kind {
+            Kind::Bytes => match self.fold {
+                Fold::None => out.extend_from_slice(field),
+                Fold::Ascii => out.extend(field.iter().map(|b| b.to_ascii_uppercase())),
+                Fold::Unicode => match str::from_utf8(field) {
+                    Ok(s) => {
+                        let mut tmp = [0; 4];
+                        for c in s.chars().map(fold_char) {
+                            out.extend_from_slice(c.encode_utf8(&mut tmp).as_bytes());
+                        }
+                    }
+                    Err(_) => out.extend(field.iter().map(|b| b.to_ascii_lowercase())),
+                },
+            },
+            Kind::Numeric => {
+                let (neg, int, frac) = parse_numeric(field);
+                if int.is_empty() && frac.is_empty() {
+                    out.push(b'0');
+                } else {
+                    if neg {
+                        out.push(b'-');
+                    }
+                    out.extend_from_slice(int);
+                    out.push(b'.');
+                    out.extend_from_slice(frac);
+                }
+            }
+            Kind::General => match parse_general(field) {
+                // -0.0 is equal to 0.0
+                Some(x) => out.extend_from_slice(&(x + 0.0).to_bits().to_le_bytes()),
+                None => out.push(b'n'),
+            },
+        }
+    }
+}
+
+#[inline]
+fn trim(field: &[u8]) -> &[u8] {
+    let start = field.iter().position(|b| !b.is_ascii_whitespace()).unwrap_or(field.len());
+    let end = field.iter().rposition(|b| !b.is_ascii_whitespace()).map_or(start, |i| i + 1);
+    &field[start..end]
+}
+
+#[inline]
+fn cmp_ascii_fold(a: &[u8], b: &[u8]) -> Ordering {
+    a.iter().map(|b| b.to_ascii_uppercase())
+        .cmp(b.iter().map(|b| b.to_ascii_uppercase()))
+}
+
+#[inline]
+fn cmp_ascii_lower(a: &[u8], b: &[u8]) -> Ordering {
+    a.iter().map(|b| b.to_ascii_lowercase())
+        .cmp(b.iter().map(|b| b.to_ascii_lowercase()))
+}
+
+#[inline]
+fn cmp_unicode_fold(a: &[u8], b: &[u8]) -> Ordering {
+    // the Unicode case folding is towards lowercase
+    if a.is_ascii() && b.is_ascii() {
+        return cmp_ascii_lower(a, b);
+    }
+    match (str::from_utf8(a), str::from_utf8(b)) {
+        // the order of the code points is the order of their UTF-8 bytes
+        (Ok(a), Ok(b)) => a.chars().map(fold_char).cmp(b.chars().map(fold_char)),
+        _ => cmp_ascii_lower(a, b),
+    }
+}
+
+/// Fold the case of the character `c`. The case folding maps the characters with multiple
+/// lowercase forms, e.g. 'ς' and 'σ', to the lowercase of their uppercase.
+#[inline]
+fn fold_char(c: char) -> char {
+    if c.is_ascii() {
+        return c.to_ascii_lowercase();
+    }
+    let mut upper = c.to_uppercase();
+    let c = match (upper.next(), upper.next()) {
+        (Some(u), None) => u,
+        _ => c,
+    };
+    let mut lower = c.to_lowercase();
+    match (lower.next(), lower.next()) {
+        (Some(l), None) => l,
+        _ => c,
+    }
+}
+
+/// Split the numeric prefix of the field into the sign, the integral part without the leading zeros
+/// and the fractional part without the trailing zeros.
+#[inline]
+fn parse_numeric(field: &[u8]) -> (bool, &[u8], &[u8]) {
+    let mut i = 0;
+    while i < field.len() && (field[i] == b' ' || field[i] == b'\t') {
+        i += 1;
+    }
+    let mut neg = false;
+    if i < field.len() && (field[i] == b'-' || field[i] == b'+') {
+        neg = field[i] == b'-';
+        i += 1;
+    }
+    while i < field.len() && field[i] == b'0' {
+        i += 1;
+    }
+    let int_start = i;
+    while i < field.len() && field[i].is_ascii_digit() {
+        i += 1;
+    }
+    let int = &field[int_start..i];
+
+    let mut frac: &[u8] = &[];
+    if i < field.len() && field[i] == b'.' {
+        i += 1;
+        let frac_start = i;
+        while i < field.len() && field[i].is_ascii_digit() {
+            i += 1;
+        }
+        let mut frac_end = i;
+        while frac_end > frac_start && field[frac_end - 1] == b'0' {
+            frac_end -= 1;
+        }
+        frac = &field[frac_start..frac_end];
+    }
+    (neg, int, frac)
+}
+
+#[inline]
+fn cmp_numeric(a: &[u8], b: &[u8]) -> Ordering {
+    let (neg_a, int_a, frac_a) = parse_numeric(a);
+    let (neg_b, int_b, frac_b) = parse_numeric(b);
+    let zero_a = int_a.is_empty() && frac_a.is_empty();
+    let zero_b = int_b.is_empty() && frac_b.is_empty();
+    // the sign of zero does not matter
+    let neg_a = neg_a && !zero_a;
+    let neg_b = neg_b && !zero_b;
+
+    match (neg_a, neg_b) {
+        (false, true) => Ordering::Greater,
+        (true, false) => Ordering::Less,
+        (neg, _) => {
+            let ord = int_a.len().cmp(&int_b.len())
+                .then_with(|| int_a.cmp(int_b))
+                .then_with(|| frac_a.cmp(frac_b));
+            if neg {
+                ord.reverse()
+            } else {
+                ord
+            }
+        }
+    }
+}
+
+#[inline]
+fn parse_general(field: &[u8]) -> Option<f64> {
+    str::from_utf8(field).ok()
+        .and_then(|s| s.trim().parse::<f64>().ok())
+        .filter(|x| !x.is_nan())
+}
+
+#[inline]
+fn cmp_general(a: &[u8], b: &[u8]) -> Ordering {
+    match (parse_general(a), parse_general(b)) {
+        (Some(x), Some(y)) => x.partial_cmp(&y).unwrap_or(Ordering::Equal),
+        (Some(_), None) => Ordering::Greater,
+        (None, Some(_)) => Ordering::Less,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+/// Parse the comparison suffix of a key field, e.g. `n` in `1n`. The suffix is a combination of
+/// `n` or `g` for the kind, `f` or `u` for the ASCII or Unicode case folding, `t` for trimming and
+/// `r` for the descending order.
+pub fn parse_compare(suffix: &str) -> Option<Compare> {
+    let mut cmp = Compare::default();
+    for c in suffix.chars() {
+        match c {
+            'n' | 'g' if cmp.kind != Kind::Bytes => return None,
+            'n' => cmp.kind = Kind::Numeric,
+            'g' => cmp.kind = Kind::General,
+            'f' | 'u' if cmp.fold != Fold::None => return None,
+            'f' => cmp.fold = Fold::Ascii,
+            'u' => cmp.fold = Fold::Unicode,
+            't' => cmp.trim = true,
+            'r' => cmp.reverse = true,
+            _ => return None,
+        }
+    }
+    Some(cmp)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compare() {
+        struct TestCase {
+            cmp: Compare,
+            a: String,
+            b: String,
+            want: Ordering,
+        }
+
+        let test_cases = vec![
+            TestCase { cmp: Compare::new(Kind::Bytes), a: "9".into(), b: "10".into(), want: Ordering::Greater },
+            TestCase { cmp: Compare::new(Kind::Numeric), a: "9".into(), b: "10".into(), want: Ordering::Less },
+            TestCase { cmp: Compare::new(Kind::Numeric), a: "007".into(), b: "7".into(), want: Ordering::Equal },
+            TestCase { cmp: Compare::new(Kind::Numeric), a: "+7".into(), b: " 7.00".into(), want: Ordering::Equal },
+            TestCase { cmp: Compare::new(Kind::Numeric), a: "-0".into(), b: "0.0".into(), want: Ordering::Equal },
+            TestCase { cmp: Compare::new(Kind::Numeric), a: "-2".into(), b: "-10".into(), want: Ordering::Greater },
+            TestCase { cmp: Compare::new(Kind::Numeric), a: "-1.5".into(), b: "1".into(), want: Ordering::Less },
+            TestCase { cmp: Compare::new(Kind::Numeric), a: "1.5".into(), b: "1.49".into(), want: Ordering::Greater },
+            TestCase { cmp: Compare::new(Kind::Numeric), a: ".5".into(), b: "0.50".into(), want: Ordering::Equal },
+            TestCase { cmp: Compare::new(Kind::Numeric), a: "12abc".into(), b: "12".into(), want: Ordering::Equal },
+            TestCase { cmp: Compare::new(Kind::Numeric), a: "abc".into(), b: "-1".into(), want: Ordering::Greater },
+            TestCase { cmp: Compare::new(Kind::General), a: "1e3".into(), b: "999.5".into(), want: Ordering::Greater },
+            TestCase { cmp: Compare::new(Kind::General), a: "-0".into(), b: "0".into(), want: Ordering::Equal },
+            TestCase { cmp: Compare::new(Kind::General), a: "abc".into(), b: "-1e9".into(), want: Ordering::Less },
+            TestCase { cmp: Compare::new(Kind::General), a: "abc".into(), b: "xyz".into(), want: Ordering::Equal },
+            TestCase { cmp: Compare::new(Kind::Bytes), a: "abc".into(), b: "ABC".into(), want: Ordering::Greater },
+            TestCase {
+                cmp: Compare::new(Kind::Bytes).with_fold(Fold::Ascii),
+                a: "abc".into(),
+                b: "ABC".into(),
+                want: Ordering::Equal,
+            },
+            TestCase {
+                cmp: Compare::new(Kind::Bytes).with_fold(Fold::Ascii),
+                a: "B".into(),
+                b: "a".into(),
+                want: Ordering::Greater,
+            },
+            TestCase {
+                cmp: Compare::new(Kind::Bytes).with_fold(Fold::Ascii),
+                a: "Straße".into(),
+                b: "STRASSE".into(),
+                want: Ordering::Greater,
+            },
+            TestCase {
+                cmp: Compare::new(Kind::Bytes).with_fold(Fold::Ascii),
+                a: "Żółw".into(),
+                b: "żółw".into(),
+                want: Ordering::Less,
+            },
+            TestCase {
+                cmp: Compare::new(Kind::Bytes).with_fold(Fold::Unicode),
+                a: "Żółw".into(),
+                b: "żÓŁW".into(),
+                want: Ordering::Equal,
+            },
+            TestCase {
+                cmp: Compare::new(Kind::Bytes).with_fold(Fold::Unicode),
+                a: "ΣΟΦΟΣ".into(),
+                b: "σοφος".into(),
+                want: Ordering::Equal,
+            },
+            TestCase {
+                cmp: Compare::new(Kind::Bytes).with_fold(Fold::Ascii),
+                a: "_".into(),
+                b: "a".into(),
+                want: Ordering::Greater,
+            },
+            TestCase {
+                cmp: Compare::new(Kind::Bytes).with_fold(Fold::Unicode),
+                a: "_".into(),
+                b: "a".into(),
+                want: Ordering::Less,
+            },
+            TestCase {
+                cmp: Compare::new(Kind::Bytes).with_fold(Fold::Unicode),
+                a: "b".into(),
+                b: "Ą".into(),
+                want: Ordering::Less,
+            },
+            TestCase { cmp: Compare::new(Kind::Bytes), a: " a ".into(), b: "a".into(), want: Ordering::Less },
+            TestCase {
+                cmp: Compare::new(Kind::Bytes).with_trim(true),
+                a: " a\t".into(),
+                b: "a".into(),
+                want: Ordering::Equal,
+            },
+            TestCase {
+                cmp: Compare::new(Kind::Bytes).with_trim(true),
+                a: " ".into(),
+                b: "".into(),
+                want: Ordering::Equal,
+            },
+            TestCase {
+                cmp: Compare::new(Kind::Bytes).with_fold(Fold::Ascii).with_trim(true),
+                a: "  Key ".into(),
+                b: "kEY".into(),
+                want: Ordering::Equal,
+            },
+            TestCase {
+                cmp: Compare::new(Kind::Bytes).with_reverse(true),
+                a: "a".into(),
+                b: "b".into(),
+                want: Ordering::Greater,
+            },
+            TestCase {
+                cmp: Compare::new(Kind::Numeric).with_reverse(true),
+                a: "9".into(),
+                b: "10".into(),
+                want: Ordering::Greater,
+            },
+            TestCase {
+                cmp: Compare::new(Kind::Numeric).with_reverse(true),
+                a: "010".into(),
+                b: "10".into(),
+                want: Ordering::Equal,
+            },
+            TestCase {
+                cmp: Compare::new(Kind::General).with_trim(true),
+                a: " 1e3\n".into(),
+                b: "1000".into(),
+                want: Ordering::Equal,
+            },
+        ];
+
+        for t in test_cases {
+            let TestCase { cmp, a, b, want } = t;
+            assert_eq!(cmp.cmp(a.as_bytes(), b.as_bytes()), want, "{:?} {} {}", cmp, a, b);
+
+            let mut enc_a = Vec::new();
+            let mut enc_b = Vec::new();
+            cmp.encode(a.as_bytes(), &mut enc_a);
+            cmp.encode(b.as_bytes(), &mut enc_b);
+            assert_eq!(enc_a == enc_b, want == Ordering::Equal, "{:?} {} {}", cmp, a, b);
+        }
+    }
+
+    #[test]
+    fn test_parse_compare() {
+        assert_eq!(parse_compare(""), Some(Compare::new(Kind::Bytes)));
+        assert_eq!(parse_compare("n"), Some(Compare::new(Kind::Numeric)));
+        assert_eq!(parse_compare("g"), Some(Compare::new(Kind::General)));
+        assert_eq!(parse_compare("ft"), Some(Compare::new(Kind::Bytes).with_fold(Fold::Ascii).with_trim(true)));
+        assert_eq!(parse_compare("tu"), Some(Compare::new(Kind::Bytes).with_fold(Fold::Unicode).with_trim(true)));
+        assert_eq!(parse_compare("rn"), Some(Compare::new(Kind::Numeric).with_reverse(true)));
+        assert_eq!(parse_compare("ng"), None);
+        assert_eq!(parse_compare("fu"), None);
+        assert_eq!(parse_compare("x"), None);
+    }
+
+    #[test]
+    fn test_value() {
+        struct TestCase {
+            cmp: Compare,
+            field: String,
+            want: Option<f64>,
+        }
+
+        let test_cases = vec![
+            TestCase { cmp: Compare::new(Kind::Numeric), field: "007".into(), want: Some(7.0) },
+            TestCase { cmp: Compare::new(Kind::Numeric), field: " -1.50".into(), want: Some(-1.5) },
+            TestCase { cmp: Compare::new(Kind::Numeric), field: "12ms".into(), want: Some(12.0) },
+            TestCase { cmp: Compare::new(Kind::Numeric), field: "x".into(), want: Some(0.0) },
+            TestCase { cmp: Compare::new(Kind::General), field: "1e3".into(), want: Some(1000.0) },
+            TestCase { cmp: Compare::new(Kind::General), field: "x".into(), want: None },
+            TestCase { cmp: Compare::new(Kind::Bytes), field: "1".into(), want: None },
+        ];
+
+        for t in test_cases {
+            assert_eq!(t.cmp.value(t.field.as_bytes()), t.want, "{}", t.field);
+        }
+    }
+}
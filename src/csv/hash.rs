@@ -0,0 +1,236 @@
+use super::basic::push_record;
+use super::compare::Compare;
+use csvroll::parser::{Parser, Index,};
+use csvroll::error::{Error, ErrorKind,};
+use std::collections::HashMap;
+use std::ops::Range;
+use std::io;
+
+/// An in-memory table of records indexed by their key fields.
+///
+/// The records are copied into a single buffer, so they can be printed like the records of any
+/// other input. The records with equal keys form an entry, which is either matched or not.
+pub struct HashTable {
+    buf: Vec<u8>,
+    idx: Index,
+    key_idx: Vec<usize>,
+    cmp: Vec<Compare>,
+    map: HashMap<Vec<u8>, usize>,
+    // the records of each entry
+    entries: Vec<Vec<usize>>,
+    // the entry of each record
+    entry_of: Vec<usize>,
+    matched: Vec<bool>,
+}
+
+impl HashTable {
+    /// Read all the records from `parser` into the table.
+    pub fn load<R: io::Read>(parser: Parser<R>, key_idx: Vec<usize>) -> Result<Self, Error> {
+        Self::load_with_compare(parser, key_idx, Vec::new())
+    }
+
+    /// Read all the records from `parser` into the table, the keys are equal according to `cmp`.
+    pub fn load_with_compare<R: io::Read>(
+        mut parser: Parser<R>,
+        key_idx: Vec<usize>,
+        cmp: Vec<Compare>,
+    ) -> Result<Self, Error> {
+        let mut table = HashTable {
+            buf: Vec::new(),
+            idx: Index::new(),
+            key_idx,
+            cmp,
+            map: HashMap::new(),
+            entries: Vec::new(),
+            entry_of: Vec::new(),
+            matched: Vec::new(),
+        };
+        let mut key = Vec::new();
+
+        loop {
+            let is_buf_full = parser.parse()?;
+            let n = {
+                let (buf, idx) = parser.output();
+                let fields = idx.fields();
+                let mut start = 0;
+                for (i, &end) in idx.records().iter().enumerate() {
+                    if !table.push(buf, &fields[start..end], &mut key) {
+                        return Err(Error::at(ErrorKind::ShortRecord, parser.position(i)));
+                    }
+                    start = end;
+                }
+                idx.records().len()
+            };
+            parser.consume(n);
+            if !is_buf_full {
+                return Ok(table);
+            }
+        }
+    }
+
+    // returns false, if the record has less fields than the key
+    fn push(
+        &mut self,
+        buf: &[u8],
+        rec: &[Range<usize>],
+        key: &mut Vec<u8>,
+    ) -> bool {
+        let n = self.entry_of.len();
+        if !key_bytes(buf, rec, &self.key_idx, &self.cmp, key) {
+            return false;
+        }
+
+        // the record has at least as many fields as the key
+        push_record(&mut self.buf, &mut self.idx, buf, rec);
+
+        let entry = match self.map.get(key.as_slice()) {
+            Some(&e) => e,
+            None => {
+                let e = self.entries.len();
+                self.map.insert(key.clone(), e);
+                self.entries.push(Vec::new());
+                self.matched.push(false);
+                e
+            }
+        };
+        self.entries[entry].push(n);
+        self.entry_of.push(entry);
+        true
+    }
+
+    /// Return the key fields of the table.
+    #[inline]
+    pub fn key_idx(&self) -> &[usize] {
+        &self.key_idx
+    }
+
+    /// Return the comparison of each key field.
+    #[inline]
+    pub fn compare(&self) -> &[Compare] {
+        &self.cmp
+    }
+
+    /// Return the number of records in the table.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.entry_of.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.entry_of.is_empty()
+    }
+
+    /// Return the number of fields of the first record, if any.
+    #[inline]
+    pub fn field_count(&self) -> Option<usize> {
+        self.idx.get_record(0).map(|r| r.end - r.start)
+    }
+
+    /// Find the entry with the key encoded by `key_bytes`.
+    #[inline]
+    pub fn find(&self, key: &[u8]) -> Option<usize> {
+        self.map.get(key).cloned()
+    }
+
+    /// Return the record numbers of the `entry` in the order they were read.
+    #[inline]
+    pub fn records(&self, entry: usize) -> &[usize] {
+        &self.entries[entry]
+    }
+
+    #[inline]
+    pub fn set_matched(&mut self, entry: usize) {
+        self.matched[entry] = true;
+    }
+
+    /// Test if the record number `n` has been matched.
+    #[inline]
+    pub fn is_matched(&self, n: usize) -> bool {
+        self.matched[self.entry_of[n]]
+    }
+
+    #[inline]
+    pub fn buf_index(&self) -> (&[u8], &Index) {
+        (&self.buf, &self.idx)
+    }
+}
+
+/// Encode the key fields of the record `rec` into `out`, so that the keys equal according to
+/// `cmp` have equal encodings. The key fields without a comparison are compared as bytes.
+///
+/// Returns `false` if the record has less fields than the key.
+#[inline]
+pub fn key_bytes(
+    buf: &[u8],
+    rec: &[Range<usize>],
+    key_idx: &[usize],
+    cmp: &[Compare],
+    out: &mut Vec<u8>,
+) -> bool {
+    out.clear();
+    for (i, &k) in key_idx.iter().enumerate() {
+        let f = match rec.get(k) {
+            Some(f) => f.clone(),
+            None => return false,
+        };
+        // the length prefix keeps the encoding of ("ab", "c") and ("a", "bc") apart
+        let start = out.len();
+        out.extend_from_slice(&[0; 8]);
+        cmp.get(i).cloned().unwrap_or_default().encode(&buf[f], out);
+        let len = (out.len() - start - 8) as u64;
+        out[start..(start + 8)].copy_from_slice(&len.to_le_bytes());
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rollbuf::RollBuf;
+    use csvroll::index_builder::IndexBuilder;
+
+    #[test]
+    #[allow(clippy::single_range_in_vec_init)]
+    fn test_hash_table() {
+        let input = "b,0\na,1\nb,2\nc,3";
+        let buf = RollBuf::with_capacity(5, input.as_bytes());
+        let idx_builder = IndexBuilder::new(b',', b'\n');
+        let parser = Parser::from_parts(buf, idx_builder);
+        let mut table = HashTable::load(parser, vec![0]).unwrap();
+
+        {
+            let (buf, idx) = table.buf_index();
+            assert_eq!(buf, b"b,0a,1b,2c,3");
+            assert_eq!(idx, &Index::from_parts(
+                vec![0..1, 2..3, 3..4, 5..6, 6..7, 8..9, 9..10, 11..12],
+                vec![2, 4, 6, 8],
+            ));
+        }
+        assert_eq!(table.len(), 4);
+        assert_eq!(table.field_count(), Some(2));
+
+        let mut key = Vec::new();
+        assert!(key_bytes(b"b", &[0..1], &[0], &[], &mut key));
+        let e = table.find(&key).unwrap();
+        assert_eq!(table.records(e), &[0, 2]);
+        table.set_matched(e);
+        assert_eq!(
+            (0..4).map(|n| table.is_matched(n)).collect::<Vec<_>>(),
+            vec![true, false, true, false],
+        );
+
+        assert!(key_bytes(b"d", &[0..1], &[0], &[], &mut key));
+        assert_eq!(table.find(&key), None);
+        assert!(!key_bytes(b"d", &[0..1], &[1], &[], &mut key));
+    }
+
+    #[test]
+    fn test_hash_table_short_record() {
+        let input = "a,0\nb";
+        let buf = RollBuf::with_capacity(16, input.as_bytes());
+        let idx_builder = IndexBuilder::new(b',', b'\n');
+        let parser = Parser::from_parts(buf, idx_builder);
+        assert!(HashTable::load(parser, vec![1]).is_err());
+    }
+}
@@ -0,0 +1,177 @@
+use super::basic::push_record;
+use super::compare::Compare;
+use csvroll::parser::Index;
+use std::cmp::Ordering;
+use std::mem;
+use std::ops::Range;
+
+/// The records of the intervals, which contain the current point of a sweep over the points in
+/// the sort order, i.e. the intervals which started before the point and did not end yet.
+///
+/// The records are copied into a single buffer, so they can be printed like the records of any
+/// other input. The ended records are dropped from the buffer once they take half of it.
+pub struct Intervals {
+    buf: Vec<u8>,
+    idx: Index,
+    end_idx: usize,
+    cmp: Compare,
+    // the records, which did not end yet
+    live: Vec<bool>,
+    matched: Vec<bool>,
+    ended: usize,
+}
+
+impl Intervals {
+    /// Create a new empty set of the intervals ending with the field `end_idx` of their records,
+    /// which is compared with the points according to `cmp`.
+    pub fn new(end_idx: usize, cmp: Compare) -> Self {
+        Intervals {
+            buf: Vec::new(),
+            idx: Index::new(),
+            end_idx,
+            cmp,
+            live: Vec::new(),
+            matched: Vec::new(),
+            ended: 0,
+        }
+    }
+
+    /// Add the record `rec` of `buf`, which starts not after the current point.
+    ///
+    /// Returns false if the record has no end field.
+    pub fn push(&mut self, buf: &[u8], rec: &[Range<usize>]) -> bool {
+        if rec.len() <= self.end_idx {
+            return false;
+        }
+        push_record(&mut self.buf, &mut self.idx, buf, rec);
+        self.live.push(true);
+        self.matched.push(false);
+        true
+    }
+
+    /// Drop the intervals ending before the `point`, each of them is passed to `f` along with
+    /// the flag telling whether it was matched.
+    pub fn end_before<E, F>(&mut self, point: &[u8], mut f: F) -> Result<(), E>
+        where F: FnMut(&[u8], &Index, usize, bool) -> Result<(), E>,
+    {
+        for n in 0..self.live.len() {
+            if !self.live[n] {
+                continue;
+            }
+            let end = self.idx.get_record(n).map(|r| self.idx.fields()[r.start + self.end_idx].clone());
+            if let Some(end) = end {
+                if self.cmp.cmp(&self.buf[end], point) == Ordering::Less {
+                    self.live[n] = false;
+                    self.ended += 1;
+                    f(&self.buf, &self.idx, n, self.matched[n])?;
+                }
+            }
+        }
+        if self.ended * 2 > self.live.len() {
+            self.compact();
+        }
+        Ok(())
+    }
+
+    /// Drop all the intervals, each of them is passed to `f` like in `end_before`.
+    pub fn end_all<E, F>(&mut self, mut f: F) -> Result<(), E>
+        where F: FnMut(&[u8], &Index, usize, bool) -> Result<(), E>,
+    {
+        for n in 0..self.live.len() {
+            if self.live[n] {
+                f(&self.buf, &self.idx, n, self.matched[n])?;
+            }
+        }
+        self.buf.clear();
+        self.idx = Index::new();
+        self.live.clear();
+        self.matched.clear();
+        self.ended = 0;
+        Ok(())
+    }
+
+    /// Return the numbers of the records of the intervals, which did not end yet.
+    pub fn live(&self) -> impl Iterator<Item = usize> + '_ {
+        self.live.iter().enumerate().filter(|&(_, &l)| l).map(|(n, _)| n)
+    }
+
+    /// Test if all the intervals ended.
+    pub fn is_empty(&self) -> bool {
+        self.ended == self.live.len()
+    }
+
+    /// Mark the record `n` as matched, returns true if it was not matched before.
+    pub fn set_matched(&mut self, n: usize) -> bool {
+        !mem::replace(&mut self.matched[n], true)
+    }
+
+    /// Return the buffer and the index of the records.
+    pub fn buf_index(&self) -> (&[u8], &Index) {
+        (&self.buf, &self.idx)
+    }
+
+    // copy the live records to the start of the buffer
+    fn compact(&mut self) {
+        let mut buf = Vec::with_capacity(self.buf.len());
+        let mut idx = Index::new();
+        let mut matched = Vec::with_capacity(self.live.len() - self.ended);
+        for n in self.live() {
+            if let Some(r) = self.idx.get_record(n) {
+                push_record(&mut buf, &mut idx, &self.buf, &self.idx.fields()[r]);
+                matched.push(self.matched[n]);
+            }
+        }
+        self.live = vec![true; matched.len()];
+        self.buf = buf;
+        self.idx = idx;
+        self.matched = matched;
+        self.ended = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use csv::compare::Kind;
+
+    #[test]
+    #[allow(clippy::single_range_in_vec_init)]
+    fn test_intervals() {
+        let input: &[(&[u8], &[Range<usize>])] = &[
+            (b"1,5,a", &[0..1, 2..3, 4..5]),
+            (b"2,3,b", &[0..1, 2..3, 4..5]),
+            (b"2,10,c", &[0..1, 2..4, 5..6]),
+        ];
+        let mut intervals = Intervals::new(1, Compare::new(Kind::Numeric));
+        for &(buf, rec) in input {
+            assert!(intervals.push(buf, rec));
+        }
+        assert!(!intervals.push(b"3", &[0..1]));
+        assert!(intervals.set_matched(0));
+        assert!(!intervals.set_matched(0));
+
+        let mut ended: Vec<(Vec<u8>, bool)> = Vec::new();
+        let mut end_before = |intervals: &mut Intervals, point: &[u8]| {
+            intervals.end_before(point, |buf, idx, n, matched| -> Result<(), ()> {
+                let r = idx.get_record(n).unwrap();
+                let rec = &idx.fields()[r];
+                ended.push((buf[rec[0].start..rec[rec.len() - 1].end].to_vec(), matched));
+                Ok(())
+            }).unwrap();
+            intervals.live().collect::<Vec<_>>()
+        };
+        assert_eq!(end_before(&mut intervals, b"3"), vec![0, 1, 2]);
+        assert_eq!(end_before(&mut intervals, b"4"), vec![0, 2]);
+        // the second ended record triggers the compaction
+        assert_eq!(end_before(&mut intervals, b"6"), vec![0]);
+        assert!(!intervals.is_empty());
+        assert_eq!(intervals.buf_index().0, b"2,10,c");
+        assert!(intervals.set_matched(0));
+        assert_eq!(end_before(&mut intervals, b"11"), Vec::<usize>::new());
+        assert!(intervals.is_empty());
+        assert_eq!(
+            ended,
+            vec![(b"2,3,b".to_vec(), false), (b"1,5,a".to_vec(), true), (b"2,10,c".to_vec(), true)],
+        );
+    }
+}
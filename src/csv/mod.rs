@@ -1 +1,4 @@
 pub mod basic;
+pub mod compare;
+pub mod hash;
+pub mod interval;
@@ -1,5 +1,10 @@
-use super::printer::Print;
+use super::printer::{Print, Records,};
 use super::csv::basic::{FirstRec, Group, cmp_records,};
+use super::csv::hash::{HashTable, key_bytes,};
+use super::csv::interval::Intervals;
+use super::csv::compare::Compare;
+use csvroll::parser::{Parser, Index,};
+use csvroll::error::{Error as CsvError, ErrorKind, Input,};
 use std::io;
 use std::cmp::Ordering;
 use std::error::Error;
@@ -13,12 +18,15 @@ use std::ops::Range;
 ///   * RIGHT OUTER JOIN - `show_left: false`, `show_right: true`, `show_both: true`
 ///   * FULL OUTER JOIN - `show_left: true`, `show_right: true`, `show_both: true`
 ///
-/// and even exclusive joins (outer joins without the inner part).
+/// and even exclusive joins (outer joins without the inner part). SEMI JOIN and ANTI JOIN, which
+/// print the records of one input only, are created by `JoinOptions::semi` and `JoinOptions::anti`.
 #[derive(Debug, Clone, Copy)]
 pub struct JoinOptions {
     show_left: bool,
     show_right: bool,
     show_both: bool,
+    semi_left: bool,
+    semi_right: bool,
 }
 
 impl Default for JoinOptions {
@@ -27,6 +35,8 @@ impl Default for JoinOptions {
             show_left: false,
             show_right: false,
             show_both: true,
+            semi_left: false,
+            semi_right: false,
         }
     }
 }
@@ -40,27 +50,58 @@ impl JoinOptions {
     /// Create a new instance of `JoinOptions` with the specified options.
     pub fn from_options(show_left: bool, show_right: bool, show_both: bool) -> Self {
         JoinOptions {
-            show_left: show_left,
-            show_right: show_right,
-            show_both: show_both,
+            show_left,
+            show_right,
+            show_both,
+            semi_left: false,
+            semi_right: false,
         }
     }
+
+    /// Create the options of SEMI JOIN, which prints the matched records of the left input, or
+    /// of the right one if not `is_left`. Each record is printed once, no matter how many records
+    /// of the other input match it.
+    pub fn semi(is_left: bool) -> Self {
+        JoinOptions {
+            show_left: false,
+            show_right: false,
+            show_both: false,
+            semi_left: is_left,
+            semi_right: !is_left,
+        }
+    }
+
+    /// Create the options of ANTI JOIN, which prints the unmatched records of the left input, or
+    /// of the right one if not `is_left`.
+    pub fn anti(is_left: bool) -> Self {
+        Self::from_options(is_left, !is_left, false)
+    }
+
+    // test if any records of the left and the right input are printed
+    #[inline]
+    fn shows(&self) -> (bool, bool) {
+        (self.show_left || self.semi_left, self.show_right || self.semi_right)
+    }
 }
 
 /// Join the groups of records `group0` and `group1`. The output is
 /// written into `w` using the provided printer `p`. 
+///
+/// The groups of an input, which are not printed unless they match, are skipped by
+/// `Group::skip_to` up to the key of the other input.
 pub fn join<R0,R1,W,P>(
     group0: &mut Group<R0>,
     group1: &mut Group<R1>,
     w: &mut W,
     mut p: P,
     opts: JoinOptions,
-) -> Result<(), Box<Error>>
+) -> Result<(), Box<dyn Error>>
     where R0: io::Read,
           R1: io::Read,
           W: io::Write,
           P: Print<W>,
 {
+    p.set_field_counts(&[group0.field_count(), group1.field_count()]);
     let mut ord = Ordering::Equal;
     let mut g0: Option<Range<usize>> = None;
     let mut g1: Option<Range<usize>> = None;
@@ -69,30 +110,38 @@ pub fn join<R0,R1,W,P>(
     loop {
         match ord {
             Ordering::Less => {
-                g0 = match group0.next_group() {
+                let res = match g1 {
+                    Some(ref rng1) if !opts.show_left => skip_to(group0, group1, rng1.start),
+                    _ => group0.next_group(),
+                };
+                g0 = match res {
                     Ok(o) => o,
-                    Err(e) => return Err(format!("left input: {}", e).into()),
+                    Err(e) => return Err(e.with_input(Input::Left).into()),
                 };
             }
             Ordering::Greater => {
-                g1 = match group1.next_group() {
+                let res = match g0 {
+                    Some(ref rng0) if !opts.show_right => skip_to(group1, group0, rng0.start),
+                    _ => group1.next_group(),
+                };
+                g1 = match res {
                     Ok(o) => o,
-                    Err(e) => return Err(format!("right input: {}", e).into()),
+                    Err(e) => return Err(e.with_input(Input::Right).into()),
                 };
             }
             Ordering::Equal => {
                 g0 = match group0.next_group() {
                     Ok(o) => o,
-                    Err(e) => return Err(format!("left input: {}", e).into()),
+                    Err(e) => return Err(e.with_input(Input::Left).into()),
                 };
                 g1 = match group1.next_group() {
                     Ok(o) => o,
-                    Err(e) => return Err(format!("right input: {}", e).into()),
+                    Err(e) => return Err(e.with_input(Input::Right).into()),
                 };
             }
         }
-        ord = match (&g0, &g1) {
-            (&Some(ref rng0), &Some(ref rng1)) => {
+        ord = match (g0.as_ref(), g1.as_ref()) {
+            (Some(rng0), Some(rng1)) => {
                 let (buf0, idx0) = group0.buf_index();
                 let (buf1, idx1) = group1.buf_index();
                 r0 = idx0.get_record(rng0.start).unwrap_or(0..0);
@@ -104,33 +153,30 @@ pub fn join<R0,R1,W,P>(
                     &idx1.fields()[r1.clone()],
                     group0.key_idx(),
                     group1.key_idx(),
+                    group0.compare(),
                     ) {
 
                     Ok(ord) => {
                         match ord {
                             Ordering::Less => {
                                 if opts.show_left {
-                                    p.print_left(w, buf0, idx0.fields(), idx0.records(), rng0.clone())?;
+                                    print_group(group0, rng0.clone(), true, w, &mut p)?;
                                 }
                             }
                             Ordering::Greater => {
                                 if opts.show_right {
-                                    p.print_right(w, buf1, idx1.fields(), idx1.records(), rng1.clone())?;
+                                    print_group(group1, rng1.clone(), false, w, &mut p)?;
                                 }
                             }
                             Ordering::Equal => {
                                 if opts.show_both {
-                                    p.print_both(
-                                        w,
-                                        buf0,
-                                        buf1,
-                                        idx0.fields(),
-                                        idx1.fields(),
-                                        idx0.records(),
-                                        idx1.records(),
-                                        rng0.clone(),
-                                        rng1.clone(),
-                                    )?;
+                                    print_groups(group0, group1, rng0.clone(), rng1.clone(), w, &mut p)?;
+                                }
+                                if opts.semi_left {
+                                    print_group(group0, rng0.clone(), true, w, &mut p)?;
+                                }
+                                if opts.semi_right {
+                                    print_group(group1, rng1.clone(), false, w, &mut p)?;
                                 }
                             }
                         }
@@ -141,27 +187,815 @@ pub fn join<R0,R1,W,P>(
                     }
                 }
             }
-            (&Some(ref rng0), &None) => {
-                let (buf0, idx0) = group0.buf_index();
+            (Some(rng0), None) => {
                 if opts.show_left {
-                    p.print_left(w, buf0, idx0.fields(), idx0.records(), rng0.clone())?;
+                    print_group(group0, rng0.clone(), true, w, &mut p)?;
                 } else {
                     return Ok(());
                 }
                 Ordering::Less
             }
-            (&None, &Some(ref rng1)) => {
-                let (buf1, idx1) = group1.buf_index();
+            (None, Some(rng1)) => {
                 if opts.show_right {
-                    p.print_right(w, buf1, idx1.fields(), idx1.records(), rng1.clone())?;
+                    print_group(group1, rng1.clone(), false, w, &mut p)?;
                 } else {
                     return Ok(());
                 }
                 Ordering::Greater
             }
-            (&None, &None) => return Ok(()),
+            (None, None) => return Ok(()),
+        }
+    }
+}
+
+// Skip the groups of `group` up to the key of the record `n` of `other`.
+#[inline]
+fn skip_to<R0,R1>(
+    group: &mut Group<R0>,
+    other: &Group<R1>,
+    n: usize,
+) -> Result<Option<Range<usize>>, CsvError>
+    where R0: io::Read,
+          R1: io::Read,
+{
+    let (buf, idx) = other.buf_index();
+    let rec = idx.get_record(n).unwrap_or(0..0);
+    group.skip_to(buf, &idx.fields()[rec], other.key_idx())
+}
+
+// Print the left or the right records of the group `rng` of `group`, the spilled records first.
+fn print_group<R,W,P>(
+    group: &Group<R>,
+    rng: Range<usize>,
+    is_left: bool,
+    w: &mut W,
+    p: &mut P,
+) -> Result<(), Box<dyn Error>>
+    where R: io::Read,
+          W: io::Write,
+          P: Print<W>,
+{
+    let mut print = |buf: &[u8], idx: &Index, rng: Range<usize>| {
+        if is_left {
+            p.print_left(w, buf, idx.fields(), idx.records(), rng)
+        } else {
+            p.print_right(w, buf, idx.fields(), idx.records(), rng)
+        }
+    };
+    if let Some(file) = group.spilled() {
+        file.replay(|buf, idx| print(buf, idx, 0..idx.records().len()))?;
+    }
+    let (buf, idx) = group.buf_index();
+    print(buf, idx, rng)
+}
+
+// Print the cartesian product of the groups `rng0` of `group0` and `rng1` of `group1` in the order
+// of the left records. The spilled right records are replayed for each left record.
+fn print_groups<R0,R1,W,P>(
+    group0: &Group<R0>,
+    group1: &Group<R1>,
+    rng0: Range<usize>,
+    rng1: Range<usize>,
+    w: &mut W,
+    p: &mut P,
+) -> Result<(), Box<dyn Error>>
+    where R0: io::Read,
+          R1: io::Read,
+          W: io::Write,
+          P: Print<W>,
+{
+    let (buf1, idx1) = group1.buf_index();
+    let spilled1 = group1.spilled();
+    let mut print = |buf0: &[u8], idx0: &Index, rng0: Range<usize>| -> Result<(), Box<dyn Error>> {
+        let file1 = match spilled1 {
+            Some(file1) => file1,
+            None => {
+                return p.print_both(
+                    w,
+                    buf0,
+                    buf1,
+                    idx0.fields(),
+                    idx1.fields(),
+                    idx0.records(),
+                    idx1.records(),
+                    rng0,
+                    rng1.clone(),
+                );
+            }
+        };
+        for r0 in rng0 {
+            file1.replay(|b1, i1| p.print_both(
+                w,
+                buf0,
+                b1,
+                idx0.fields(),
+                i1.fields(),
+                idx0.records(),
+                i1.records(),
+                r0..(r0 + 1),
+                0..i1.records().len(),
+            ))?;
+            p.print_both(
+                w,
+                buf0,
+                buf1,
+                idx0.fields(),
+                idx1.fields(),
+                idx0.records(),
+                idx1.records(),
+                r0..(r0 + 1),
+                rng1.clone(),
+            )?;
+        }
+        Ok(())
+    };
+    if let Some(file0) = group0.spilled() {
+        file0.replay(|buf0, idx0| print(buf0, idx0, 0..idx0.records().len()))?;
+    }
+    let (buf0, idx0) = group0.buf_index();
+    print(buf0, idx0, rng0)
+}
+
+/// The direction, in which the right key nearest to the left key is searched for by `asof_join`.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Direction {
+    /// The last right record with the key not after the left key in the sort order.
+    Backward,
+    /// The first right record with the key not before the left key in the sort order.
+    Forward,
+    /// The nearer of the backward and the forward record, the backward one on a tie.
+    Nearest,
+}
+
+/// Options defining the output of the as-of join, see `asof_join`.
+#[derive(Debug, Clone, Copy)]
+pub struct AsofOptions {
+    direction: Direction,
+    tolerance: Option<f64>,
+    show_left: bool,
+}
+
+impl AsofOptions {
+    /// Create a new instance of `AsofOptions` searching the right key in the `direction`.
+    pub fn new(direction: Direction) -> Self {
+        AsofOptions {
+            direction,
+            tolerance: None,
+            show_left: false,
+        }
+    }
+
+    /// Pair only the right records, whose as-of key is at most `tolerance` away from the left one.
+    /// The distance is known only for the numeric keys.
+    pub fn with_tolerance(mut self, tolerance: f64) -> Self {
+        self.tolerance = Some(tolerance);
+        self
+    }
+
+    /// Print the left records without a right record as well, i.e. LEFT OUTER JOIN.
+    pub fn with_show_left(mut self, show_left: bool) -> Self {
+        self.show_left = show_left;
+        self
+    }
+}
+
+/// Join each left record with the right record with the nearest key in the direction of `opts`,
+/// i.e. an as-of join. The output is written into `w` using the provided printer `p`.
+///
+/// The last key field is the as-of key, the preceding key fields must be equal. Both inputs are
+/// streamed, only the last group of the right records and the first record of the following group
+/// are kept to be paired with the left records.
+pub fn asof_join<R0,R1,W,P>(
+    group0: &mut Group<R0>,
+    group1: &mut Group<R1>,
+    w: &mut W,
+    mut p: P,
+    opts: AsofOptions,
+) -> Result<(), Box<dyn Error>>
+    where R0: io::Read,
+          R1: io::Read,
+          W: io::Write,
+          P: Print<W>,
+{
+    if group0.key_idx().is_empty() || group0.key_idx().len() != group1.key_idx().len() {
+        return Err("the as-of join requires the same number of the key fields of both inputs".into());
+    }
+    p.set_field_counts(&[group0.field_count(), group1.field_count()]);
+    let by = group0.key_idx().len() - 1;
+    let asof_cmp = group0.compare().get(by).cloned().unwrap_or_default();
+    // the last returned right group
+    let mut g1: Option<Range<usize>> = None;
+    loop {
+        let rng0 = match group0.next_group() {
+            Ok(Some(rng)) => rng,
+            Ok(None) => return Ok(()),
+            Err(e) => return Err(e.with_input(Input::Left).into()),
+        };
+        let (buf0, idx0) = group0.buf_index();
+        let rec0 = &idx0.fields()[idx0.get_record(rng0.start).unwrap_or(0..0)];
+        // compare the key, or only the equal key fields, of the right record `n` with the left one
+        let cmp1 = |group1: &Group<R1>, n: usize, key_len: usize| -> Result<Ordering, Box<dyn Error>> {
+            let (buf1, idx1) = group1.buf_index();
+            let rec1 = &idx1.fields()[idx1.get_record(n).unwrap_or(0..0)];
+            cmp_records(
+                buf1,
+                buf0,
+                rec1,
+                rec0,
+                &group1.key_idx()[..key_len],
+                &group0.key_idx()[..key_len],
+                group0.compare(),
+            ).map_err(|_| "internal: the record was not grouped properly".into())
+        };
+        // the distance of the as-of key of the right record `n` from the left one
+        let distance = |group1: &Group<R1>, n: usize| -> Option<f64> {
+            let (buf1, idx1) = group1.buf_index();
+            let rec1 = &idx1.fields()[idx1.get_record(n).unwrap_or(0..0)];
+            let v0 = asof_cmp.value(&buf0[rec0[group0.key_idx()[by]].clone()])?;
+            let v1 = asof_cmp.value(&buf1[rec1[group1.key_idx()[by]].clone()])?;
+            Some((v0 - v1).abs())
+        };
+
+        // move the right input up to the left key, the following group is after it
+        while let Some(next) = group1.peek() {
+            let is_behind = match (opts.direction, cmp1(group1, next, by + 1)?) {
+                (Direction::Forward, ord) => ord == Ordering::Less,
+                (_, ord) => ord != Ordering::Greater,
+            };
+            if !is_behind {
+                break;
+            }
+            g1 = match group1.next_group() {
+                Ok(o) => o,
+                Err(e) => return Err(e.with_input(Input::Right).into()),
+            };
+        }
+        let mut back = None;
+        if let Some(ref rng1) = g1 {
+            if cmp1(group1, rng1.end - 1, by)? == Ordering::Equal {
+                back = Some(rng1.end - 1);
+            }
+        }
+        let mut forward = None;
+        if let Some(next) = group1.peek() {
+            if cmp1(group1, next, by)? == Ordering::Equal {
+                forward = Some(next);
+            }
+        }
+        let found = match opts.direction {
+            Direction::Backward => back,
+            Direction::Forward => forward,
+            Direction::Nearest => match (back, forward) {
+                (Some(b), Some(f)) => match (distance(group1, b), distance(group1, f)) {
+                    (Some(db), Some(df)) if df < db => Some(f),
+                    _ => Some(b),
+                },
+                (b, f) => b.or(f),
+            },
+        };
+        let found = match opts.tolerance {
+            Some(tolerance) => found.filter(|&n| distance(group1, n).is_some_and(|d| d <= tolerance)),
+            None => found,
+        };
+        match found {
+            Some(n) => print_asof(group0, group1, rng0, n, w, &mut p)?,
+            None if opts.show_left => print_group(group0, rng0, true, w, &mut p)?,
+            None => {}
+        }
+    }
+}
+
+// Print the records of the group `rng0` of `group0`, the spilled ones first, each paired with the
+// record `n` of `group1`.
+fn print_asof<R0,R1,W,P>(
+    group0: &Group<R0>,
+    group1: &Group<R1>,
+    rng0: Range<usize>,
+    n: usize,
+    w: &mut W,
+    p: &mut P,
+) -> Result<(), Box<dyn Error>>
+    where R0: io::Read,
+          R1: io::Read,
+          W: io::Write,
+          P: Print<W>,
+{
+    let (buf1, idx1) = group1.buf_index();
+    let mut print = |buf0: &[u8], idx0: &Index, rng0: Range<usize>| p.print_both(
+        w,
+        buf0,
+        buf1,
+        idx0.fields(),
+        idx1.fields(),
+        idx0.records(),
+        idx1.records(),
+        rng0,
+        n..(n + 1),
+    );
+    if let Some(file0) = group0.spilled() {
+        file0.replay(|buf0, idx0| print(buf0, idx0, 0..idx0.records().len()))?;
+    }
+    let (buf0, idx0) = group0.buf_index();
+    print(buf0, idx0, rng0)
+}
+
+/// Join the left records with the right records, whose interval contains the left key, i.e. an
+/// interval join or a range join. The output is written into `w` using the provided printer `p`.
+///
+/// The key of `group1` is the start of the interval, which ends with the field `end_idx`, the key
+/// of `group0` is a single field. Both inputs are streamed, only the intervals containing the
+/// current left key are kept in the memory. The unmatched right records are printed once their
+/// interval ends before the left key.
+pub fn interval_join<R0,R1,W,P>(
+    group0: &mut Group<R0>,
+    group1: &mut Group<R1>,
+    end_idx: usize,
+    w: &mut W,
+    mut p: P,
+    opts: JoinOptions,
+) -> Result<(), Box<dyn Error>>
+    where R0: io::Read,
+          R1: io::Read,
+          W: io::Write,
+          P: Print<W>,
+{
+    if group0.key_idx().len() != 1 || group1.key_idx().len() != 1 {
+        return Err("the interval join requires a single key field of both inputs".into());
+    }
+    p.set_field_counts(&[group0.field_count(), group1.field_count()]);
+    let cmp = group0.compare().first().cloned().unwrap_or_default();
+    let mut intervals = Intervals::new(end_idx, cmp);
+    loop {
+        let rng0 = match group0.next_group() {
+            Ok(Some(rng)) => rng,
+            Ok(None) => break,
+            Err(e) => return Err(e.with_input(Input::Left).into()),
+        };
+        let (buf0, idx0) = group0.buf_index();
+        let rec0 = &idx0.fields()[idx0.get_record(rng0.start).unwrap_or(0..0)];
+        let point = &buf0[rec0[group0.key_idx()[0]].clone()];
+
+        // add the intervals starting up to the left key
+        while let Some(next) = group1.peek() {
+            let (buf1, idx1) = group1.buf_index();
+            let rec1 = &idx1.fields()[idx1.get_record(next).unwrap_or(0..0)];
+            match cmp_records(buf1, buf0, rec1, rec0, group1.key_idx(), group0.key_idx(), group0.compare()) {
+                Ok(Ordering::Greater) => break,
+                Ok(_) => {}
+                Err(_) => return Err("internal: the record was not grouped properly".into()),
+            }
+            let rng1 = match group1.next_group() {
+                Ok(Some(rng)) => rng,
+                Ok(None) => break,
+                Err(e) => return Err(e.with_input(Input::Right).into()),
+            };
+            push_intervals(&mut intervals, group1, rng1)?;
+        }
+        intervals.end_before(point, |buf, idx, n, matched| {
+            if opts.show_right && !matched {
+                p.print_right(w, buf, idx.fields(), idx.records(), n..(n + 1))
+            } else {
+                Ok(())
+            }
+        })?;
+
+        if intervals.is_empty() {
+            if opts.show_left {
+                print_group(group0, rng0, true, w, &mut p)?;
+            }
+            continue;
+        }
+        if opts.semi_left {
+            print_group(group0, rng0.clone(), true, w, &mut p)?;
+        }
+        if opts.show_both {
+            print_intervals(group0, &intervals, rng0, w, &mut p)?;
+        }
+        let live: Vec<usize> = intervals.live().collect();
+        for n in live {
+            if intervals.set_matched(n) && opts.semi_right {
+                let (buf1, idx1) = intervals.buf_index();
+                p.print_right(w, buf1, idx1.fields(), idx1.records(), n..(n + 1))?;
+            }
+        }
+    }
+
+    if opts.show_right {
+        intervals.end_all(|buf, idx, n, matched| {
+            if matched {
+                Ok(())
+            } else {
+                p.print_right(w, buf, idx.fields(), idx.records(), n..(n + 1))
+            }
+        })?;
+        loop {
+            match group1.next_group() {
+                Ok(Some(rng1)) => print_group(group1, rng1, false, w, &mut p)?,
+                Ok(None) => return Ok(()),
+                Err(e) => return Err(e.with_input(Input::Right).into()),
+            }
+        }
+    }
+    Ok(())
+}
+
+// Add the records of the group `rng` of `group`, the spilled records first, to `intervals`.
+fn push_intervals<R: io::Read>(
+    intervals: &mut Intervals,
+    group: &Group<R>,
+    rng: Range<usize>,
+) -> Result<(), Box<dyn Error>> {
+    let short = || CsvError::new(ErrorKind::ShortRecord).with_input(Input::Right);
+    if let Some(file) = group.spilled() {
+        file.replay(|buf, idx| {
+            let fields = idx.fields();
+            let mut start = 0;
+            for &end in idx.records() {
+                if !intervals.push(buf, &fields[start..end]) {
+                    return Err(short().into());
+                }
+                start = end;
+            }
+            Ok(())
+        })?;
+    }
+    let (buf, idx) = group.buf_index();
+    for n in rng {
+        let rec = &idx.fields()[idx.get_record(n).unwrap_or(0..0)];
+        if !intervals.push(buf, rec) {
+            return Err(short().with_position(group.position(n)).into());
+        }
+    }
+    Ok(())
+}
+
+// Print the records of the group `rng0` of `group0`, the spilled ones first, each paired with the
+// live records of `intervals` in their order.
+fn print_intervals<R,W,P>(
+    group0: &Group<R>,
+    intervals: &Intervals,
+    rng0: Range<usize>,
+    w: &mut W,
+    p: &mut P,
+) -> Result<(), Box<dyn Error>>
+    where R: io::Read,
+          W: io::Write,
+          P: Print<W>,
+{
+    let (buf1, idx1) = intervals.buf_index();
+    let mut print = |buf0: &[u8], idx0: &Index, rng0: Range<usize>| -> Result<(), Box<dyn Error>> {
+        for r0 in rng0 {
+            for n in intervals.live() {
+                p.print_both(
+                    w,
+                    buf0,
+                    buf1,
+                    idx0.fields(),
+                    idx1.fields(),
+                    idx0.records(),
+                    idx1.records(),
+                    r0..(r0 + 1),
+                    n..(n + 1),
+                )?;
+            }
+        }
+        Ok(())
+    };
+    if let Some(file0) = group0.spilled() {
+        file0.replay(|buf0, idx0| print(buf0, idx0, 0..idx0.records().len()))?;
+    }
+    let (buf0, idx0) = group0.buf_index();
+    print(buf0, idx0, rng0)
+}
+
+/// Options defining the output of the join of any number of inputs.
+///
+/// A row is printed only if all the `required` inputs are present in it, e.g. all inputs required
+/// give INNER JOIN, only the first input required gives LEFT OUTER JOIN and no input required gives
+/// FULL OUTER JOIN.
+#[derive(Debug, Clone)]
+pub struct MultiJoinOptions {
+    required: Vec<bool>,
+}
+
+impl MultiJoinOptions {
+    /// Create a new instance of `MultiJoinOptions` with all the `inputs` required.
+    pub fn new(inputs: usize) -> Self {
+        MultiJoinOptions {
+            required: vec![true; inputs],
+        }
+    }
+
+    /// Create a new instance of `MultiJoinOptions` with the specified required inputs.
+    pub fn from_required(required: Vec<bool>) -> Self {
+        MultiJoinOptions {
+            required,
         }
     }
+
+    #[inline]
+    fn is_printed(&self, present: &[bool]) -> bool {
+        self.required.iter().zip(present).all(|(&r, &p)| !r || p)
+    }
+}
+
+/// Join the groups of records of any number of inputs. The output is written into `w` using the
+/// provided printer `p`.
+///
+/// The rows are formed by the groups with the lowest key, the inputs whose group has a higher
+/// key are missing from the row.
+pub fn join_all<R,W,P>(
+    groups: &mut [Group<R>],
+    w: &mut W,
+    mut p: P,
+    opts: &MultiJoinOptions,
+) -> Result<(), Box<dyn Error>>
+    where R: io::Read,
+          W: io::Write,
+          P: Print<W>,
+{
+    if opts.required.len() != groups.len() {
+        return Err("the number of the inputs differs from the join options".into());
+    }
+    let counts: Vec<Option<usize>> = groups.iter().map(|g| g.field_count()).collect();
+    p.set_field_counts(&counts);
+
+    let mut g: Vec<Option<Range<usize>>> = vec![None; groups.len()];
+    let mut present = vec![true; groups.len()];
+    loop {
+        // advance the inputs which were printed in the last row
+        for (i, group) in groups.iter_mut().enumerate() {
+            if present[i] {
+                g[i] = match group.next_group() {
+                    Ok(o) => o,
+                    Err(e) => return Err(e.with_input(Input::Nth(i)).into()),
+                };
+                if group.spilled().is_some() {
+                    return Err("the spilled groups are supported only when joining two inputs".into());
+                }
+            }
+        }
+        // no more rows can be printed once a required input is exhausted
+        if g.iter().zip(&opts.required).any(|(g, &r)| r && g.is_none()) {
+            return Ok(());
+        }
+
+        // find the inputs with the lowest key
+        let mut min: Option<usize> = None;
+        for i in 0..groups.len() {
+            let rng = match g[i] {
+                Some(ref rng) => rng,
+                None => {
+                    present[i] = false;
+                    continue;
+                }
+            };
+            let m = match min {
+                Some(m) => m,
+                None => {
+                    min = Some(i);
+                    present[i] = true;
+                    continue;
+                }
+            };
+            let ord = {
+                let (buf_m, idx_m) = groups[m].buf_index();
+                let (buf_i, idx_i) = groups[i].buf_index();
+                let r_m = idx_m.get_record(g[m].as_ref().map_or(0, |r| r.start)).unwrap_or(0..0);
+                let r_i = idx_i.get_record(rng.start).unwrap_or(0..0);
+                match cmp_records(
+                    buf_i,
+                    buf_m,
+                    &idx_i.fields()[r_i],
+                    &idx_m.fields()[r_m],
+                    groups[i].key_idx(),
+                    groups[m].key_idx(),
+                    groups[m].compare(),
+                ) {
+                    Ok(ord) => ord,
+                    Err(_) => return Err("internal: the record was not grouped properly".into()),
+                }
+            };
+            match ord {
+                Ordering::Less => {
+                    for p in &mut present[..i] {
+                        *p = false;
+                    }
+                    min = Some(i);
+                    present[i] = true;
+                }
+                Ordering::Equal => present[i] = true,
+                Ordering::Greater => present[i] = false,
+            }
+        }
+        if min.is_none() {
+            return Ok(());
+        }
+
+        if opts.is_printed(&present) {
+            let inputs: Vec<Option<Records>> = groups.iter().enumerate().map(|(i, group)| {
+                if !present[i] {
+                    return None;
+                }
+                let (buf, idx) = group.buf_index();
+                g[i].as_ref().map(|rng| Records {
+                    buf,
+                    fields: idx.fields(),
+                    records: idx.records(),
+                    print: rng.clone(),
+                })
+            }).collect();
+            p.print_many(w, &inputs)?;
+        }
+    }
+}
+
+/// The input which is loaded into the memory by `hash_join`.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum BuildSide {
+    Left,
+    Right,
+}
+
+/// Join the records of the inputs which need not be sorted. The records of the `build` side are
+/// loaded into a hash table, the records of the other side are streamed through it. The output is
+/// written into `w` using the provided printer `p`.
+///
+/// The matched and the unmatched streamed records are printed in the order they are read, the
+/// unmatched records of the build side are printed at the end.
+#[allow(clippy::too_many_arguments)]
+pub fn hash_join<R0,R1,W,P>(
+    parser0: Parser<R0>,
+    parser1: Parser<R1>,
+    key_idx0: Vec<usize>,
+    key_idx1: Vec<usize>,
+    cmp: Vec<Compare>,
+    build: BuildSide,
+    w: &mut W,
+    mut p: P,
+    opts: JoinOptions,
+) -> Result<(), Box<dyn Error>>
+    where R0: io::Read,
+          R1: io::Read,
+          W: io::Write,
+          P: Print<W>,
+{
+    match build {
+        BuildSide::Left => {
+            let mut table = match HashTable::load_with_compare(parser0, key_idx0, cmp) {
+                Ok(t) => t,
+                Err(e) => return Err(e.with_input(Input::Left).into()),
+            };
+            p.set_field_counts(&[table.field_count(), None]);
+            probe(&mut table, parser1, &key_idx1, build, w, &mut p, opts)?;
+            if opts.show_left || opts.semi_left {
+                print_table(&table, build, opts.semi_left, w, &mut p)?;
+            }
+        }
+        BuildSide::Right => {
+            let mut table = match HashTable::load_with_compare(parser1, key_idx1, cmp) {
+                Ok(t) => t,
+                Err(e) => return Err(e.with_input(Input::Right).into()),
+            };
+            p.set_field_counts(&[None, table.field_count()]);
+            probe(&mut table, parser0, &key_idx0, build, w, &mut p, opts)?;
+            if opts.show_right || opts.semi_right {
+                print_table(&table, build, opts.semi_right, w, &mut p)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Stream the records of `parser` through the `table`.
+fn probe<R,W,P>(
+    table: &mut HashTable,
+    mut parser: Parser<R>,
+    key_idx: &[usize],
+    build: BuildSide,
+    w: &mut W,
+    p: &mut P,
+    opts: JoinOptions,
+) -> Result<(), Box<dyn Error>>
+    where R: io::Read,
+          W: io::Write,
+          P: Print<W>,
+{
+    let (show_probe, semi_probe, input) = match build {
+        BuildSide::Left => (opts.show_right, opts.semi_right, Input::Right),
+        BuildSide::Right => (opts.show_left, opts.semi_left, Input::Left),
+    };
+    let mut key = Vec::new();
+    let mut rec_count = 0;
+
+    loop {
+        let is_buf_full = parser.parse().map_err(|e| e.with_input(input))?;
+        let n = {
+            let (buf, idx) = parser.output();
+            let fields = idx.fields();
+            let records = idx.records();
+            let mut start = 0;
+            for (i, &end) in records.iter().enumerate() {
+                if rec_count == 0 {
+                    match build {
+                        BuildSide::Left => p.set_field_counts(&[None, Some(end - start)]),
+                        BuildSide::Right => p.set_field_counts(&[Some(end - start), None]),
+                    }
+                }
+                rec_count += 1;
+                if !key_bytes(buf, &fields[start..end], key_idx, table.compare(), &mut key) {
+                    let pos = parser.position(i);
+                    return Err(CsvError::at(ErrorKind::ShortRecord, pos).with_input(input).into());
+                }
+                match table.find(&key) {
+                    Some(e) => {
+                        if opts.show_both {
+                            let (tbuf, tidx) = table.buf_index();
+                            for &m in table.records(e) {
+                                match build {
+                                    BuildSide::Left => p.print_both(
+                                        w,
+                                        tbuf,
+                                        buf,
+                                        tidx.fields(),
+                                        fields,
+                                        tidx.records(),
+                                        records,
+                                        m..(m + 1),
+                                        i..(i + 1),
+                                    )?,
+                                    BuildSide::Right => p.print_both(
+                                        w,
+                                        buf,
+                                        tbuf,
+                                        fields,
+                                        tidx.fields(),
+                                        records,
+                                        tidx.records(),
+                                        i..(i + 1),
+                                        m..(m + 1),
+                                    )?,
+                                }
+                            }
+                        }
+                        if semi_probe {
+                            match build {
+                                BuildSide::Left => p.print_right(w, buf, fields, records, i..(i + 1))?,
+                                BuildSide::Right => p.print_left(w, buf, fields, records, i..(i + 1))?,
+                            }
+                        }
+                        table.set_matched(e);
+                    }
+                    None => {
+                        if show_probe {
+                            match build {
+                                BuildSide::Left => p.print_right(w, buf, fields, records, i..(i + 1))?,
+                                BuildSide::Right => p.print_left(w, buf, fields, records, i..(i + 1))?,
+                            }
+                        }
+                    }
+                }
+                start = end;
+            }
+            records.len()
+        };
+        parser.consume(n);
+        if !is_buf_full {
+            return Ok(());
+        }
+    }
+}
+
+/// Print the records of the `table` which were matched by a streamed record, if `matched`, or
+/// the records which were not matched by any.
+fn print_table<W,P>(
+    table: &HashTable,
+    build: BuildSide,
+    matched: bool,
+    w: &mut W,
+    p: &mut P,
+) -> Result<(), Box<dyn Error>>
+    where W: io::Write,
+          P: Print<W>,
+{
+    let (buf, idx) = table.buf_index();
+    let mut n = 0;
+    while n < table.len() {
+        if table.is_matched(n) != matched {
+            n += 1;
+            continue;
+        }
+        // print the consecutive records at once
+        let start = n;
+        while n < table.len() && table.is_matched(n) == matched {
+            n += 1;
+        }
+        match build {
+            BuildSide::Left => p.print_left(w, buf, idx.fields(), idx.records(), start..n)?,
+            BuildSide::Right => p.print_right(w, buf, idx.fields(), idx.records(), start..n)?,
+        }
+    }
+    Ok(())
 }
 
 pub fn head<R0,R1,W,P>(
@@ -170,144 +1004,827 @@ pub fn head<R0,R1,W,P>(
     w: &mut W,
     mut p: P,
     opts: JoinOptions,
-) -> Result<(), Box<Error>>
+) -> Result<(), Box<dyn Error>>
     where R0: io::Read,
           R1: io::Read,
           W: io::Write,
           P: Print<W>,
 {
-    let fr0 = first_rec0.is_present()?;
-    let fr1 = first_rec1.is_present()?;
+    let fr0 = first_rec0.is_present().map_err(|e| e.with_input(Input::Left))?;
+    let fr1 = first_rec1.is_present().map_err(|e| e.with_input(Input::Right))?;
+    p.set_field_counts(&[first_rec0.field_count(), first_rec1.field_count()]);
+
+    let (buf0, idx0) = first_rec0.buf_index();
+    let (buf1, idx1) = first_rec1.buf_index();
+    let rec0 = Records { buf: buf0, fields: idx0.fields(), records: idx0.records(), print: 0..1 };
+    let rec1 = Records { buf: buf1, fields: idx1.fields(), records: idx1.records(), print: 0..1 };
+    let (show_left, show_right) = opts.shows();
+    if opts.show_both || (show_left && show_right) {
+        if fr0 && fr1 {
+            p.print_header(w, &[Some(rec0), Some(rec1)])?;
+        }
+    } else if show_left {
+        if fr0 {
+            p.print_header(w, &[Some(rec0), None])?;
+        }
+    }
+    else {
+        if fr1 {
+            p.print_header(w, &[None, Some(rec1)])?;
+        }
+    }
+    Ok(())
+}
+
+/// Print the first records of any number of inputs as the header. The header is printed if
+/// all the required inputs have the first record.
+pub fn head_all<R,W,P>(
+    first_recs: &mut [FirstRec<R>],
+    w: &mut W,
+    mut p: P,
+    opts: &MultiJoinOptions,
+) -> Result<(), Box<dyn Error>>
+    where R: io::Read,
+          W: io::Write,
+          P: Print<W>,
+{
+    let mut present = Vec::with_capacity(first_recs.len());
+    for (i, fr) in first_recs.iter_mut().enumerate() {
+        present.push(fr.is_present().map_err(|e| e.with_input(Input::Nth(i)))?);
+    }
+    let counts: Vec<Option<usize>> = first_recs.iter().map(|fr| fr.field_count()).collect();
+    p.set_field_counts(&counts);
+
+    if !present.iter().any(|&p| p) || !opts.is_printed(&present) {
+        return Ok(());
+    }
+    let inputs: Vec<Option<Records>> = first_recs.iter().zip(&present).map(|(fr, &is_present)| {
+        if !is_present {
+            return None;
+        }
+        let (buf, idx) = fr.buf_index();
+        Some(Records {
+            buf,
+            fields: idx.fields(),
+            records: idx.records(),
+            print: 0..1,
+        })
+    }).collect();
+    p.print_header(w, &inputs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{JoinOptions, MultiJoinOptions, BuildSide, AsofOptions, Direction, join, join_all, hash_join, asof_join, interval_join, head, head_all,};
+    use printer::KeyFirst;
+    use csv::basic::{FirstRec, Group};
+    use csv::compare::{Compare, Fold, Kind,};
+    use rollbuf::RollBuf;
+    use csvroll::index_builder::IndexBuilder;
+    use csvroll::parser::Parser;
+    use spill::Spiller;
+    use std::env;
+
+    #[test]
+    fn test_join() {
+        struct TestCase {
+            note: String,
+            data0: String,
+            data1: String,
+            opts: JoinOptions,
+            want: String,
+        }
+
+        let test_cases = vec![
+            TestCase {
+                note: "inner join with cartesian product".into(),
+                data0: "color,red\ncolor,green\ncolor,blue\nshape,circle\nshape,square".into(),
+                data1: "color,orange\ncolor,purple\nsize,small\nsize,large".into(),
+                opts: JoinOptions::from_options(false, false, true),
+                want: 
+                    "\
+                     color,red,orange\n\
+                     color,red,purple\n\
+                     color,green,orange\n\
+                     color,green,purple\n\
+                     color,blue,orange\n\
+                     color,blue,purple\n\
+                    ".into(),
+            },
+            TestCase {
+                note: "inner join simple".into(),
+                data0: "altitude,low\naltitude,high\ncolor,red".into(),
+                data1: "color,orange\nsize,small\nsize,large".into(),
+                opts: JoinOptions::from_options(false, false, true),
+                want: "color,red,orange\n".into(),
+            },
+            TestCase {
+                note: "left outer join simple".into(),
+                data0: "altitude,low\naltitude,high\ncolor,red".into(),
+                data1: "color,orange\nsize,small\nsize,large".into(),
+                opts: JoinOptions::from_options(true, false, true),
+                want: 
+                    "\
+                     altitude,low\n\
+                     altitude,high\n\
+                     color,red,orange\n\
+                    ".into(),
+            },
+            TestCase {
+                note: "left exclusion join simple".into(),
+                data0: "altitude,low\naltitude,high\ncolor,red".into(),
+                data1: "color,orange\nsize,small\nsize,large".into(),
+                opts: JoinOptions::from_options(true, false, false),
+                want: 
+                    "\
+                     altitude,low\n\
+                     altitude,high\n\
+                    ".into(),
+            },
+            TestCase {
+                note: "right outer join simple".into(),
+                data0: "altitude,low\naltitude,high\ncolor,red".into(),
+                data1: "color,orange\nsize,small\nsize,large".into(),
+                opts: JoinOptions::from_options(false, true, true),
+                want: 
+                    "\
+                     color,red,orange\n\
+                     size,small\n\
+                     size,large\n\
+                    ".into(),
+            },
+            TestCase {
+                note: "right exclusion join simple".into(),
+                data0: "altitude,low\naltitude,high\ncolor,red".into(),
+                data1: "color,orange\nsize,small\nsize,large".into(),
+                opts: JoinOptions::from_options(false, true, false),
+                want: 
+                    "\
+                     size,small\n\
+                     size,large\n\
+                    ".into(),
+            },
+            TestCase {
+                note: "full outer join simple".into(),
+                data0: "altitude,low\naltitude,high\ncolor,red".into(),
+                data1: "color,orange\nsize,small\nsize,large".into(),
+                opts: JoinOptions::from_options(true, true, true),
+                want: 
+                    "\
+                     altitude,low\n\
+                     altitude,high\n\
+                     color,red,orange\n\
+                     size,small\n\
+                     size,large\n\
+                    ".into(),
+            },
+            TestCase {
+                note: "left semi join".into(),
+                data0: "altitude,low\ncolor,red\ncolor,green\nsize,big".into(),
+                data1: "color,orange\ncolor,purple\nsize,small\nsize,large".into(),
+                opts: JoinOptions::semi(true),
+                want: 
+                    "\
+                     color,red\n\
+                     color,green\n\
+                     size,big\n\
+                    ".into(),
+            },
+            TestCase {
+                note: "right semi join".into(),
+                data0: "altitude,low\ncolor,red\ncolor,green\nsize,big".into(),
+                data1: "color,orange\ncolor,purple\nsize,small\nweight,heavy".into(),
+                opts: JoinOptions::semi(false),
+                want: 
+                    "\
+                     color,orange\n\
+                     color,purple\n\
+                     size,small\n\
+                    ".into(),
+            },
+            TestCase {
+                note: "left anti join".into(),
+                data0: "altitude,low\ncolor,red\ncolor,green\nsize,big".into(),
+                data1: "color,orange\ncolor,purple\nsize,small\nsize,large".into(),
+                opts: JoinOptions::anti(true),
+                want: "altitude,low\n".into(),
+            },
+            TestCase {
+                note: "right anti join".into(),
+                data0: "altitude,low\ncolor,red\ncolor,green\nsize,big".into(),
+                data1: "color,orange\ncolor,purple\nsize,small\nweight,heavy".into(),
+                opts: JoinOptions::anti(false),
+                want: "weight,heavy\n".into(),
+            },
+            TestCase {
+                note: "left semi join with many matches of each record".into(),
+                data0: "a,0\nb,1\nb,2\nb,3\nc,4\nd,5".into(),
+                data1: "b,x\nb,y\nb,z\nc,w\nc,v\ne,u".into(),
+                opts: JoinOptions::semi(true),
+                want: "b,1\nb,2\nb,3\nc,4\n".into(),
+            },
+            TestCase {
+                note: "right semi join with many matches of each record".into(),
+                data0: "a,0\nb,1\nb,2\nb,3\nc,4\nd,5".into(),
+                data1: "b,x\nb,y\nb,z\nc,w\nc,v\ne,u".into(),
+                opts: JoinOptions::semi(false),
+                want: "b,x\nb,y\nb,z\nc,w\nc,v\n".into(),
+            },
+        ];
+
+        for t in test_cases {
+            let TestCase {note, data0, data1, opts, want } = t;
+            let buf0 = RollBuf::with_capacity(16, data0.as_bytes());
+            let buf1 = RollBuf::with_capacity(16, data1.as_bytes());
+            let idx_builder0 = IndexBuilder::new(b',', b'\n');
+            let idx_builder1 = IndexBuilder::new(b',', b'\n');
+            let parser0 = Parser::from_parts(buf0, idx_builder0);
+            let parser1 = Parser::from_parts(buf1, idx_builder1);
+            let mut group0 = Group::init(parser0, vec![0]).unwrap();
+            let mut group1 = Group::init(parser1, vec![0]).unwrap();
+            let mut out: Vec<u8> = Vec::new();
+            let printer = KeyFirst::from_parts(b',', b'\n', vec![0], vec![0]);
+
+            println!("{}", note);
+            join(&mut group0, &mut group1, &mut out, printer, opts).unwrap();
+            assert_eq!(out, want.as_bytes());
+        }
+    }
+
+    #[test]
+    fn test_join_empty() {
+        struct TestCase {
+            note: String,
+            data0: String,
+            data1: String,
+            want: String,
+        }
+
+        let test_cases = vec![
+            TestCase {
+                note: "padding of both inputs".into(),
+                data0: "a,0,x\nb,1,y".into(),
+                data1: "b,2\nc,3".into(),
+                want: "a,0,x,-\nb,1,y,2\nc,-,-,3\n".into(),
+            },
+            TestCase {
+                note: "empty right input".into(),
+                data0: "a,0,x".into(),
+                data1: "".into(),
+                want: "a,0,x\n".into(),
+            },
+        ];
+
+        for t in test_cases {
+            let TestCase {note, data0, data1, want } = t;
+            let buf0 = RollBuf::with_capacity(16, data0.as_bytes());
+            let buf1 = RollBuf::with_capacity(16, data1.as_bytes());
+            let parser0 = Parser::from_parts(buf0, IndexBuilder::new(b',', b'\n'));
+            let parser1 = Parser::from_parts(buf1, IndexBuilder::new(b',', b'\n'));
+            let mut group0 = Group::init(parser0, vec![0]).unwrap();
+            let mut group1 = Group::init(parser1, vec![0]).unwrap();
+            let mut out: Vec<u8> = Vec::new();
+            let printer = KeyFirst::with_empty(b',', b'\n', vec![0], vec![0], b"-".to_vec());
+            let opts = JoinOptions::from_options(true, true, true);
+
+            println!("{}", note);
+            join(&mut group0, &mut group1, &mut out, printer, opts).unwrap();
+            assert_eq!(String::from_utf8(out).unwrap(), want);
+        }
+    }
+
+    #[test]
+    fn test_join_spill() {
+        struct TestCase {
+            note: String,
+            data0: String,
+            data1: String,
+            threshold: usize,
+        }
+
+        let many = |key: &str, n: usize| -> String {
+            (0..n).map(|i| format!("{},{}{}\n", key, key, i)).collect()
+        };
+        let test_cases = vec![
+            TestCase {
+                note: "spilled left group".into(),
+                data0: format!("a,x\n{}c,y\n", many("b", 20)),
+                data1: "b,0\nb,1\nc,2\nd,3".into(),
+                threshold: 8,
+            },
+            TestCase {
+                note: "spilled right group".into(),
+                data0: "a,x\nb,0\nb,1\nc,y".into(),
+                data1: format!("{}c,2\nd,3", many("b", 20)),
+                threshold: 8,
+            },
+            TestCase {
+                note: "spilled groups of both inputs".into(),
+                data0: format!("{}{}", many("b", 15), many("c", 3)),
+                data1: format!("a,0\n{}{}", many("b", 12), many("d", 20)),
+                threshold: 1,
+            },
+        ];
+
+        let join_str = |data0: &str, data1: &str, spill: Option<usize>, opts: JoinOptions| {
+            let buf0 = RollBuf::with_capacity(16, data0.as_bytes());
+            let buf1 = RollBuf::with_capacity(16, data1.as_bytes());
+            let parser0 = Parser::from_parts(buf0, IndexBuilder::new(b',', b'\n'));
+            let parser1 = Parser::from_parts(buf1, IndexBuilder::new(b',', b'\n'));
+            let mut group0 = Group::init(parser0, vec![0]).unwrap();
+            let mut group1 = Group::init(parser1, vec![0]).unwrap();
+            if let Some(threshold) = spill {
+                let spiller = Spiller::from_parts(threshold, b',', b'\n', None, env::temp_dir());
+                group0 = group0.with_spill(spiller.clone());
+                group1 = group1.with_spill(spiller);
+            }
+            let mut out: Vec<u8> = Vec::new();
+            let printer = KeyFirst::from_parts(b',', b'\n', vec![0], vec![0]);
+            join(&mut group0, &mut group1, &mut out, printer, opts).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+
+        let opts = [
+            JoinOptions::from_options(false, false, true),
+            JoinOptions::from_options(true, true, true),
+            JoinOptions::from_options(true, true, false),
+            JoinOptions::semi(true),
+            JoinOptions::semi(false),
+            JoinOptions::anti(true),
+            JoinOptions::anti(false),
+        ];
+        for t in test_cases {
+            for &opts in &opts {
+                let want = join_str(&t.data0, &t.data1, None, opts);
+                let got = join_str(&t.data0, &t.data1, Some(t.threshold), opts);
+                assert_eq!(got, want, "{}", t.note);
+            }
+        }
+    }
 
-    if opts.show_both || (opts.show_left && opts.show_right) {
-        if fr0 && fr1 {
-            let (buf0, idx0) = first_rec0.buf_index();
-            let (buf1, idx1) = first_rec1.buf_index();
-            p.print_both(
-                w,
-                buf0,
-                buf1,
-                idx0.fields(),
-                idx1.fields(),
-                idx0.records(),
-                idx1.records(),
-                0..1,
-                0..1,
-            )?;
+    #[test]
+    fn test_join_reverse() {
+        let data0 = "size,small\ncolor,red\naltitude,low";
+        let data1 = "size,large\nshape,circle\ncolor,orange";
+        let cmp = vec![Compare::new(Kind::Bytes).with_reverse(true)];
+        let buf0 = RollBuf::with_capacity(16, data0.as_bytes());
+        let buf1 = RollBuf::with_capacity(16, data1.as_bytes());
+        let parser0 = Parser::from_parts(buf0, IndexBuilder::new(b',', b'\n'));
+        let parser1 = Parser::from_parts(buf1, IndexBuilder::new(b',', b'\n'));
+        let mut group0 = Group::with_compare(parser0, vec![0], cmp.clone()).unwrap();
+        let mut group1 = Group::with_compare(parser1, vec![0], cmp).unwrap();
+        let mut out: Vec<u8> = Vec::new();
+        let printer = KeyFirst::from_parts(b',', b'\n', vec![0], vec![0]);
+        let opts = JoinOptions::from_options(true, true, true);
+
+        join(&mut group0, &mut group1, &mut out, printer, opts).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "size,small,large\nshape,circle\ncolor,red,orange\naltitude,low\n",
+        );
+    }
+
+    #[test]
+    fn test_join_compare() {
+        struct TestCase {
+            note: &'static str,
+            data0: &'static str,
+            data1: &'static str,
+            cmp: Compare,
+            want: &'static str,
         }
-    } else if opts.show_left {
-        if fr0 {
-            let (buf0, idx0) = first_rec0.buf_index();
-            p.print_left(w, buf0, idx0.fields(), idx0.records(), 0..1)?;
+
+        let test_cases = vec![
+            TestCase {
+                note: "numeric keys differing in the left group",
+                data0: "7,1\n007,2\n8,3",
+                data1: "7.0,x\n9,y",
+                cmp: Compare::new(Kind::Numeric),
+                want: "7,1,x\n007,2,x\n",
+            },
+            TestCase {
+                note: "keys differing in case in the left group",
+                data0: "A,1\na,2\nb,3",
+                data1: "a,x\nc,y",
+                cmp: Compare::new(Kind::Bytes).with_fold(Fold::Ascii),
+                want: "A,1,x\na,2,x\n",
+            },
+        ];
+
+        for t in test_cases {
+            let buf0 = RollBuf::with_capacity(16, t.data0.as_bytes());
+            let buf1 = RollBuf::with_capacity(16, t.data1.as_bytes());
+            let parser0 = Parser::from_parts(buf0, IndexBuilder::new(b',', b'\n'));
+            let parser1 = Parser::from_parts(buf1, IndexBuilder::new(b',', b'\n'));
+            let mut group0 = Group::with_compare(parser0, vec![0], vec![t.cmp]).unwrap();
+            let mut group1 = Group::with_compare(parser1, vec![0], vec![t.cmp]).unwrap();
+            let mut out: Vec<u8> = Vec::new();
+            let printer = KeyFirst::from_parts(b',', b'\n', vec![0], vec![0]);
+            let opts = JoinOptions::from_options(false, false, true);
+
+            join(&mut group0, &mut group1, &mut out, printer, opts).unwrap();
+            // the key is printed as it is in each left record
+            assert_eq!(String::from_utf8(out).unwrap(), t.want, "{}", t.note);
         }
     }
-    else {
-        if fr1 {
-            let (buf1, idx1) = first_rec1.buf_index();
-            p.print_right(w, buf1, idx1.fields(), idx1.records(), 0..1)?;
+
+    #[test]
+    fn test_asof_join() {
+        struct TestCase {
+            note: String,
+            data0: String,
+            data1: String,
+            opts: AsofOptions,
+            want: String,
         }
-    }
-    Ok(())
-}
 
-#[cfg(test)]
-mod tests {
-    use super::{JoinOptions, join, head,};
-    use printer::KeyFirst;
-    use csv::basic::{FirstRec, Group};
-    use rollbuf::RollBuf;
-    use csvroll::index_builder::IndexBuilder;
-    use csvroll::parser::Parser;
+        let data0 = "a,1,x\na,5,y\na,5,y2\na,9,z\nb,2,w";
+        let data1 = "a,0,p\na,4,q\na,6,r\nb,3,s";
+        let test_cases = vec![
+            TestCase {
+                note: "backward".into(),
+                data0: data0.into(),
+                data1: data1.into(),
+                opts: AsofOptions::new(Direction::Backward),
+                want: "a,1,x,0,p\na,5,y,4,q\na,5,y2,4,q\na,9,z,6,r\n".into(),
+            },
+            TestCase {
+                note: "forward".into(),
+                data0: data0.into(),
+                data1: data1.into(),
+                opts: AsofOptions::new(Direction::Forward),
+                want: "a,1,x,4,q\na,5,y,6,r\na,5,y2,6,r\nb,2,w,3,s\n".into(),
+            },
+            TestCase {
+                note: "nearest with a tie".into(),
+                data0: data0.into(),
+                data1: data1.into(),
+                opts: AsofOptions::new(Direction::Nearest),
+                want: "a,1,x,0,p\na,5,y,4,q\na,5,y2,4,q\na,9,z,6,r\nb,2,w,3,s\n".into(),
+            },
+            TestCase {
+                note: "backward with tolerance and unmatched left records".into(),
+                data0: data0.into(),
+                data1: data1.into(),
+                opts: AsofOptions::new(Direction::Backward).with_tolerance(1.0).with_show_left(true),
+                want: "a,1,x,0,p\na,5,y,4,q\na,5,y2,4,q\na,9,z\nb,2,w\n".into(),
+            },
+            TestCase {
+                note: "backward to the last of the equal keys".into(),
+                data0: "a,4,x".into(),
+                data1: "a,3,o\na,4,q1\na,4,q2\na,5,t".into(),
+                opts: AsofOptions::new(Direction::Backward),
+                want: "a,4,x,4,q2\n".into(),
+            },
+            TestCase {
+                note: "forward to the first of the equal keys".into(),
+                data0: "a,4,x".into(),
+                data1: "a,3,o\na,4,q1\na,4,q2\na,5,t".into(),
+                opts: AsofOptions::new(Direction::Forward),
+                want: "a,4,x,4,q1\n".into(),
+            },
+        ];
+
+        for t in test_cases {
+            let TestCase {note, data0, data1, opts, want } = t;
+            let cmp = vec![Compare::default(), Compare::new(Kind::Numeric)];
+            let buf0 = RollBuf::with_capacity(16, data0.as_bytes());
+            let buf1 = RollBuf::with_capacity(16, data1.as_bytes());
+            let parser0 = Parser::from_parts(buf0, IndexBuilder::new(b',', b'\n'));
+            let parser1 = Parser::from_parts(buf1, IndexBuilder::new(b',', b'\n'));
+            let mut group0 = Group::with_compare(parser0, vec![0, 1], cmp.clone()).unwrap();
+            let mut group1 = Group::with_compare(parser1, vec![0, 1], cmp).unwrap();
+            let mut out: Vec<u8> = Vec::new();
+            // the as-of field of the right input is printed with its other fields
+            let printer = KeyFirst::from_parts(b',', b'\n', vec![0, 1], vec![0]);
+
+            println!("{}", note);
+            asof_join(&mut group0, &mut group1, &mut out, printer, opts).unwrap();
+            assert_eq!(String::from_utf8(out).unwrap(), want);
+        }
+
+        // the header has the same columns as the matched and the padded rows
+        let cmp = vec![Compare::default(), Compare::new(Kind::Numeric)];
+        let buf0 = RollBuf::with_capacity(16, "k,t,v\na,1,x\nb,2,w".as_bytes());
+        let buf1 = RollBuf::with_capacity(16, "k,t,w\na,0,p".as_bytes());
+        let mut first_rec0 = FirstRec::init(Parser::from_parts(buf0, IndexBuilder::new(b',', b'\n'))).unwrap();
+        let mut first_rec1 = FirstRec::init(Parser::from_parts(buf1, IndexBuilder::new(b',', b'\n'))).unwrap();
+        let mut out: Vec<u8> = Vec::new();
+        let mut printer = KeyFirst::with_empty(b',', b'\n', vec![0, 1], vec![0], Vec::new());
+        let opts = AsofOptions::new(Direction::Backward).with_show_left(true);
+        head(&mut first_rec0, &mut first_rec1, &mut out, &mut printer, JoinOptions::from_options(true, false, true)).unwrap();
+        let mut group0 = Group::with_compare(first_rec0.into_inner(), vec![0, 1], cmp.clone()).unwrap();
+        let mut group1 = Group::with_compare(first_rec1.into_inner(), vec![0, 1], cmp).unwrap();
+        asof_join(&mut group0, &mut group1, &mut out, &mut printer, opts).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(out, "k,t,v,t,w\na,1,x,0,p\nb,2,w,,\n");
+        assert!(out.lines().all(|l| l.split(',').count() == 5));
+    }
 
     #[test]
-    fn test_join() {
+    fn test_interval_join() {
         struct TestCase {
             note: String,
             data0: String,
             data1: String,
+            kind: Kind,
             opts: JoinOptions,
             want: String,
         }
 
+        let data0 = "1,x\n4,y\n4,y2\n7,z\n12,w";
+        let data1 = "0,2,a\n3,5,b\n3,8,c\n6,6,d\n9,10,e";
         let test_cases = vec![
             TestCase {
-                note: "inner join with cartesian product".into(),
-                data0: "color,red\ncolor,green\ncolor,blue\nshape,circle\nshape,square".into(),
-                data1: "color,orange\ncolor,purple\nsize,small\nsize,large".into(),
-                opts: JoinOptions { show_left: false, show_right: false, show_both: true },
-                want: 
+                note: "inner join with overlapping intervals".into(),
+                data0: data0.into(),
+                data1: data1.into(),
+                kind: Kind::Numeric,
+                opts: JoinOptions::from_options(false, false, true),
+                want: "1,x,0,2,a\n4,y,3,5,b\n4,y,3,8,c\n4,y2,3,5,b\n4,y2,3,8,c\n7,z,3,8,c\n".into(),
+            },
+            TestCase {
+                note: "full outer join".into(),
+                data0: data0.into(),
+                data1: data1.into(),
+                kind: Kind::Numeric,
+                opts: JoinOptions::from_options(true, true, true),
+                want:
+                    "\
+                     1,x,0,2,a\n\
+                     4,y,3,5,b\n\
+                     4,y,3,8,c\n\
+                     4,y2,3,5,b\n\
+                     4,y2,3,8,c\n\
+                     6,6,d\n\
+                     7,z,3,8,c\n\
+                     9,10,e\n\
+                     12,w\n\
+                    ".into(),
+            },
+            TestCase {
+                note: "left semi join".into(),
+                data0: data0.into(),
+                data1: data1.into(),
+                kind: Kind::Numeric,
+                opts: JoinOptions::semi(true),
+                want: "1,x\n4,y\n4,y2\n7,z\n".into(),
+            },
+            TestCase {
+                note: "right semi join".into(),
+                data0: data0.into(),
+                data1: data1.into(),
+                kind: Kind::Numeric,
+                opts: JoinOptions::semi(false),
+                want: "0,2,a\n3,5,b\n3,8,c\n".into(),
+            },
+            TestCase {
+                note: "left anti join".into(),
+                data0: data0.into(),
+                data1: data1.into(),
+                kind: Kind::Numeric,
+                opts: JoinOptions::anti(true),
+                want: "12,w\n".into(),
+            },
+            TestCase {
+                note: "right anti join".into(),
+                data0: data0.into(),
+                data1: data1.into(),
+                kind: Kind::Numeric,
+                opts: JoinOptions::anti(false),
+                want: "6,6,d\n9,10,e\n".into(),
+            },
+            TestCase {
+                note: "left exclusion join".into(),
+                data0: data0.into(),
+                data1: data1.into(),
+                kind: Kind::Numeric,
+                opts: JoinOptions::from_options(true, false, false),
+                want: "12,w\n".into(),
+            },
+            TestCase {
+                note: "right exclusion join with intervals after the last left key".into(),
+                data0: "1,x".into(),
+                data1: "0,2,a\n5,6,b\n7,8,c".into(),
+                kind: Kind::Numeric,
+                opts: JoinOptions::from_options(false, true, false),
+                want: "5,6,b\n7,8,c\n".into(),
+            },
+            TestCase {
+                note: "inner join comparing bytes".into(),
+                data0: "b,x\nd,y".into(),
+                data1: "a,c,r\nc,c,s".into(),
+                kind: Kind::Bytes,
+                opts: JoinOptions::from_options(false, false, true),
+                want: "b,x,a,c,r\n".into(),
+            },
+        ];
+
+        for t in test_cases {
+            let TestCase {note, data0, data1, kind, opts, want } = t;
+            let buf0 = RollBuf::with_capacity(16, data0.as_bytes());
+            let buf1 = RollBuf::with_capacity(16, data1.as_bytes());
+            let parser0 = Parser::from_parts(buf0, IndexBuilder::new(b',', b'\n'));
+            let parser1 = Parser::from_parts(buf1, IndexBuilder::new(b',', b'\n'));
+            let mut group0 = Group::with_compare(parser0, vec![0], vec![Compare::new(kind)]).unwrap();
+            let mut group1 = Group::with_compare(parser1, vec![0], vec![Compare::new(kind)]).unwrap();
+            let mut out: Vec<u8> = Vec::new();
+            // the right records are printed whole, including the start of the interval
+            let printer = KeyFirst::from_parts(b',', b'\n', vec![0], Vec::new());
+
+            println!("{}", note);
+            interval_join(&mut group0, &mut group1, 1, &mut out, printer, opts).unwrap();
+            assert_eq!(String::from_utf8(out).unwrap(), want);
+        }
+
+        // the header has the same columns as the matched and the padded rows
+        let cmp = vec![Compare::new(Kind::Numeric)];
+        let buf0 = RollBuf::with_capacity(16, "k,v\n1,x\n12,w".as_bytes());
+        let buf1 = RollBuf::with_capacity(16, "s,e,n\n0,2,a\n6,6,d".as_bytes());
+        let mut first_rec0 = FirstRec::init(Parser::from_parts(buf0, IndexBuilder::new(b',', b'\n'))).unwrap();
+        let mut first_rec1 = FirstRec::init(Parser::from_parts(buf1, IndexBuilder::new(b',', b'\n'))).unwrap();
+        let mut out: Vec<u8> = Vec::new();
+        let mut printer = KeyFirst::with_empty(b',', b'\n', vec![0], Vec::new(), Vec::new());
+        let opts = JoinOptions::from_options(true, true, true);
+        head(&mut first_rec0, &mut first_rec1, &mut out, &mut printer, opts).unwrap();
+        let mut group0 = Group::with_compare(first_rec0.into_inner(), vec![0], cmp.clone()).unwrap();
+        let mut group1 = Group::with_compare(first_rec1.into_inner(), vec![0], cmp).unwrap();
+        interval_join(&mut group0, &mut group1, 1, &mut out, &mut printer, opts).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(out, "k,v,s,e,n\n1,x,0,2,a\n,,6,6,d\n12,w,,,\n");
+        assert!(out.lines().all(|l| l.split(',').count() == 5));
+    }
+
+    #[test]
+    fn test_hash_join() {
+        struct TestCase {
+            note: String,
+            opts: JoinOptions,
+            build: BuildSide,
+            want: String,
+        }
+
+        let data0 = "shape,circle\ncolor,red\naltitude,low\ncolor,green\naltitude,high";
+        let data1 = "size,small\ncolor,orange\nsize,large\ncolor,purple";
+
+        let test_cases = vec![
+            TestCase {
+                note: "inner join, right build side".into(),
+                opts: JoinOptions::from_options(false, false, true),
+                build: BuildSide::Right,
+                want:
                     "\
                      color,red,orange\n\
                      color,red,purple\n\
                      color,green,orange\n\
                      color,green,purple\n\
-                     color,blue,orange\n\
-                     color,blue,purple\n\
                     ".into(),
             },
             TestCase {
-                note: "inner join simple".into(),
-                data0: "altitude,low\naltitude,high\ncolor,red".into(),
-                data1: "color,orange\nsize,small\nsize,large".into(),
-                opts: JoinOptions { show_left: false, show_right: false, show_both: true },
-                want: "color,red,orange\n".into(),
+                note: "inner join, left build side".into(),
+                opts: JoinOptions::from_options(false, false, true),
+                build: BuildSide::Left,
+                want:
+                    "\
+                     color,red,orange\n\
+                     color,green,orange\n\
+                     color,red,purple\n\
+                     color,green,purple\n\
+                    ".into(),
             },
             TestCase {
-                note: "left outer join simple".into(),
-                data0: "altitude,low\naltitude,high\ncolor,red".into(),
-                data1: "color,orange\nsize,small\nsize,large".into(),
-                opts: JoinOptions { show_left: true, show_right: false, show_both: true },
-                want: 
+                note: "full outer join, right build side".into(),
+                opts: JoinOptions::from_options(true, true, true),
+                build: BuildSide::Right,
+                want:
                     "\
+                     shape,circle\n\
+                     color,red,orange\n\
+                     color,red,purple\n\
                      altitude,low\n\
+                     color,green,orange\n\
+                     color,green,purple\n\
                      altitude,high\n\
+                     size,small\n\
+                     size,large\n\
+                    ".into(),
+            },
+            TestCase {
+                note: "full outer join, left build side".into(),
+                opts: JoinOptions::from_options(true, true, true),
+                build: BuildSide::Left,
+                want:
+                    "\
+                     size,small\n\
                      color,red,orange\n\
+                     color,green,orange\n\
+                     size,large\n\
+                     color,red,purple\n\
+                     color,green,purple\n\
+                     shape,circle\n\
+                     altitude,low\n\
+                     altitude,high\n\
                     ".into(),
             },
             TestCase {
-                note: "left exclusion join simple".into(),
-                data0: "altitude,low\naltitude,high\ncolor,red".into(),
-                data1: "color,orange\nsize,small\nsize,large".into(),
-                opts: JoinOptions { show_left: true, show_right: false, show_both: false },
-                want: 
+                note: "left exclusion join, left build side".into(),
+                opts: JoinOptions::from_options(true, false, false),
+                build: BuildSide::Left,
+                want:
                     "\
+                     shape,circle\n\
                      altitude,low\n\
                      altitude,high\n\
                     ".into(),
             },
             TestCase {
-                note: "right outer join simple".into(),
-                data0: "altitude,low\naltitude,high\ncolor,red".into(),
-                data1: "color,orange\nsize,small\nsize,large".into(),
-                opts: JoinOptions { show_left: false, show_right: true, show_both: true },
-                want: 
+                note: "right exclusion join, left build side".into(),
+                opts: JoinOptions::from_options(false, true, false),
+                build: BuildSide::Left,
+                want:
                     "\
-                     color,red,orange\n\
                      size,small\n\
                      size,large\n\
                     ".into(),
             },
             TestCase {
-                note: "right exclusion join simple".into(),
-                data0: "altitude,low\naltitude,high\ncolor,red".into(),
-                data1: "color,orange\nsize,small\nsize,large".into(),
-                opts: JoinOptions { show_left: false, show_right: true, show_both: false },
-                want: 
+                note: "left semi join, right build side".into(),
+                opts: JoinOptions::semi(true),
+                build: BuildSide::Right,
+                want:
                     "\
-                     size,small\n\
-                     size,large\n\
+                     color,red\n\
+                     color,green\n\
                     ".into(),
             },
             TestCase {
-                note: "full outer join simple".into(),
-                data0: "altitude,low\naltitude,high\ncolor,red".into(),
-                data1: "color,orange\nsize,small\nsize,large".into(),
-                opts: JoinOptions { show_left: true, show_right: true, show_both: true },
-                want: 
+                note: "left semi join, left build side".into(),
+                opts: JoinOptions::semi(true),
+                build: BuildSide::Left,
+                want:
+                    "\
+                     color,red\n\
+                     color,green\n\
+                    ".into(),
+            },
+            TestCase {
+                note: "right semi join, left build side".into(),
+                opts: JoinOptions::semi(false),
+                build: BuildSide::Left,
+                want:
+                    "\
+                     color,orange\n\
+                     color,purple\n\
+                    ".into(),
+            },
+            TestCase {
+                note: "right semi join, right build side".into(),
+                opts: JoinOptions::semi(false),
+                build: BuildSide::Right,
+                want:
+                    "\
+                     color,orange\n\
+                     color,purple\n\
+                    ".into(),
+            },
+            TestCase {
+                note: "left anti join, right build side".into(),
+                opts: JoinOptions::anti(true),
+                build: BuildSide::Right,
+                want:
                     "\
+                     shape,circle\n\
                      altitude,low\n\
                      altitude,high\n\
-                     color,red,orange\n\
+                    ".into(),
+            },
+            TestCase {
+                note: "left anti join, left build side".into(),
+                opts: JoinOptions::anti(true),
+                build: BuildSide::Left,
+                want:
+                    "\
+                     shape,circle\n\
+                     altitude,low\n\
+                     altitude,high\n\
+                    ".into(),
+            },
+            TestCase {
+                note: "right anti join, right build side".into(),
+                opts: JoinOptions::anti(false),
+                build: BuildSide::Right,
+                want:
+                    "\
+                     size,small\n\
+                     size,large\n\
+                    ".into(),
+            },
+            TestCase {
+                note: "right anti join, left build side".into(),
+                opts: JoinOptions::anti(false),
+                build: BuildSide::Left,
+                want:
+                    "\
                      size,small\n\
                      size,large\n\
                     ".into(),
@@ -315,21 +1832,19 @@ mod tests {
         ];
 
         for t in test_cases {
-            let TestCase {note, data0, data1, opts, want } = t;
+            let TestCase { note, opts, build, want } = t;
             let buf0 = RollBuf::with_capacity(16, data0.as_bytes());
             let buf1 = RollBuf::with_capacity(16, data1.as_bytes());
             let idx_builder0 = IndexBuilder::new(b',', b'\n');
             let idx_builder1 = IndexBuilder::new(b',', b'\n');
             let parser0 = Parser::from_parts(buf0, idx_builder0);
             let parser1 = Parser::from_parts(buf1, idx_builder1);
-            let mut group0 = Group::init(parser0, vec![0]).unwrap();
-            let mut group1 = Group::init(parser1, vec![0]).unwrap();
             let mut out: Vec<u8> = Vec::new();
             let printer = KeyFirst::from_parts(b',', b'\n', vec![0], vec![0]);
 
             println!("{}", note);
-            join(&mut group0, &mut group1, &mut out, printer, opts).unwrap();
-            assert_eq!(out, want.as_bytes());
+            hash_join(parser0, parser1, vec![0], vec![0], Vec::new(), build, &mut out, printer, opts).unwrap();
+            assert_eq!(String::from_utf8(out).unwrap(), want);
         }
     }
 
@@ -348,44 +1863,58 @@ mod tests {
                 note: "inner join".into(),
                 data0: "col0,col1\naltitude,low\naltitude,high\ncolor,red".into(),
                 data1: "col2,col3\ncolor,orange\nsize,small\nsize,large".into(),
-                opts: JoinOptions { show_left: false, show_right: false, show_both: true },
+                opts: JoinOptions::from_options(false, false, true),
                 want: "col0,col1,col3\n".into(),
             },
             TestCase {
                 note: "left outer join".into(),
                 data0: "col0,col1\naltitude,low\naltitude,high\ncolor,red".into(),
                 data1: "col2,col3\ncolor,orange\nsize,small\nsize,large".into(),
-                opts: JoinOptions { show_left: true, show_right: false, show_both: true },
+                opts: JoinOptions::from_options(true, false, true),
                 want: "col0,col1,col3\n".into(),
             },
             TestCase {
                 note: "left exclusion join".into(),
                 data0: "col0,col1\naltitude,low\naltitude,high\ncolor,red".into(),
                 data1: "col2,col3\ncolor,orange\nsize,small\nsize,large".into(),
-                opts: JoinOptions { show_left: true, show_right: false, show_both: false },
+                opts: JoinOptions::from_options(true, false, false),
                 want: "col0,col1\n".into(),
             },
             TestCase {
                 note: "right outer join".into(),
                 data0: "col0,col1\naltitude,low\naltitude,high\ncolor,red".into(),
                 data1: "col2,col3\ncolor,orange\nsize,small\nsize,large".into(),
-                opts: JoinOptions { show_left: false, show_right: true, show_both: true },
+                opts: JoinOptions::from_options(false, true, true),
                 want: "col0,col1,col3\n".into(),
             },
             TestCase {
                 note: "right exclusion join".into(),
                 data0: "col0,col1\naltitude,low\naltitude,high\ncolor,red".into(),
                 data1: "col2,col3\ncolor,orange\nsize,small\nsize,large".into(),
-                opts: JoinOptions { show_left: false, show_right: true, show_both: false },
+                opts: JoinOptions::from_options(false, true, false),
                 want: "col2,col3\n".into(),
             },
             TestCase {
                 note: "full outer join".into(),
                 data0: "col0,col1\naltitude,low\naltitude,high\ncolor,red".into(),
                 data1: "col2,col3\ncolor,orange\nsize,small\nsize,large".into(),
-                opts: JoinOptions { show_left: true, show_right: true, show_both: true },
+                opts: JoinOptions::from_options(true, true, true),
                 want: "col0,col1,col3\n".into(),
             },
+            TestCase {
+                note: "left semi join".into(),
+                data0: "col0,col1\naltitude,low\naltitude,high\ncolor,red".into(),
+                data1: "col2,col3\ncolor,orange\nsize,small\nsize,large".into(),
+                opts: JoinOptions::semi(true),
+                want: "col0,col1\n".into(),
+            },
+            TestCase {
+                note: "right anti join".into(),
+                data0: "col0,col1\naltitude,low\naltitude,high\ncolor,red".into(),
+                data1: "col2,col3\ncolor,orange\nsize,small\nsize,large".into(),
+                opts: JoinOptions::anti(false),
+                want: "col2,col3\n".into(),
+            },
         ];
 
         for t in test_cases {
@@ -406,6 +1935,78 @@ mod tests {
             assert_eq!(out, want.as_bytes());
         }
     }
+
+    #[test]
+    fn test_join_all() {
+        struct TestCase {
+            note: String,
+            required: Vec<bool>,
+            want: String,
+        }
+
+        let data = [
+            "a,0\nb,1\nb,2\nd,3",
+            "b,x\nc,y\nd,z",
+            "a,A\nb,B\nd,D\nd,E",
+        ];
+
+        let test_cases = vec![
+            TestCase {
+                note: "inner join".into(),
+                required: vec![true, true, true],
+                want: "b,1,x,B\nb,2,x,B\nd,3,z,D\nd,3,z,E\n".into(),
+            },
+            TestCase {
+                note: "first input required".into(),
+                required: vec![true, false, false],
+                want: "a,0,,A\nb,1,x,B\nb,2,x,B\nd,3,z,D\nd,3,z,E\n".into(),
+            },
+            TestCase {
+                note: "middle input missing".into(),
+                required: vec![true, false, true],
+                want: "a,0,,A\nb,1,x,B\nb,2,x,B\nd,3,z,D\nd,3,z,E\n".into(),
+            },
+            TestCase {
+                note: "full outer join".into(),
+                required: vec![false, false, false],
+                want: "a,0,,A\nb,1,x,B\nb,2,x,B\nc,,y,\nd,3,z,D\nd,3,z,E\n".into(),
+            },
+            TestCase {
+                note: "second input required".into(),
+                required: vec![false, true, false],
+                want: "b,1,x,B\nb,2,x,B\nc,,y,\nd,3,z,D\nd,3,z,E\n".into(),
+            },
+        ];
+
+        for t in test_cases {
+            let TestCase { note, required, want } = t;
+            let mut groups: Vec<_> = data.iter().map(|d| {
+                let buf = RollBuf::with_capacity(16, d.as_bytes());
+                let parser = Parser::from_parts(buf, IndexBuilder::new(b',', b'\n'));
+                Group::init(parser, vec![0]).unwrap()
+            }).collect();
+            let mut out: Vec<u8> = Vec::new();
+            let printer = KeyFirst::from_keys(b',', b'\n', vec![vec![0]; 3], None);
+            let opts = MultiJoinOptions::from_required(required);
+
+            println!("{}", note);
+            join_all(&mut groups, &mut out, printer, &opts).unwrap();
+            assert_eq!(String::from_utf8(out).unwrap(), want);
+        }
+    }
+
+    #[test]
+    fn test_head_all() {
+        let data = ["k,a\n1,x", "k,b\n1,y", "k,c\n"];
+        let mut first_recs: Vec<_> = data.iter().map(|d| {
+            let buf = RollBuf::with_capacity(16, d.as_bytes());
+            let parser = Parser::from_parts(buf, IndexBuilder::new(b',', b'\n'));
+            FirstRec::init(parser).unwrap()
+        }).collect();
+        let mut out: Vec<u8> = Vec::new();
+        let printer = KeyFirst::from_keys(b',', b'\n', vec![vec![0]; 3], None);
+
+        head_all(&mut first_recs, &mut out, printer, &MultiJoinOptions::new(3)).unwrap();
+        assert_eq!(out, b"k,a,b,c\n");
+    }
 }
-         
-        
@@ -1,9 +1,18 @@
 extern crate csvroll;
 extern crate rollbuf;
+extern crate flate2;
+extern crate zstd;
+extern crate bzip2;
+#[cfg(test)]
+extern crate memmap2;
 
 pub mod csv;
 pub mod printer;
 pub mod join;
+pub mod sort;
+pub mod spill;
+pub mod pipeline;
+pub mod compress;
 
 
 
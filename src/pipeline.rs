@@ -0,0 +1,409 @@
+use std::io::{self, Write,};
+use std::error::Error;
+use std::mem;
+use std::ops::Range;
+use std::sync::mpsc::{self, SyncSender,};
+use std::thread::{self, JoinHandle,};
+use csvroll::error::Error as CsvError;
+use printer::{Print, Records,};
+
+// the size of the copied records in a batch sent to the printing thread
+const BATCH_CAP: usize = 1 << 16;
+// the number of the calls of the printer in a batch, e.g. of many short records
+const CALLS_CAP: usize = 1 << 10;
+
+// the errors sent back from the thread
+type ThreadError = Box<dyn Error + Send + Sync>;
+
+/// A printer, which formats and writes the records on another thread, e.g. to format and compress
+/// the output while the join goes on.
+///
+/// The printed records are copied into batches and sent to the thread, where they are printed by
+/// the `printer` into the `inner` writer. The writer passed to the methods of `Print` is not used.
+///
+/// The batches are sent over a channel of at most `bound` batches. The printer must be finished by
+/// `finish` to get the errors of the thread and the `inner` writer back.
+pub struct ThreadPrinter<W> {
+    tx: Option<SyncSender<Batch>>,
+    handle: Option<JoinHandle<Result<W, ThreadError>>>,
+    batch: Batch,
+}
+
+impl<W: Write + Send + 'static> ThreadPrinter<W> {
+    pub fn spawn<P>(mut printer: P, mut inner: W, bound: usize) -> Self
+        where P: Print<W> + Send + 'static,
+    {
+        let (tx, rx) = mpsc::sync_channel::<Batch>(bound);
+        let handle = thread::spawn(move || {
+            for batch in rx {
+                batch.print(&mut printer, &mut inner).map_err(into_thread_error)?;
+            }
+            inner.flush()?;
+            Ok(inner)
+        });
+        ThreadPrinter {
+            tx: Some(tx),
+            handle: Some(handle),
+            batch: Batch::new(),
+        }
+    }
+
+    /// Print the rest of the records, wait for the thread and return the `inner` writer.
+    pub fn finish(mut self) -> Result<W, Box<dyn Error>> {
+        self.send()?;
+        self.tx = None;
+        self.join()
+    }
+}
+
+impl<W> ThreadPrinter<W> {
+    // send the current batch to the thread once it is full
+    #[inline]
+    fn push(&mut self, call: Call) -> Result<(), Box<dyn Error>> {
+        self.batch.calls.push(call);
+        if self.batch.buf.len() >= BATCH_CAP || self.batch.calls.len() >= CALLS_CAP {
+            self.send()?;
+        }
+        Ok(())
+    }
+
+    // send the current batch to the thread
+    fn send(&mut self) -> Result<(), Box<dyn Error>> {
+        if self.batch.calls.is_empty() {
+            return Ok(());
+        }
+        let batch = mem::replace(&mut self.batch, Batch::new());
+        let is_sent = match self.tx {
+            Some(ref tx) => tx.send(batch).is_ok(),
+            None => false,
+        };
+        if is_sent {
+            return Ok(());
+        }
+        // the thread stopped on an error
+        self.tx = None;
+        self.join().map(|_| ())
+    }
+
+    fn join(&mut self) -> Result<W, Box<dyn Error>> {
+        match self.handle.take() {
+            Some(handle) => match handle.join() {
+                Ok(res) => res.map_err(|e| e as Box<dyn Error>),
+                Err(_) => Err(io::Error::other("the printer thread panicked").into()),
+            },
+            None => Err(io::Error::new(io::ErrorKind::BrokenPipe, "the printer thread stopped").into()),
+        }
+    }
+}
+
+// the records printed so far are not lost, if the printer is not finished, e.g. on an error
+impl<W> Drop for ThreadPrinter<W> {
+    fn drop(&mut self) {
+        if self.handle.is_some() {
+            let _ = self.send();
+            self.tx = None;
+            let _ = self.join();
+        }
+    }
+}
+
+impl<V: io::Write, W> Print<V> for ThreadPrinter<W> {
+    #[inline]
+    fn print_left(
+        &mut self,
+        _w: &mut V,
+        buf: &[u8],
+        fields: &[Range<usize>],
+        records: &[usize],
+        print: Range<usize>
+    ) -> Result<(),Box<dyn Error>> {
+        let c = self.batch.copy(buf, fields, records, print);
+        self.push(Call::Left(c))
+    }
+
+    #[inline]
+    fn print_right(
+        &mut self,
+        _w: &mut V,
+        buf: &[u8],
+        fields: &[Range<usize>],
+        records: &[usize],
+        print: Range<usize>
+    ) -> Result<(),Box<dyn Error>> {
+        let c = self.batch.copy(buf, fields, records, print);
+        self.push(Call::Right(c))
+    }
+
+    #[inline]
+    fn print_both(
+        &mut self,
+        _w: &mut V,
+        buf0: &[u8],
+        buf1: &[u8],
+        fields0: &[Range<usize>],
+        fields1: &[Range<usize>],
+        records0: &[usize],
+        records1: &[usize],
+        print0: Range<usize>,
+        print1: Range<usize>
+    ) -> Result<(),Box<dyn Error>> {
+        let c0 = self.batch.copy(buf0, fields0, records0, print0);
+        let c1 = self.batch.copy(buf1, fields1, records1, print1);
+        self.push(Call::Both(c0, c1))
+    }
+
+    #[inline]
+    fn print_many(
+        &mut self,
+        _w: &mut V,
+        inputs: &[Option<Records>],
+    ) -> Result<(),Box<dyn Error>> {
+        let c = self.batch.copy_many(inputs);
+        self.push(Call::Many(c))
+    }
+
+    #[inline]
+    fn print_header(
+        &mut self,
+        _w: &mut V,
+        inputs: &[Option<Records>],
+    ) -> Result<(),Box<dyn Error>> {
+        let c = self.batch.copy_many(inputs);
+        self.push(Call::Header(c))
+    }
+
+    #[inline]
+    fn set_field_counts(&mut self, counts: &[Option<usize>]) {
+        // an error of the thread is returned by the next call
+        let _ = self.push(Call::FieldCounts(counts.to_vec()));
+    }
+}
+
+// a call of the printer on the thread
+enum Call {
+    Left(Chunk),
+    Right(Chunk),
+    Both(Chunk, Chunk),
+    Many(Vec<Option<Chunk>>),
+    Header(Vec<Option<Chunk>>),
+    FieldCounts(Vec<Option<usize>>),
+}
+
+// the records of one input copied into a batch
+struct Chunk {
+    fields: Range<usize>,
+    records: Range<usize>,
+}
+
+// the calls of the printer with the copies of their records
+struct Batch {
+    buf: Vec<u8>,
+    fields: Vec<Range<usize>>,
+    records: Vec<usize>,
+    calls: Vec<Call>,
+}
+
+impl Batch {
+    fn new() -> Self {
+        Batch {
+            buf: Vec::with_capacity(BATCH_CAP),
+            fields: Vec::new(),
+            records: Vec::new(),
+            calls: Vec::new(),
+        }
+    }
+
+    // Copy the records `print` and their fields, which are rebased on the buffer of the batch.
+    // The records are followed by the end of their fields as in the arguments of `Print`.
+    fn copy(
+        &mut self,
+        buf: &[u8],
+        fields: &[Range<usize>],
+        records: &[usize],
+        print: Range<usize>,
+    ) -> Chunk {
+        let start = match print.start.checked_sub(1).and_then(|i| records.get(i)) {
+            Some(&start) => start,
+            None => 0,
+        };
+        let end = if print.start < print.end { records[print.end - 1] } else { start };
+        let fields = &fields[start..end];
+        let buf_start = fields.iter().map(|f| f.start).min().unwrap_or(0);
+        let buf_end = fields.iter().map(|f| f.end).max().unwrap_or(0);
+        let offset = self.buf.len();
+        self.buf.extend_from_slice(&buf[buf_start..buf_end]);
+
+        let fields_start = self.fields.len();
+        self.fields.extend(fields.iter().map(|f| f.start - buf_start + offset..f.end - buf_start + offset));
+        let records_start = self.records.len();
+        self.records.extend(records[print].iter().map(|r| r - start));
+        Chunk {
+            fields: fields_start..self.fields.len(),
+            records: records_start..self.records.len(),
+        }
+    }
+
+    fn copy_many(&mut self, inputs: &[Option<Records>]) -> Vec<Option<Chunk>> {
+        inputs.iter()
+              .map(|r| r.as_ref().map(|r| self.copy(r.buf, r.fields, r.records, r.print.clone())))
+              .collect()
+    }
+
+    // the copied records of the chunk, all of them are printed
+    #[inline]
+    fn records(&self, c: &Chunk) -> Records<'_> {
+        Records {
+            buf: &self.buf,
+            fields: &self.fields[c.fields.clone()],
+            records: &self.records[c.records.clone()],
+            print: 0..c.records.len(),
+        }
+    }
+
+    fn records_many(&self, c: &[Option<Chunk>]) -> Vec<Option<Records<'_>>> {
+        c.iter().map(|c| c.as_ref().map(|c| self.records(c))).collect()
+    }
+
+    // repeat the calls of the printer with the copied records
+    fn print<W, P>(&self, p: &mut P, w: &mut W) -> Result<(), Box<dyn Error>>
+        where W: io::Write,
+              P: Print<W>,
+    {
+        for call in &self.calls {
+            match *call {
+                Call::Left(ref c) => {
+                    let r = self.records(c);
+                    p.print_left(w, r.buf, r.fields, r.records, r.print)?;
+                }
+                Call::Right(ref c) => {
+                    let r = self.records(c);
+                    p.print_right(w, r.buf, r.fields, r.records, r.print)?;
+                }
+                Call::Both(ref c0, ref c1) => {
+                    let r0 = self.records(c0);
+                    let r1 = self.records(c1);
+                    p.print_both(
+                        w,
+                        r0.buf,
+                        r1.buf,
+                        r0.fields,
+                        r1.fields,
+                        r0.records,
+                        r1.records,
+                        r0.print,
+                        r1.print,
+                    )?;
+                }
+                Call::Many(ref c) => p.print_many(w, &self.records_many(c))?,
+                Call::Header(ref c) => p.print_header(w, &self.records_many(c))?,
+                Call::FieldCounts(ref counts) => p.set_field_counts(counts),
+            }
+        }
+        Ok(())
+    }
+}
+
+// Only the io and the csv errors keep their kind, which tells the exit status, the other errors
+// of the printer keep their message.
+fn into_thread_error(e: Box<dyn Error>) -> ThreadError {
+    let e = match e.downcast::<io::Error>() {
+        Ok(e) => return e,
+        Err(e) => e,
+    };
+    match e.downcast::<CsvError>() {
+        Ok(e) => e,
+        Err(e) => e.to_string().into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use printer::KeyFirst;
+
+    // fails after `limit` bytes
+    struct Limited {
+        out: Vec<u8>,
+        limit: usize,
+    }
+
+    impl Write for Limited {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if self.out.len() + buf.len() > self.limit {
+                return Err(io::Error::new(io::ErrorKind::WriteZero, "no space left"));
+            }
+            self.out.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    // Print the records the same way into `w` and `p`: the left records one by one, the right
+    // records together and both of them as the cartesian product of two records each.
+    fn print_all<W, P>(w: &mut W, mut p: P, lines: usize) -> Result<(), Box<dyn Error>>
+        where W: io::Write,
+              P: Print<W>,
+    {
+        let mut buf = Vec::new();
+        let mut fields = Vec::new();
+        let mut records = Vec::new();
+        for i in 0..lines {
+            let line = format!("{},{}\n", i, i * i);
+            let start = buf.len();
+            let sep = start + line.find(',').unwrap();
+            buf.extend_from_slice(line.as_bytes());
+            fields.push(start..sep);
+            fields.push(sep + 1..buf.len() - 1);
+            records.push(fields.len());
+        }
+        p.set_field_counts(&[Some(2), Some(2)]);
+        for i in 0..lines {
+            p.print_left(w, &buf, &fields, &records, i..i + 1)?;
+        }
+        p.print_right(w, &buf, &fields, &records, 0..lines)?;
+        for i in 0..lines.saturating_sub(1) {
+            p.print_both(w, &buf, &buf, &fields, &fields, &records, &records, i..i + 2, i + 1..i + 1)?;
+            p.print_both(w, &buf, &buf, &fields, &fields, &records, &records, i..i + 2, i..i + 2)?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_thread_printer() {
+        struct TestCase {
+            lines: usize,
+            limit: usize,
+            want_err: bool,
+        }
+
+        let test_cases = vec![
+            TestCase { lines: 0, limit: 0, want_err: false },
+            TestCase { lines: 10, limit: usize::MAX, want_err: false },
+            TestCase { lines: 10_000, limit: usize::MAX, want_err: false },
+            TestCase { lines: 10_000, limit: 1000, want_err: true },
+        ];
+
+        for t in test_cases {
+            let printer = || KeyFirst::with_empty(b',', b'\n', vec![0], vec![0], b"-".to_vec());
+            let mut want = Vec::new();
+            print_all(&mut want, printer(), t.lines).unwrap();
+
+            let inner = Limited { out: Vec::new(), limit: t.limit };
+            let mut p = ThreadPrinter::spawn(printer(), inner, 2);
+            let res = print_all(&mut io::sink(), &mut p, t.lines).and_then(|_| p.finish());
+            match res {
+                Ok(inner) => {
+                    assert!(!t.want_err);
+                    assert_eq!(String::from_utf8(inner.out).unwrap(), String::from_utf8(want).unwrap());
+                }
+                Err(e) => {
+                    assert!(t.want_err, "{}", e);
+                    assert!(e.is::<io::Error>(), "{}", e);
+                }
+            }
+        }
+    }
+}
@@ -1,6 +1,21 @@
 use std::io::{self, Write,};
 use std::error::Error;
 use std::ops::Range;
+use std::collections::HashMap;
+use csvroll::index_builder::unescape;
+use csvroll::writer::Writer;
+
+/// The records of one input printed by `Print::print_many`.
+#[derive(Debug, Clone)]
+pub struct Records<'a> {
+    pub buf: &'a [u8],
+    pub fields: &'a [Range<usize>],
+    pub records: &'a [usize],
+    pub print: Range<usize>,
+}
+
+// the buffer and the fields of a single record
+type Record<'a> = (&'a [u8], &'a [Range<usize>]);
 
 /// A trait for printing records in a desired format.
 pub trait Print<W:io::Write> {
@@ -12,7 +27,7 @@ pub trait Print<W:io::Write> {
         fields: &[Range<usize>],
         records: &[usize],
         print: Range<usize>
-    ) -> Result<(),Box<Error>>;
+    ) -> Result<(),Box<dyn Error>>;
     /// Print the right records into `w`.
     fn print_right(
         &mut self,
@@ -21,8 +36,9 @@ pub trait Print<W:io::Write> {
         fields: &[Range<usize>],
         records: &[usize],
         print: Range<usize>
-    ) -> Result<(),Box<Error>>;
+    ) -> Result<(),Box<dyn Error>>;
     /// Print both left anf right records into `w`.
+    #[allow(clippy::too_many_arguments)]
     fn print_both(
         &mut self,
         w: &mut W,
@@ -34,61 +50,998 @@ pub trait Print<W:io::Write> {
         records1: &[usize],
         print0: Range<usize>,
         print1: Range<usize>
-    ) -> Result<(),Box<Error>>;
+    ) -> Result<(),Box<dyn Error>>;
+    /// Print the cartesian product of the records of any number of inputs into `w`. The missing
+    /// inputs are `None`, at least one input must be present.
+    ///
+    /// By default, only two inputs are supported.
+    fn print_many(
+        &mut self,
+        w: &mut W,
+        inputs: &[Option<Records>],
+    ) -> Result<(),Box<dyn Error>> {
+        match inputs {
+            [Some(r0), Some(r1)] => self.print_both(
+                w,
+                r0.buf,
+                r1.buf,
+                r0.fields,
+                r1.fields,
+                r0.records,
+                r1.records,
+                r0.print.clone(),
+                r1.print.clone(),
+            ),
+            [Some(r0), None] => self.print_left(w, r0.buf, r0.fields, r0.records, r0.print.clone()),
+            [None, Some(r1)] => self.print_right(w, r1.buf, r1.fields, r1.records, r1.print.clone()),
+            _ => Err("the printer supports only two inputs".into()),
+        }
+    }
+    /// Print the header records of the inputs into `w`, the missing inputs are `None`.
+    ///
+    /// By default, the header is printed like any other records.
+    fn print_header(
+        &mut self,
+        w: &mut W,
+        inputs: &[Option<Records>],
+    ) -> Result<(),Box<dyn Error>> {
+        self.print_many(w, inputs)
+    }
+    /// Set the number of fields of the records of each input, if known. It is called before
+    /// the records are printed, so that the unmatched records can be padded to the same number of
+    /// columns. Only the first known count of each input is taken into account.
+    fn set_field_counts(&mut self, _counts: &[Option<usize>]) {}
+}
+
+impl<W:io::Write, P: Print<W>> Print<W> for &mut P {
+    #[inline]
+    fn print_left(
+        &mut self,
+        w: &mut W,
+        buf: &[u8],
+        fields: &[Range<usize>],
+        records: &[usize],
+        print: Range<usize>
+    ) -> Result<(),Box<dyn Error>> {
+        (**self).print_left(w, buf, fields, records, print)
+    }
+
+    #[inline]
+    fn print_right(
+        &mut self,
+        w: &mut W,
+        buf: &[u8],
+        fields: &[Range<usize>],
+        records: &[usize],
+        print: Range<usize>
+    ) -> Result<(),Box<dyn Error>> {
+        (**self).print_right(w, buf, fields, records, print)
+    }
+
+    #[inline]
+    fn print_both(
+        &mut self,
+        w: &mut W,
+        buf0: &[u8],
+        buf1: &[u8],
+        fields0: &[Range<usize>],
+        fields1: &[Range<usize>],
+        records0: &[usize],
+        records1: &[usize],
+        print0: Range<usize>,
+        print1: Range<usize>
+    ) -> Result<(),Box<dyn Error>> {
+        (**self).print_both(w, buf0, buf1, fields0, fields1, records0, records1, print0, print1)
+    }
+
+    #[inline]
+    fn print_many(
+        &mut self,
+        w: &mut W,
+        inputs: &[Option<Records>],
+    ) -> Result<(),Box<dyn Error>> {
+        (**self).print_many(w, inputs)
+    }
+
+    #[inline]
+    fn print_header(
+        &mut self,
+        w: &mut W,
+        inputs: &[Option<Records>],
+    ) -> Result<(),Box<dyn Error>> {
+        (**self).print_header(w, inputs)
+    }
+
+    #[inline]
+    fn set_field_counts(&mut self, counts: &[Option<usize>]) {
+        (**self).set_field_counts(counts)
+    }
+}
+
+/// The indicator column, which tells if the row is made of the left record, the right record or
+/// both, like `_merge` of pandas.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Indicator {
+    name: Vec<u8>,
+    left: Vec<u8>,
+    right: Vec<u8>,
+    both: Vec<u8>,
+    first: bool,
+}
+
+impl Indicator {
+    /// Create an indicator column named `name` in the header, which is appended to the rows and
+    /// labels them `left_only`, `right_only` or `both`.
+    pub fn new(name: Vec<u8>) -> Self {
+        Indicator {
+            name,
+            left: b"left_only".to_vec(),
+            right: b"right_only".to_vec(),
+            both: b"both".to_vec(),
+            first: false,
+        }
+    }
+
+    /// Label the rows of the left record, the right record and both records with `left`, `right`
+    /// and `both`.
+    pub fn with_labels(mut self, left: Vec<u8>, right: Vec<u8>, both: Vec<u8>) -> Self {
+        self.left = left;
+        self.right = right;
+        self.both = both;
+        self
+    }
+
+    /// Prepend the column to the rows instead of appending it.
+    pub fn with_first(mut self, first: bool) -> Self {
+        self.first = first;
+        self
+    }
+
+    #[inline]
+    pub fn name(&self) -> &[u8] {
+        &self.name
+    }
+
+    #[inline]
+    pub fn first(&self) -> bool {
+        self.first
+    }
+
+    /// Return the label of the row, at least one of the records must be present.
+    #[inline]
+    pub fn label(&self, left: bool, right: bool) -> &[u8] {
+        match (left, right) {
+            (true, false) => &self.left,
+            (false, true) => &self.right,
+            _ => &self.both,
+        }
+    }
+
+    // the label of the row of two inputs or the name for the header
+    #[inline]
+    fn row_label(&self, row: &[Option<Record>], is_header: bool) -> &[u8] {
+        if is_header {
+            &self.name
+        } else {
+            self.label(row[0].is_some(), row[1].is_some())
+        }
+    }
 }
 
 /// Print the records in the following format: first the key fields followed by non-key
 /// fields.
 #[derive(Clone)]
-pub struct KeyFirst {
-    delimiter: u8,
-    terminator: u8,
-    key_idx0: Vec<usize>,
-    key_idx0_asc: Vec<usize>,
-    key_idx1: Vec<usize>,
-    key_idx1_asc: Vec<usize>,
-    key_buf: Vec<u8>,
+pub struct KeyFirst {
+    writer: Writer,
+    key_idx: Vec<Vec<usize>>,
+    key_idx_asc: Vec<Vec<usize>>,
+    key_buf: Vec<u8>,
+    empty: Option<Vec<u8>>,
+    counts: Vec<Option<usize>>,
+    indicator: Option<Indicator>,
+}
+
+impl Default for KeyFirst {
+    fn default() -> Self {
+        KeyFirst::from_keys(b',', b'\n', vec![vec![0], vec![0]], None)
+    }
+}
+
+impl KeyFirst {
+    pub fn from_parts(
+        delimiter: u8,
+        terminator: u8,
+        key_idx0: Vec<usize>,
+        key_idx1: Vec<usize>,
+    ) -> Self {
+        Self::from_keys(delimiter, terminator, vec![key_idx0, key_idx1], None)
+    }
+
+    /// Create a new instance of `KeyFirst`, which pads the unmatched records with the `empty`
+    /// fields, so that they have the same number of columns as the matched records.
+    pub fn with_empty(
+        delimiter: u8,
+        terminator: u8,
+        key_idx0: Vec<usize>,
+        key_idx1: Vec<usize>,
+        empty: Vec<u8>,
+    ) -> Self {
+        Self::from_keys(delimiter, terminator, vec![key_idx0, key_idx1], Some(empty))
+    }
+
+    /// Create a new instance of `KeyFirst` for any number of inputs with the key fields
+    /// `key_idx` of each input.
+    pub fn from_keys(
+        delimiter: u8,
+        terminator: u8,
+        key_idx: Vec<Vec<usize>>,
+        empty: Option<Vec<u8>>,
+    ) -> Self {
+        Self::with_writer(Writer::new(delimiter, terminator), key_idx, empty)
+    }
+
+    /// Create a new instance of `KeyFirst`, which writes the fields using `writer`.
+    pub fn with_writer(
+        writer: Writer,
+        key_idx: Vec<Vec<usize>>,
+        empty: Option<Vec<u8>>,
+    ) -> Self {
+        let key_idx_asc = key_idx.iter()
+                                 .map(|k| {
+                                     let mut k = k.clone();
+                                     k.sort();
+                                     k
+                                 })
+                                 .collect();
+        let counts = vec![None; key_idx.len()];
+
+        KeyFirst {
+            writer,
+            key_idx,
+            key_idx_asc,
+            key_buf: Vec::new(),
+            empty,
+            counts,
+            indicator: None,
+        }
+    }
+
+    /// Add the `indicator` column to the rows, only two inputs are supported. The unmatched
+    /// records are padded like with the `empty` fields, so that the column stays under its
+    /// name in the header. The padding is empty, unless the `empty` fields are given.
+    pub fn with_indicator(mut self, indicator: Indicator) -> Self {
+        self.indicator = Some(indicator);
+        self
+    }
+
+    // the number of the padding fields in place of the non-key fields of the `input`
+    #[inline]
+    fn padding(&self, input: usize) -> usize {
+        match self.counts[input] {
+            Some(c) if self.is_padded() => c.saturating_sub(self.key_idx[input].len()),
+            _ => 0,
+        }
+    }
+
+    // the number of the padding fields in place of the key fields of the first input, which
+    // the `input` lacks, e.g. the right input of an interval join has none
+    #[inline]
+    fn key_padding(&self, input: usize) -> usize {
+        if self.is_padded() {
+            self.key_idx[0].len().saturating_sub(self.key_idx[input].len())
+        } else {
+            0
+        }
+    }
+
+    // the missing inputs of more than two are always padded, otherwise the fields of the
+    // following inputs would shift to their columns
+    #[inline]
+    fn is_padded(&self) -> bool {
+        self.empty.is_some() || self.indicator.is_some() || self.key_idx.len() > 2
+    }
+
+    /// Write a row of the records of the inputs, at least one of them must be present.
+    fn print_row<W:io::Write>(
+        &self,
+        w: &mut W,
+        row: &[Option<Record>],
+        is_header: bool,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut is_first = true;
+        if let Some(ref ind) = self.indicator {
+            if ind.first() {
+                write_field(w, &mut is_first, &self.writer, ind.row_label(row, is_header))?;
+            }
+        }
+        // write key fields of the first present input first
+        if let Some((i, &Some((buf, rec)))) = row.iter().enumerate().find(|&(_, r)| r.is_some()) {
+            for &k in &self.key_idx[i] {
+                write_field(w, &mut is_first, &self.writer, &buf[rec[k].clone()])?;
+            }
+            for _ in 0..self.key_padding(i) {
+                write_field(w, &mut is_first, &self.writer, self.empty.as_deref().unwrap_or(b""))?;
+            }
+        }
+        for (i, r) in row.iter().enumerate() {
+            match *r {
+                Some((buf, rec)) => {
+                    // write non-key fields in their order
+                    for (j, f) in rec.iter().enumerate() {
+                        if self.key_idx_asc[i].binary_search(&j).is_err() {
+                            write_field(w, &mut is_first, &self.writer, &buf[f.clone()])?;
+                        }
+                    }
+                }
+                None => {
+                    let empty = self.empty.as_deref().unwrap_or(b"");
+                    for _ in 0..self.padding(i) {
+                        write_field(w, &mut is_first, &self.writer, empty)?;
+                    }
+                }
+            }
+        }
+        if let Some(ref ind) = self.indicator {
+            if !ind.first() {
+                write_field(w, &mut is_first, &self.writer, ind.row_label(row, is_header))?;
+            }
+        }
+        w.write_all(&[self.writer.terminator()])?;
+        Ok(())
+    }
+}
+
+impl<W:io::Write> Print<W> for KeyFirst {
+    #[inline]
+    fn print_left(
+        &mut self,
+        w: &mut W,
+        buf: &[u8],
+        fields: &[Range<usize>],
+        records: &[usize],
+        print: Range<usize>
+    ) -> Result<(),Box<dyn Error>> {
+        print_single(
+            w,
+            buf,
+            fields,
+            records,
+            print,
+            &self.writer,
+            &self.key_idx[0],
+            &self.key_idx_asc[0],
+            self.empty.as_deref().unwrap_or(b""),
+            0,
+            self.padding(1),
+            self.indicator.as_ref().map(|ind| (ind.label(true, false), ind.first())),
+        )
+    }
+        
+    #[inline]
+    fn print_right(
+        &mut self,
+        w: &mut W,
+        buf: &[u8],
+        fields: &[Range<usize>],
+        records: &[usize],
+        print: Range<usize>
+    ) -> Result<(),Box<dyn Error>> {
+        print_single(
+            w,
+            buf,
+            fields,
+            records,
+            print,
+            &self.writer,
+            &self.key_idx[1],
+            &self.key_idx_asc[1],
+            self.empty.as_deref().unwrap_or(b""),
+            self.key_padding(1) + self.padding(0),
+            0,
+            self.indicator.as_ref().map(|ind| (ind.label(false, true), ind.first())),
+        )
+    }
+        
+    #[inline]
+    fn print_both(
+        &mut self,
+        w: &mut W,
+        buf0: &[u8],
+        buf1: &[u8],
+        fields0: &[Range<usize>],
+        fields1: &[Range<usize>],
+        records0: &[usize],
+        records1: &[usize],
+        print0: Range<usize>,
+        print1: Range<usize>
+    ) -> Result<(),Box<dyn Error>> {
+        let start0 = match print0.start.checked_sub(1).and_then(|i| records0.get(i)) {
+            Some(&start) => start,
+            None => 0,
+        };
+        let start1 = match print1.start.checked_sub(1).and_then(|i| records1.get(i)) {
+            Some(&start) => start,
+            None => 0,
+        };
+        let mut r0 = start0..start0;
+        let mut r1 = start1..start1;
+        for r0e in &records0[print0] {
+            r0.end = *r0e;
+            let r0f = &fields0[r0.clone()];
+
+            // the key fields of the left record, which are equal to the right ones only
+            // according to the comparison, e.g. ignoring the case
+            self.key_buf.clear();
+            for (i, k) in self.key_idx[0].iter().enumerate() {
+                if i > 0 {
+                    self.key_buf.write_all(&[self.writer.delimiter()])?;
+                }
+                self.writer.write_field(&mut self.key_buf, &buf0[r0f[*k].clone()])?;
+            }
+
+            for r1e in &records1[print1.clone()] {
+                r1.end = *r1e;
+                let r1f = &fields1[r1.clone()];
+                // write key fields first
+                if let Some(ref ind) = self.indicator {
+                    if ind.first() {
+                        self.writer.write_field(w, ind.label(true, true))?;
+                        w.write_all(&[self.writer.delimiter()])?;
+                    }
+                }
+                w.write_all(&self.key_buf)?;
+
+                // write non-key fields that lie between key fields
+                let mut start = 0;
+                for k in &self.key_idx_asc[0] {
+                    for f in &r0f[start..*k] {
+                        w.write_all(&[self.writer.delimiter()])?;
+                        self.writer.write_field(w, &buf0[f.clone()])?;
+                    }
+                    start = *k + 1;
+                }
+                // write remaining non-key fields
+                for f in &r0f[start..] {
+                    w.write_all(&[self.writer.delimiter()])?;
+                    self.writer.write_field(w, &buf0[f.clone()])?;
+                }
+
+                start = 0;
+                // write non-key fields that lie in between key fields
+                for k in &self.key_idx_asc[1] {
+                    for f in &r1f[start..*k] {
+                        w.write_all(&[self.writer.delimiter()])?;
+                        self.writer.write_field(w, &buf1[f.clone()])?;
+                    }
+                    start = *k + 1;
+                }
+                // write remaining non-key fields
+                for f in &r1f[start..] {
+                    w.write_all(&[self.writer.delimiter()])?;
+                    self.writer.write_field(w, &buf1[f.clone()])?;
+                }
+                if let Some(ref ind) = self.indicator {
+                    if !ind.first() {
+                        w.write_all(&[self.writer.delimiter()])?;
+                        self.writer.write_field(w, ind.label(true, true))?;
+                    }
+                }
+                w.write_all(&[self.writer.terminator()])?;
+                r1.start = r1.end;
+            }
+            r0.start = r0.end;
+            r1.start = start1;
+        }
+        Ok(())
+    }
+
+    #[inline]
+    fn print_many(
+        &mut self,
+        w: &mut W,
+        inputs: &[Option<Records>],
+    ) -> Result<(),Box<dyn Error>> {
+        if inputs.len() != self.key_idx.len() {
+            return Err("the number of the inputs differs from the number of the keys".into());
+        }
+        if self.indicator.is_some() && inputs.len() != 2 {
+            return Err("the indicator column supports only two inputs".into());
+        }
+        for_each_row(inputs, |row| self.print_row(w, row, false))
+    }
+
+    #[inline]
+    fn print_header(
+        &mut self,
+        w: &mut W,
+        inputs: &[Option<Records>],
+    ) -> Result<(),Box<dyn Error>> {
+        if inputs.len() != self.key_idx.len() {
+            return Err("the number of the inputs differs from the number of the keys".into());
+        }
+        if self.indicator.is_some() && inputs.len() != 2 {
+            return Err("the indicator column supports only two inputs".into());
+        }
+        for_each_row(inputs, |row| self.print_row(w, row, true))
+    }
+
+    #[inline]
+    fn set_field_counts(&mut self, counts: &[Option<usize>]) {
+        for (c, &n) in self.counts.iter_mut().zip(counts) {
+            *c = c.or(n);
+        }
+    }
+}
+
+/// An output column of `Columns`. The inputs and the fields are numbered from zero.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Column {
+    /// The key fields.
+    Key,
+    /// The fields `start..end` of the `input`, `end` is `None` for all the fields up to the last.
+    Fields {
+        input: usize,
+        start: usize,
+        end: Option<usize>,
+    },
+    /// The non-key fields of the `input`, which are not selected by any other column.
+    Rest(usize),
+}
+
+impl Column {
+    // test if the field `i` of the `input` is explicitly selected by the column
+    #[inline]
+    fn selects(&self, input: usize, i: usize) -> bool {
+        match *self {
+            Column::Fields { input: c_input, start, end } => {
+                c_input == input && i >= start && end.is_none_or(|e| i < e)
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Parse the output column specification, e.g. `0,1.3,2.2-4,1.*`.
+///
+/// The columns are separated by a comma and have the following form:
+///   * `0` - the key fields
+///   * `F.N` - the field `N` of the file `F`
+///   * `F.N-M`, `F.N-` - the fields `N` through `M` or the last field of the file `F`
+///   * `F.*` - the non-key fields of the file `F`, which are not selected by any other column
+///
+/// The files and the fields are numbered from one.
+pub fn parse_columns(spec: &str, inputs: usize) -> Result<Vec<Column>, Box<dyn Error>> {
+    let mut columns = Vec::new();
+    for c in spec.split(',') {
+        let err = || format!("could not parse the output column '{}'", c);
+        if c == "0" {
+            columns.push(Column::Key);
+            continue;
+        }
+        let mut it = c.splitn(2, '.');
+        let input = match it.next().and_then(|f| f.parse::<usize>().ok()) {
+            Some(f) if f >= 1 && f <= inputs => f - 1,
+            _ => return Err(err().into()),
+        };
+        let fields = it.next().ok_or_else(err)?;
+        if fields == "*" {
+            columns.push(Column::Rest(input));
+            continue;
+        }
+        let mut it = fields.splitn(2, '-');
+        let start = match it.next().and_then(|n| n.parse::<usize>().ok()) {
+            Some(n) if n >= 1 => n - 1,
+            _ => return Err(err().into()),
+        };
+        let end = match it.next() {
+            None => Some(start + 1),
+            Some("") => None,
+            Some(m) => match m.parse::<usize>() {
+                Ok(m) if m > start => Some(m),
+                _ => return Err(err().into()),
+            },
+        };
+        columns.push(Column::Fields { input, start, end });
+    }
+    Ok(columns)
+}
+
+/// Print the records in the format given by the output columns.
+///
+/// The columns of the missing input are printed as `empty` fields. `Column::Rest` and the open
+/// ranges of the missing input are omitted, unless `empty` is set and the number of fields of the
+/// input is known. The key fields are taken from the first present input.
+#[derive(Clone)]
+pub struct Columns {
+    writer: Writer,
+    key_idx: Vec<Vec<usize>>,
+    columns: Vec<Column>,
+    empty: Option<Vec<u8>>,
+    counts: Vec<Option<usize>>,
+    indicator: Option<Indicator>,
+}
+
+impl Columns {
+    /// Create a new instance of `Columns` with the key fields `key_idx` of each input.
+    pub fn from_parts(
+        delimiter: u8,
+        terminator: u8,
+        key_idx: Vec<Vec<usize>>,
+        columns: Vec<Column>,
+        empty: Option<Vec<u8>>,
+    ) -> Self {
+        Self::with_writer(Writer::new(delimiter, terminator), key_idx, columns, empty)
+    }
+
+    /// Create a new instance of `Columns`, which writes the fields using `writer`.
+    pub fn with_writer(
+        writer: Writer,
+        key_idx: Vec<Vec<usize>>,
+        columns: Vec<Column>,
+        empty: Option<Vec<u8>>,
+    ) -> Self {
+        let counts = vec![None; key_idx.len()];
+        Columns {
+            writer,
+            key_idx,
+            columns,
+            empty,
+            counts,
+            indicator: None,
+        }
+    }
+
+    /// Add the `indicator` column to the rows, only two inputs are supported.
+    pub fn with_indicator(mut self, indicator: Indicator) -> Self {
+        self.indicator = Some(indicator);
+        self
+    }
+
+    // test if the field `i` of the `input` is printed by `Column::Rest`
+    #[inline]
+    fn is_rest(&self, input: usize, i: usize) -> bool {
+        !self.key_idx[input].contains(&i) && !self.columns.iter().any(|c| c.selects(input, i))
+    }
+
+    /// Write a row of the records of the inputs, at least one of them must be present.
+    fn print_row<W:io::Write>(
+        &self,
+        w: &mut W,
+        row: &[Option<Record>],
+        is_header: bool,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut is_first = true;
+        if let Some(ref ind) = self.indicator {
+            if ind.first() {
+                write_field(w, &mut is_first, &self.writer, ind.row_label(row, is_header))?;
+            }
+        }
+        let empty = self.empty.as_deref().unwrap_or(b"");
+        // the number of fields of the missing input used for the padding
+        let pad_count = |input: usize| {
+            if self.empty.is_none() {
+                0
+            } else {
+                self.counts[input].unwrap_or(0)
+            }
+        };
+        for c in &self.columns {
+            match *c {
+                Column::Key => {
+                    let first = row.iter().enumerate().find(|&(_, r)| r.is_some());
+                    if let Some((i, &Some((buf, rec)))) = first {
+                        for &k in &self.key_idx[i] {
+                            write_field(w, &mut is_first, &self.writer, &buf[rec[k].clone()])?;
+                        }
+                        // the key fields of the first input, which the input lacks
+                        for _ in self.key_idx[i].len()..self.key_idx[0].len() {
+                            write_field(w, &mut is_first, &self.writer, empty)?;
+                        }
+                    }
+                }
+                Column::Fields { input, start, end } => {
+                    match (row[input], end) {
+                        (Some((buf, rec)), Some(end)) => {
+                            // the fields beyond the end of the record are printed empty
+                            for i in start..end {
+                                let f = rec.get(i).map_or(empty, |f| &buf[f.clone()]);
+                                write_field(w, &mut is_first, &self.writer, f)?;
+                            }
+                        }
+                        (Some((buf, rec)), None) => {
+                            for f in rec.iter().skip(start) {
+                                write_field(w, &mut is_first, &self.writer, &buf[f.clone()])?;
+                            }
+                        }
+                        (None, Some(end)) => {
+                            for _ in start..end {
+                                write_field(w, &mut is_first, &self.writer, empty)?;
+                            }
+                        }
+                        (None, None) => {
+                            for _ in start..pad_count(input) {
+                                write_field(w, &mut is_first, &self.writer, empty)?;
+                            }
+                        }
+                    }
+                }
+                Column::Rest(input) => {
+                    match row[input] {
+                        Some((buf, rec)) => {
+                            for (i, f) in rec.iter().enumerate() {
+                                if self.is_rest(input, i) {
+                                    write_field(w, &mut is_first, &self.writer, &buf[f.clone()])?;
+                                }
+                            }
+                        }
+                        None => {
+                            for i in 0..pad_count(input) {
+                                if self.is_rest(input, i) {
+                                    write_field(w, &mut is_first, &self.writer, empty)?;
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        if let Some(ref ind) = self.indicator {
+            if !ind.first() {
+                write_field(w, &mut is_first, &self.writer, ind.row_label(row, is_header))?;
+            }
+        }
+        w.write_all(&[self.writer.terminator()])?;
+        Ok(())
+    }
+}
+
+impl<W:io::Write> Print<W> for Columns {
+    #[inline]
+    fn print_left(
+        &mut self,
+        w: &mut W,
+        buf: &[u8],
+        fields: &[Range<usize>],
+        records: &[usize],
+        print: Range<usize>
+    ) -> Result<(),Box<dyn Error>> {
+        for n in print {
+            self.print_row(w, &[Some((buf, record(fields, records, n))), None], false)?;
+        }
+        Ok(())
+    }
+
+    #[inline]
+    fn print_right(
+        &mut self,
+        w: &mut W,
+        buf: &[u8],
+        fields: &[Range<usize>],
+        records: &[usize],
+        print: Range<usize>
+    ) -> Result<(),Box<dyn Error>> {
+        for n in print {
+            self.print_row(w, &[None, Some((buf, record(fields, records, n)))], false)?;
+        }
+        Ok(())
+    }
+
+    #[inline]
+    fn print_both(
+        &mut self,
+        w: &mut W,
+        buf0: &[u8],
+        buf1: &[u8],
+        fields0: &[Range<usize>],
+        fields1: &[Range<usize>],
+        records0: &[usize],
+        records1: &[usize],
+        print0: Range<usize>,
+        print1: Range<usize>
+    ) -> Result<(),Box<dyn Error>> {
+        for n0 in print0 {
+            let rec0 = record(fields0, records0, n0);
+            for n1 in print1.clone() {
+                let rec1 = record(fields1, records1, n1);
+                self.print_row(w, &[Some((buf0, rec0)), Some((buf1, rec1))], false)?;
+            }
+        }
+        Ok(())
+    }
+
+    #[inline]
+    fn print_many(
+        &mut self,
+        w: &mut W,
+        inputs: &[Option<Records>],
+    ) -> Result<(),Box<dyn Error>> {
+        if inputs.len() != self.key_idx.len() {
+            return Err("the number of the inputs differs from the number of the keys".into());
+        }
+        if self.indicator.is_some() && inputs.len() != 2 {
+            return Err("the indicator column supports only two inputs".into());
+        }
+        for_each_row(inputs, |row| self.print_row(w, row, false))
+    }
+
+    #[inline]
+    fn print_header(
+        &mut self,
+        w: &mut W,
+        inputs: &[Option<Records>],
+    ) -> Result<(),Box<dyn Error>> {
+        if inputs.len() != self.key_idx.len() {
+            return Err("the number of the inputs differs from the number of the keys".into());
+        }
+        if self.indicator.is_some() && inputs.len() != 2 {
+            return Err("the indicator column supports only two inputs".into());
+        }
+        for_each_row(inputs, |row| self.print_row(w, row, true))
+    }
+
+    #[inline]
+    fn set_field_counts(&mut self, counts: &[Option<usize>]) {
+        for (c, &n) in self.counts.iter_mut().zip(counts) {
+            *c = c.or(n);
+        }
+    }
+}
+
+/// Print each row as a JSON object on its own line, i.e. JSON Lines.
+///
+/// The key fields are taken from the first present input and named after the fields of the first
+/// input. The other fields are named by the header, if it is known and the name is unique, or by
+/// the input and the number of the field, e.g. `left_2` or `input3_2`. The fields of the missing
+/// input are `null`, if the number of its fields is known.
+#[derive(Clone)]
+pub struct Jsonl {
+    key_idx: Vec<Vec<usize>>,
+    key_idx_asc: Vec<Vec<usize>>,
+    names: Vec<Vec<Option<String>>>,
+    counts: Vec<Option<usize>>,
+    quote: Option<u8>,
+    indicator: Option<Indicator>,
 }
 
-impl Default for KeyFirst {
-    fn default() -> Self {
-        KeyFirst {
-            delimiter: b',',
-            terminator: b'\n',
-            key_idx0: vec![0],
-            key_idx0_asc: vec![0],
-            key_idx1: vec![0],
-            key_idx1_asc: vec![0],
-            key_buf: Vec::new(),
+impl Jsonl {
+    /// Create a new instance of `Jsonl` with the key fields `key_idx` of each input.
+    pub fn from_keys(key_idx: Vec<Vec<usize>>) -> Self {
+        let key_idx_asc = key_idx.iter()
+                                 .map(|k| {
+                                     let mut k = k.clone();
+                                     k.sort();
+                                     k
+                                 })
+                                 .collect();
+        let names = vec![Vec::new(); key_idx.len()];
+        let counts = vec![None; key_idx.len()];
+        Jsonl {
+            key_idx,
+            key_idx_asc,
+            names,
+            counts,
+            quote: None,
+            indicator: None,
         }
     }
-}
 
-impl KeyFirst {
-    pub fn from_parts(
-        delimiter: u8,
-        terminator: u8,
-        key_idx0: Vec<usize>,
-        key_idx1: Vec<usize>,
-    ) -> Self {
-        let mut key_idx0_asc = key_idx0.clone();
-        let mut key_idx1_asc = key_idx1.clone();
-        key_idx0_asc.sort(); 
-        key_idx1_asc.sort(); 
+    /// Add the `indicator` field to the objects, only two inputs are supported.
+    pub fn with_indicator(mut self, indicator: Indicator) -> Self {
+        self.indicator = Some(indicator);
+        self
+    }
 
-        KeyFirst {
-            delimiter ,
-            terminator ,
-            key_idx0 ,
-            key_idx0_asc ,
-            key_idx1 ,
-            key_idx1_asc ,
-            key_buf: Vec::new(),
+    /// Collapse the escaped (doubled) `quote` in the fields of the inputs parsed with the quote.
+    pub fn with_quote(mut self, quote: Option<u8>) -> Self {
+        self.quote = quote;
+        self
+    }
+
+    #[inline]
+    fn write_value<W:io::Write>(&self, w: &mut W, field: &[u8]) -> io::Result<()> {
+        match self.quote {
+            Some(q) => write_json_string(w, &unescape(field, q)),
+            None => write_json_string(w, field),
+        }
+    }
+
+    #[inline]
+    fn is_key(&self, input: usize, i: usize) -> bool {
+        self.key_idx_asc[input].binary_search(&i).is_ok()
+    }
+
+    // write the indicator field of the row
+    fn write_indicator<W:io::Write>(&self, w: &mut W, row: &[Option<Record>]) -> io::Result<()> {
+        if let Some(ref ind) = self.indicator {
+            write_json_string(w, ind.name())?;
+            w.write_all(b":")?;
+            write_json_string(w, ind.row_label(row, false))?;
+        }
+        Ok(())
+    }
+
+    // write the name of the field `i` of the `input`
+    fn write_name<W:io::Write>(&self, w: &mut W, input: usize, i: usize) -> Result<(), Box<dyn Error>> {
+        match self.names[input].get(i) {
+            Some(Some(name)) => write_json_string(w, name.as_bytes())?,
+            _ => match self.key_idx.len() {
+                2 if input == 0 => write!(w, "\"left_{}\"", i + 1)?,
+                2 => write!(w, "\"right_{}\"", i + 1)?,
+                _ => write!(w, "\"input{}_{}\"", input + 1, i + 1)?,
+            },
         }
+        Ok(())
+    }
+
+    /// Write a row of the records of the inputs, at least one of them must be present.
+    fn print_row<W:io::Write>(
+        &self,
+        w: &mut W,
+        row: &[Option<Record>],
+    ) -> Result<(), Box<dyn Error>> {
+        let mut is_first = true;
+        let mut write_sep = |w: &mut W| {
+            if is_first {
+                is_first = false;
+                w.write_all(b"{")
+            } else {
+                w.write_all(b",")
+            }
+        };
+        if self.indicator.as_ref().is_some_and(|ind| ind.first()) {
+            write_sep(w)?;
+            self.write_indicator(w, row)?;
+        }
+        if let Some((i, &Some((buf, rec)))) = row.iter().enumerate().find(|&(_, r)| r.is_some()) {
+            for (j, &k) in self.key_idx[i].iter().enumerate() {
+                write_sep(w)?;
+                self.write_name(w, 0, self.key_idx[0][j])?;
+                w.write_all(b":")?;
+                self.write_value(w, &buf[rec[k].clone()])?;
+            }
+            // the key fields of the first input, which the input lacks
+            for &k in &self.key_idx[0][self.key_idx[i].len().min(self.key_idx[0].len())..] {
+                write_sep(w)?;
+                self.write_name(w, 0, k)?;
+                w.write_all(b":null")?;
+            }
+        }
+        for (i, r) in row.iter().enumerate() {
+            match *r {
+                Some((buf, rec)) => {
+                    for (j, f) in rec.iter().enumerate() {
+                        if !self.is_key(i, j) {
+                            write_sep(w)?;
+                            self.write_name(w, i, j)?;
+                            w.write_all(b":")?;
+                            self.write_value(w, &buf[f.clone()])?;
+                        }
+                    }
+                }
+                None => {
+                    for j in 0..self.counts[i].unwrap_or(0) {
+                        if !self.is_key(i, j) {
+                            write_sep(w)?;
+                            self.write_name(w, i, j)?;
+                            w.write_all(b":null")?;
+                        }
+                    }
+                }
+            }
+        }
+        if self.indicator.as_ref().is_some_and(|ind| !ind.first()) {
+            write_sep(w)?;
+            self.write_indicator(w, row)?;
+        }
+        if is_first {
+            w.write_all(b"{")?;
+        }
+        w.write_all(b"}\n")?;
+        Ok(())
     }
 }
 
-impl<W:io::Write> Print<W> for KeyFirst {
+impl<W:io::Write> Print<W> for Jsonl {
     #[inline]
     fn print_left(
         &mut self,
@@ -97,20 +1050,13 @@ impl<W:io::Write> Print<W> for KeyFirst {
         fields: &[Range<usize>],
         records: &[usize],
         print: Range<usize>
-    ) -> Result<(),Box<Error>> {
-        print_single(
-            w,
-            buf,
-            fields,
-            records,
-            print,
-            self.delimiter,
-            self.terminator,
-            &self.key_idx0,
-            &self.key_idx0_asc,
-        )
+    ) -> Result<(),Box<dyn Error>> {
+        for n in print {
+            self.print_row(w, &[Some((buf, record(fields, records, n))), None])?;
+        }
+        Ok(())
     }
-        
+
     #[inline]
     fn print_right(
         &mut self,
@@ -119,20 +1065,13 @@ impl<W:io::Write> Print<W> for KeyFirst {
         fields: &[Range<usize>],
         records: &[usize],
         print: Range<usize>
-    ) -> Result<(),Box<Error>> {
-        print_single(
-            w,
-            buf,
-            fields,
-            records,
-            print,
-            self.delimiter,
-            self.terminator,
-            &self.key_idx1,
-            &self.key_idx1_asc,
-        )
+    ) -> Result<(),Box<dyn Error>> {
+        for n in print {
+            self.print_row(w, &[None, Some((buf, record(fields, records, n)))])?;
+        }
+        Ok(())
     }
-        
+
     #[inline]
     fn print_both(
         &mut self,
@@ -145,91 +1084,189 @@ impl<W:io::Write> Print<W> for KeyFirst {
         records1: &[usize],
         print0: Range<usize>,
         print1: Range<usize>
-    ) -> Result<(),Box<Error>> {
-        let mut is_first = true;
-        let start0 = match print0.start.checked_sub(1).and_then(|i| records0.get(i)) {
-            Some(&start) => start,
-            None => 0,
-        };
-        let start1 = match print1.start.checked_sub(1).and_then(|i| records1.get(i)) {
-            Some(&start) => start,
-            None => 0,
-        };
-        let mut r0 = start0..start0;
-        let mut r1 = start1..start1;
-        self.key_buf.clear();
-        for r0e in &records0[print0] {
-            r0.end = *r0e;
-            let r0f = &fields0[r0.clone()];
+    ) -> Result<(),Box<dyn Error>> {
+        for n0 in print0 {
+            let rec0 = record(fields0, records0, n0);
+            for n1 in print1.clone() {
+                let rec1 = record(fields1, records1, n1);
+                self.print_row(w, &[Some((buf0, rec0)), Some((buf1, rec1))])?;
+            }
+        }
+        Ok(())
+    }
 
-            for r1e in &records1[print1.clone()] {
-                r1.end = *r1e;
-                let r1f = &fields1[r1.clone()];
-                // write key fields first
-                if self.key_buf.is_empty() {
-                    for k in &self.key_idx0 {
-                        if !is_first {
-                            self.key_buf.write_all(&[self.delimiter])?;
-                        } else {
-                            is_first = false;
-                        }
-                        self.key_buf.write_all(&buf0[r0f[*k].clone()])?;
-                    }
-                }
-                w.write_all(&self.key_buf)?;
+    #[inline]
+    fn print_many(
+        &mut self,
+        w: &mut W,
+        inputs: &[Option<Records>],
+    ) -> Result<(),Box<dyn Error>> {
+        if inputs.len() != self.key_idx.len() {
+            return Err("the number of the inputs differs from the number of the keys".into());
+        }
+        if self.indicator.is_some() && inputs.len() != 2 {
+            return Err("the indicator column supports only two inputs".into());
+        }
+        for_each_row(inputs, |row| self.print_row(w, row))
+    }
 
-                // write non-key fields that lie between key fields
-                let mut start = 0;
-                for k in &self.key_idx0_asc {
-                    for f in &r0f[start..*k] {
-                        w.write_all(&[self.delimiter])?;
-                        w.write_all(&buf0[f.clone()])?;
+    /// Learn the names of the fields from the header, nothing is printed.
+    fn print_header(
+        &mut self,
+        _w: &mut W,
+        inputs: &[Option<Records>],
+    ) -> Result<(),Box<dyn Error>> {
+        let quote = self.quote;
+        for (names, r) in self.names.iter_mut().zip(inputs) {
+            if let Some(ref r) = *r {
+                let rec = record(r.fields, r.records, r.print.start);
+                *names = rec.iter()
+                            .map(|f| {
+                                let name = &r.buf[f.clone()];
+                                let name = match quote {
+                                    Some(q) => unescape(name, q),
+                                    None => name.into(),
+                                };
+                                Some(String::from_utf8_lossy(&name).into_owned())
+                            })
+                            .collect();
+            }
+        }
+        // the names of the printed fields must be unique, the key fields are named after the
+        // first input
+        let mut seen: HashMap<String, usize> = HashMap::new();
+        for (i, names) in self.names.iter().enumerate() {
+            for (j, name) in names.iter().enumerate() {
+                if let Some(ref name) = *name {
+                    if i == 0 || !self.is_key(i, j) {
+                        *seen.entry(name.clone()).or_insert(0) += 1;
                     }
-                    start = *k + 1;
                 }
-                // write remaining non-key fields
-                for f in &r0f[start..] {
-                    w.write_all(&[self.delimiter])?;
-                    w.write_all(&buf0[f.clone()])?;
+            }
+        }
+        for names in &mut self.names {
+            for name in names.iter_mut() {
+                if name.as_ref().is_some_and(|n| seen.get(n).cloned().unwrap_or(0) > 1) {
+                    *name = None;
                 }
+            }
+        }
+        Ok(())
+    }
 
-                start = 0;
-                // write non-key fields that lie in between key fields
-                for k in &self.key_idx1_asc {
-                    for f in &r1f[start..*k] {
-                        w.write_all(&[self.delimiter])?;
-                        w.write_all(&buf1[f.clone()])?;
-                    }
-                    start = *k + 1;
-                }
-                // write remaining non-key fields
-                for f in &r1f[start..] {
-                    w.write_all(&[self.delimiter])?;
-                    w.write_all(&buf1[f.clone()])?;
+    #[inline]
+    fn set_field_counts(&mut self, counts: &[Option<usize>]) {
+        for (c, &n) in self.counts.iter_mut().zip(counts) {
+            *c = c.or(n);
+        }
+    }
+}
+
+/// Write the `value` as a JSON string, the invalid UTF-8 is replaced.
+fn write_json_string<W:io::Write>(w: &mut W, value: &[u8]) -> io::Result<()> {
+    let value = String::from_utf8_lossy(value);
+    let bytes = value.as_bytes();
+    w.write_all(b"\"")?;
+    let mut start = 0;
+    for (i, &b) in bytes.iter().enumerate() {
+        let esc: Option<&[u8]> = match b {
+            b'"' => Some(b"\\\""),
+            b'\\' => Some(b"\\\\"),
+            b'\n' => Some(b"\\n"),
+            b'\r' => Some(b"\\r"),
+            b'\t' => Some(b"\\t"),
+            // the other control characters
+            0x00..=0x1f => None,
+            _ => continue,
+        };
+        w.write_all(&bytes[start..i])?;
+        match esc {
+            Some(esc) => w.write_all(esc)?,
+            None => write!(w, "\\u{:04x}", b)?,
+        }
+        start = i + 1;
+    }
+    w.write_all(&bytes[start..])?;
+    w.write_all(b"\"")
+}
+
+/// Call `f` for each combination of the records of the present inputs, the first input varies
+/// the slowest.
+#[inline]
+fn for_each_row<F>(inputs: &[Option<Records>], mut f: F) -> Result<(), Box<dyn Error>>
+    where F: FnMut(&[Option<Record>]) -> Result<(), Box<dyn Error>>,
+{
+    if inputs.iter().flatten().any(|r| r.print.start >= r.print.end) {
+        return Ok(());
+    }
+    let mut n: Vec<usize> = inputs.iter().map(|r| r.as_ref().map_or(0, |r| r.print.start)).collect();
+    let mut row: Vec<Option<Record>> = vec![None; inputs.len()];
+    loop {
+        for (i, r) in inputs.iter().enumerate() {
+            row[i] = r.as_ref().map(|r| (r.buf, record(r.fields, r.records, n[i])));
+        }
+        f(&row)?;
+
+        // advance the rightmost input which has more records
+        let mut i = inputs.len();
+        loop {
+            if i == 0 {
+                return Ok(());
+            }
+            i -= 1;
+            if let Some(ref r) = inputs[i] {
+                n[i] += 1;
+                if n[i] < r.print.end {
+                    break;
                 }
-                w.write_all(&[self.terminator])?;
-                is_first = true;
-                r1.start = r1.end;
+                n[i] = r.print.start;
             }
-            r0.start = r0.end;
-            r1.start = start1;
         }
-        Ok(())
     }
 }
+
+/// Return the fields of the record number `n`.
+#[inline]
+fn record<'a>(fields: &'a [Range<usize>], records: &[usize], n: usize) -> &'a [Range<usize>] {
+    let start = match n.checked_sub(1) {
+        Some(i) => records[i],
+        None => 0,
+    };
+    &fields[start..records[n]]
+}
+
+#[inline]
+fn write_field<W:io::Write>(
+    w: &mut W,
+    is_first: &mut bool,
+    writer: &Writer,
+    field: &[u8],
+) -> Result<(), Box<dyn Error>> {
+    if !*is_first {
+        w.write_all(&[writer.delimiter()])?;
+    } else {
+        *is_first = false;
+    }
+    writer.write_field(w, field)?;
+    Ok(())
+}
         
 #[inline]
+#[allow(clippy::too_many_arguments)]
 fn print_single<W:io::Write>(
     w: &mut W,
     buf: &[u8],
     fields: &[Range<usize>],
     records: &[usize],
     print: Range<usize>,
-    delimiter: u8,
-    terminator: u8,
+    writer: &Writer,
     key_idx: &[usize],
     key_idx_asc: &[usize],
-) -> Result<(), Box<Error>> {
+    empty: &[u8],
+    pad_before: usize,
+    pad_after: usize,
+    indicator: Option<(&[u8], bool)>,
+) -> Result<(), Box<dyn Error>> {
     let mut is_first = true;
     let mut start = match print.start.checked_sub(1).and_then(|i| records.get(i)) {
         Some(&start) => start,
@@ -239,30 +1276,38 @@ fn print_single<W:io::Write>(
     for re in &records[print] {
         r.end = *re;
         let rf = &fields[r.clone()];
-        // write key fields first
+        // write the label of the indicator column, if it goes first
+        if let Some((label, true)) = indicator {
+            write_field(w, &mut is_first, writer, label)?;
+        }
+        // write key fields first, the right input of an interval join has none
         for k in key_idx {
-            if !is_first {
-                w.write_all(&[delimiter])?;
-            } else {
-                is_first = false;
-            }
-            w.write_all(&buf[rf[*k].clone()])?;
+            write_field(w, &mut is_first, writer, &buf[rf[*k].clone()])?;
+        }
+        // write the padding in place of the non-key fields of the left input
+        for _ in 0..pad_before {
+            write_field(w, &mut is_first, writer, empty)?;
         }
         // write non-key fields that lie in between key fields
         start = 0;
         for k in key_idx_asc {
             for f in &rf[start..*k] {
-                w.write_all(&[delimiter])?;
-                w.write_all(&buf[f.clone()])?;
+                write_field(w, &mut is_first, writer, &buf[f.clone()])?;
             }
             start = *k + 1;
         }
         // write remaining non-key fields
         for f in &rf[start..] {
-            w.write_all(&[delimiter])?;
-            w.write_all(&buf[f.clone()])?;
+            write_field(w, &mut is_first, writer, &buf[f.clone()])?;
+        }
+        // write the padding in place of the non-key fields of the right input
+        for _ in 0..pad_after {
+            write_field(w, &mut is_first, writer, empty)?;
         }
-        w.write_all(&[terminator])?;
+        if let Some((label, false)) = indicator {
+            write_field(w, &mut is_first, writer, label)?;
+        }
+        w.write_all(&[writer.terminator()])?;
         is_first = true;
         r.start = r.end;
     }
@@ -272,6 +1317,7 @@ fn print_single<W:io::Write>(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use csvroll::writer::QuoteStyle;
 
     #[test]
     fn test_print() {
@@ -368,8 +1414,282 @@ mod tests {
             );
         }
     }
-}
 
-                
+    #[test]
+    fn test_parse_columns() {
+        struct TestCase {
+            spec: String,
+            want: Option<Vec<Column>>,
+        }
+
+        let test_cases = vec![
+            TestCase {
+                spec: "0,1.3,2.2".to_owned(),
+                want: Some(vec![
+                    Column::Key,
+                    Column::Fields { input: 0, start: 2, end: Some(3) },
+                    Column::Fields { input: 1, start: 1, end: Some(2) },
+                ]),
+            },
+            TestCase {
+                spec: "2.2-4,1.2-,2.*".to_owned(),
+                want: Some(vec![
+                    Column::Fields { input: 1, start: 1, end: Some(4) },
+                    Column::Fields { input: 0, start: 1, end: None },
+                    Column::Rest(1),
+                ]),
+            },
+            TestCase { spec: "".to_owned(), want: None },
+            TestCase { spec: "1".to_owned(), want: None },
+            TestCase { spec: "3.1".to_owned(), want: None },
+            TestCase { spec: "1.0".to_owned(), want: None },
+            TestCase { spec: "1.3-2".to_owned(), want: None },
+            TestCase { spec: "1.a".to_owned(), want: None },
+        ];
+
+        for t in test_cases {
+            let TestCase { spec, want } = t;
+            assert_eq!(parse_columns(&spec, 2).ok(), want, "{}", spec);
+        }
+    }
+
+    #[test]
+    fn test_print_columns() {
+        struct TestCase {
+            spec: String,
+            key_idx: Vec<usize>,
+            want: (String, String, String),
+        }
+
+        let buf = "a,0,b,0\na,1,b,1".to_owned();
+        let fields = vec![0..1, 2..3, 4..5, 6..7, 8..9, 10..11, 12..13, 14..15];
+        let records = vec![4, 8];
 
+        let test_cases = vec![
+            TestCase {
+                spec: "0,1.3,2.2".to_owned(),
+                key_idx: vec![0],
+                want: (
+                    "a,b,\n".to_owned(),
+                    "a,,1\n".to_owned(),
+                    "a,b,1\n".to_owned(),
+                ),
+            },
+            TestCase {
+                spec: "2.3-4,0,1.*".to_owned(),
+                key_idx: vec![0],
+                want: (
+                    ",,a,0,b,0\n".to_owned(),
+                    "b,1,a\n".to_owned(),
+                    "b,1,a,0,b,0\n".to_owned(),
+                ),
+            },
+            TestCase {
+                spec: "1.*,1.2,2.3-,1.5".to_owned(),
+                key_idx: vec![2, 0],
+                want: (
+                    "0,0,\n".to_owned(),
+                    ",b,1,\n".to_owned(),
+                    "0,0,b,1,\n".to_owned(),
+                ),
+            },
+        ];
+
+        for t in test_cases {
+            let TestCase { spec, key_idx, want } = t;
+            let columns = parse_columns(&spec, 2).unwrap();
+            let mut p = Columns::from_parts(b',', b'\n', vec![key_idx.clone(), key_idx], columns, None);
+            let mut left: Vec<u8> = Vec::new();
+            let mut right: Vec<u8> = Vec::new();
+            let mut both: Vec<u8> = Vec::new();
+            p.print_left(&mut left, buf.as_bytes(), &fields, &records, 0..1).unwrap();
+            p.print_right(&mut right, buf.as_bytes(), &fields, &records, 1..2).unwrap();
+            p.print_both(
+                &mut both,
+                buf.as_bytes(),
+                buf.as_bytes(),
+                &fields,
+                &fields,
+                &records,
+                &records,
+                0..1,
+                1..2,
+            ).unwrap();
+
+            assert_eq!(
+                (String::from_utf8(left).unwrap(),
+                 String::from_utf8(right).unwrap(),
+                 String::from_utf8(both).unwrap()),
+                want,
+                "{}", spec
+            );
+        }
+    }
+
+    #[test]
+    fn test_print_padding() {
+        let buf = "a,0,b\nc,1".as_bytes();
+        let fields = vec![0..1, 2..3, 4..5, 6..7, 8..9];
+        let records = vec![3, 5];
+
+        let mut p = KeyFirst::with_empty(b',', b'\n', vec![0], vec![0], b"-".to_vec());
+        Print::<Vec<u8>>::set_field_counts(&mut p, &[Some(3), None]);
+        Print::<Vec<u8>>::set_field_counts(&mut p, &[Some(5), Some(2)]);
+        let mut out: Vec<u8> = Vec::new();
+        p.print_left(&mut out, buf, &fields, &records, 0..1).unwrap();
+        p.print_right(&mut out, buf, &fields, &records, 1..2).unwrap();
+        assert_eq!(out, b"a,0,b,-\nc,-,-,1\n");
+
+        let columns = parse_columns("0,2.*,1.2-", 2).unwrap();
+        let mut p = Columns::from_parts(b',', b'\n', vec![vec![0], vec![0]], columns, Some(b"-".to_vec()));
+        Print::<Vec<u8>>::set_field_counts(&mut p, &[Some(3), Some(2)]);
+        let mut out: Vec<u8> = Vec::new();
+        p.print_left(&mut out, buf, &fields, &records, 0..1).unwrap();
+        p.print_right(&mut out, buf, &fields, &records, 1..2).unwrap();
+        assert_eq!(out, b"a,-,0,b\nc,1,-,-\n");
+
+        // the right input without the key fields, like in an interval join, is padded in their place
+        let mut p = KeyFirst::with_empty(b',', b'\n', vec![0], Vec::new(), b"-".to_vec());
+        Print::<Vec<u8>>::set_field_counts(&mut p, &[Some(3), Some(2)]);
+        let mut out: Vec<u8> = Vec::new();
+        p.print_right(&mut out, buf, &fields, &records, 1..2).unwrap();
+        assert_eq!(out, b"-,-,-,c,1\n");
+
+        let mut p = KeyFirst::from_parts(b',', b'\n', vec![0], Vec::new());
+        let mut out: Vec<u8> = Vec::new();
+        p.print_right(&mut out, buf, &fields, &records, 1..2).unwrap();
+        assert_eq!(out, b"c,1\n");
+
+        let columns = parse_columns("0,2.*", 2).unwrap();
+        let mut p = Columns::from_parts(b',', b'\n', vec![vec![0], Vec::new()], columns, Some(b"-".to_vec()));
+        let mut out: Vec<u8> = Vec::new();
+        p.print_right(&mut out, buf, &fields, &records, 1..2).unwrap();
+        assert_eq!(out, b"-,c,1\n");
+
+        let mut p = Jsonl::from_keys(vec![vec![0], Vec::new()]);
+        Print::<Vec<u8>>::set_field_counts(&mut p, &[Some(3), Some(2)]);
+        let mut out: Vec<u8> = Vec::new();
+        p.print_right(&mut out, buf, &fields, &records, 1..2).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "{\"left_1\":null,\"left_2\":null,\"left_3\":null,\"right_1\":\"c\",\"right_2\":\"1\"}\n",
+        );
+    }
+
+    #[test]
+    fn test_print_indicator() {
+        let buf = "a,0,b\nc,1".as_bytes();
+        let fields = vec![0..1, 2..3, 4..5, 6..7, 8..9];
+        let records = vec![3, 5];
+        let indicator = Indicator::new(b"_merge".to_vec());
+
+        let mut p = KeyFirst::default().with_indicator(indicator.clone());
+        let mut out: Vec<u8> = Vec::new();
+        p.print_left(&mut out, buf, &fields, &records, 0..1).unwrap();
+        p.print_right(&mut out, buf, &fields, &records, 1..2).unwrap();
+        p.print_both(&mut out, buf, buf, &fields, &fields, &records, &records, 0..1, 1..2).unwrap();
+        assert_eq!(out, b"a,0,b,left_only\nc,1,right_only\na,0,b,1,both\n");
+
+        let indicator = indicator.with_first(true).with_labels(b"l".to_vec(), b"r".to_vec(), b"x,y".to_vec());
+        let mut p = KeyFirst::default().with_indicator(indicator.clone());
+        let mut out: Vec<u8> = Vec::new();
+        let header = [
+            Some(Records { buf, fields: &fields, records: &records, print: 0..1 }),
+            Some(Records { buf, fields: &fields, records: &records, print: 1..2 }),
+        ];
+        p.print_header(&mut out, &header).unwrap();
+        p.print_left(&mut out, buf, &fields, &records, 0..1).unwrap();
+        p.print_both(&mut out, buf, buf, &fields, &fields, &records, &records, 0..1, 1..2).unwrap();
+        assert_eq!(out, b"_merge,a,0,b,1\nl,a,0,b\n\"x,y\",a,0,b,1\n");
+
+        // the unmatched records are padded to the width of the header without the empty value
+        let mut p = KeyFirst::default().with_indicator(Indicator::new(b"_merge".to_vec()));
+        Print::<Vec<u8>>::set_field_counts(&mut p, &[Some(3), Some(2)]);
+        let mut out: Vec<u8> = Vec::new();
+        p.print_header(&mut out, &header).unwrap();
+        p.print_left(&mut out, buf, &fields, &records, 0..1).unwrap();
+        p.print_right(&mut out, buf, &fields, &records, 1..2).unwrap();
+        assert_eq!(out, b"a,0,b,1,_merge\na,0,b,,left_only\nc,,,1,right_only\n");
 
+        let columns = parse_columns("1.2,0", 2).unwrap();
+        let mut p = Columns::from_parts(b',', b'\n', vec![vec![0], vec![0]], columns, None)
+            .with_indicator(Indicator::new(b"_merge".to_vec()));
+        let mut out: Vec<u8> = Vec::new();
+        p.print_right(&mut out, buf, &fields, &records, 1..2).unwrap();
+        assert_eq!(out, b",c,right_only\n");
+
+        let mut p = Jsonl::from_keys(vec![vec![0], vec![0]]).with_indicator(Indicator::new(b"_merge".to_vec()));
+        let mut out: Vec<u8> = Vec::new();
+        p.print_right(&mut out, buf, &fields, &records, 1..2).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "{\"left_1\":\"c\",\"right_2\":\"1\",\"_merge\":\"right_only\"}\n",
+        );
+    }
+
+    #[test]
+    fn test_print_quoted() {
+        // the fields as read with the quote '"', the enclosing quotes are stripped
+        let buf = "a,b\tc\"\"d\na b".as_bytes();
+        let fields = vec![0..3, 4..8, 9..12];
+        let records = vec![2, 3];
+
+        let mut p = KeyFirst::from_keys(b'\t', b'\n', vec![vec![0], vec![0]], None);
+        let mut out: Vec<u8> = Vec::new();
+        p.print_both(&mut out, buf, buf, &fields, &fields, &records, &records, 0..1, 1..2).unwrap();
+        assert_eq!(out, b"a,b\t\"c\"\"\"\"d\"\n");
+
+        let writer = Writer::from_parts(b',', b'\n', b'"', QuoteStyle::Necessary, None, Some(b'"'));
+        let mut p = KeyFirst::with_writer(writer, vec![vec![0], vec![0]], None);
+        let mut out: Vec<u8> = Vec::new();
+        p.print_both(&mut out, buf, buf, &fields, &fields, &records, &records, 0..1, 1..2).unwrap();
+        assert_eq!(out, b"\"a,b\",\"c\"\"d\"\n");
+
+        let writer = Writer::from_parts(b',', b'\n', b'\'', QuoteStyle::Always, None, Some(b'"'));
+        let columns = parse_columns("2.1,0", 2).unwrap();
+        let mut p = Columns::with_writer(writer, vec![vec![0], vec![0]], columns, None);
+        let mut out: Vec<u8> = Vec::new();
+        p.print_left(&mut out, buf, &fields, &records, 0..1).unwrap();
+        assert_eq!(out, b"'','a,b'\n");
+    }
+
+    #[test]
+    fn test_print_jsonl() {
+        let header = "id,name\nid,name,note".as_bytes();
+        let header_fields = vec![0..2, 3..7, 8..10, 11..15, 16..20];
+        let header_records = vec![2, 5];
+        let buf = "1,a\"b\n1,c\\d,x\ty".as_bytes();
+        let fields = vec![0..1, 2..5, 6..7, 8..11, 12..15];
+        let records = vec![2, 5];
+
+        // without the header
+        let mut p = Jsonl::from_keys(vec![vec![0], vec![0]]);
+        let mut out: Vec<u8> = Vec::new();
+        p.print_both(&mut out, buf, buf, &fields, &fields, &records, &records, 0..1, 1..2).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "{\"left_1\":\"1\",\"left_2\":\"a\\\"b\",\"right_2\":\"c\\\\d\",\"right_3\":\"x\\ty\"}\n",
+        );
+
+        // the duplicate name is replaced, the missing input is null
+        let mut p = Jsonl::from_keys(vec![vec![0], vec![0]]);
+        let mut out: Vec<u8> = Vec::new();
+        let inputs = [
+            Some(Records { buf: header, fields: &header_fields, records: &header_records, print: 0..1 }),
+            Some(Records { buf: header, fields: &header_fields, records: &header_records, print: 1..2 }),
+        ];
+        p.print_header(&mut out, &inputs).unwrap();
+        Print::<Vec<u8>>::set_field_counts(&mut p, &[Some(2), Some(3)]);
+        p.print_left(&mut out, buf, &fields, &records, 0..1).unwrap();
+        p.print_right(&mut out, buf, &fields, &records, 1..2).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "{\"id\":\"1\",\"left_2\":\"a\\\"b\",\"right_2\":null,\"note\":null}\n\
+             {\"id\":\"1\",\"left_2\":null,\"right_2\":\"c\\\\d\",\"note\":\"x\\ty\"}\n",
+        );
+
+        let mut out: Vec<u8> = Vec::new();
+        write_json_string(&mut out, b"\x01\xff").unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "\"\\u0001\u{fffd}\"");
+    }
+}
@@ -0,0 +1,585 @@
+use super::csv::basic::{cmp_records, push_record,};
+use super::csv::compare::Compare;
+use csvroll::parser::{Parser, Index,};
+use csvroll::error::{Error, ErrorKind,};
+use csvroll::index_builder::{IndexBuilder, Backend,};
+use rollbuf::{RollBuf, DEFAULT_MAX_CAPACITY,};
+use std::cmp::Ordering;
+use std::env;
+use std::fs::{self, File, OpenOptions,};
+use std::io::{self, Seek, SeekFrom, Write,};
+use std::ops::Range;
+use std::path::{Path, PathBuf,};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering,};
+
+/// The default memory limit of `Sorter`.
+pub const DEFAULT_MEM_LIMIT: usize = 1 << 28;
+// the buffer capacity of each run read during the merge
+const RUN_BUF_CAP: usize = 1 << 16;
+// the number of bytes `Merge` prepares in advance
+const OUT_CAP: usize = 1 << 16;
+
+static RUN_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// An external sorter of records by their key fields.
+///
+/// The records are collected in the memory until `mem_limit` is reached, then they are sorted
+/// and written into a temporary run file. Finally, the runs are merged by `Merge`, which produces
+/// the sorted records in the same format as the input. The sort is stable and uses the same
+/// comparison as `Group`, so the output is always accepted by the merge join.
+#[derive(Debug, Clone)]
+pub struct Sorter {
+    key_idx: Vec<usize>,
+    cmp: Vec<Compare>,
+    delimiter: u8,
+    terminator: u8,
+    quote: Option<u8>,
+    mem_limit: usize,
+    tmp_dir: PathBuf,
+    max_buffer: usize,
+}
+
+impl Sorter {
+    /// Create a new instance of `Sorter` with the default memory limit and temporary directory.
+    pub fn new(key_idx: Vec<usize>, delimiter: u8, terminator: u8, quote: Option<u8>) -> Self {
+        Self::from_parts(
+            key_idx,
+            Vec::new(),
+            delimiter,
+            terminator,
+            quote,
+            DEFAULT_MEM_LIMIT,
+            env::temp_dir(),
+        )
+    }
+
+    /// Create a new instance of `Sorter`. The key fields are compared by `cmp`, the key fields
+    /// without a comparison are compared as bytes. The `delimiter`, `terminator` and `quote` must
+    /// match the format of the input.
+    pub fn from_parts(
+        key_idx: Vec<usize>,
+        cmp: Vec<Compare>,
+        delimiter: u8,
+        terminator: u8,
+        quote: Option<u8>,
+        mem_limit: usize,
+        tmp_dir: PathBuf,
+    ) -> Self {
+        Sorter {
+            key_idx,
+            cmp,
+            delimiter,
+            terminator,
+            quote,
+            mem_limit,
+            tmp_dir,
+            max_buffer: DEFAULT_MAX_CAPACITY,
+        }
+    }
+
+    /// Set the maximum capacity of the buffers reading the run files, i.e. the maximum size of a
+    /// record.
+    pub fn with_max_buffer(mut self, max_buffer: usize) -> Self {
+        self.max_buffer = max_buffer;
+        self
+    }
+
+    /// Read all the records from `parser` and return the reader of the sorted records.
+    pub fn sort<R: io::Read>(&self, mut parser: Parser<R>) -> Result<Merge, Error> {
+        let mut chunk = Chunk::new();
+        let mut sources: Vec<Source> = Vec::new();
+
+        loop {
+            let is_buf_full = parser.parse()?;
+            let n = {
+                let (buf, idx) = parser.output();
+                let fields = idx.fields();
+                let mut start = 0;
+                for (i, &end) in idx.records().iter().enumerate() {
+                    let rec = &fields[start..end];
+                    if self.key_idx.iter().any(|&k| k >= rec.len()) {
+                        return Err(Error::at(ErrorKind::ShortRecord, parser.position(i)));
+                    }
+                    chunk.push(buf, rec);
+                    if chunk.mem_size() >= self.mem_limit {
+                        chunk.sort(&self.key_idx, &self.cmp);
+                        sources.push(self.spill(&chunk)?);
+                        chunk.clear();
+                    }
+                    start = end;
+                }
+                idx.records().len()
+            };
+            parser.consume(n);
+            if !is_buf_full {
+                break;
+            }
+        }
+
+        // the last chunk holds the last records, so it must be the last source to keep the sort
+        // stable
+        chunk.sort(&self.key_idx, &self.cmp);
+        sources.push(Source::Memory { chunk, pos: 0 });
+
+        Ok(Merge::new(sources, self))
+    }
+
+    /// Write the sorted `chunk` into a new temporary run file.
+    fn spill(&self, chunk: &Chunk) -> Result<Source, Error> {
+        let tmp = TempPath::new(&self.tmp_dir);
+        let file = OpenOptions::new().read(true).write(true).create_new(true).open(&tmp.0)?;
+        let mut w = io::BufWriter::with_capacity(OUT_CAP, file);
+        let mut rec_buf = Vec::new();
+        for n in 0..chunk.order.len() {
+            if let Some((buf, rec)) = chunk.get(n) {
+                rec_buf.clear();
+                write_record(&mut rec_buf, buf, rec, self.delimiter, self.terminator, self.quote);
+                w.write_all(&rec_buf)?;
+            }
+        }
+        let mut file = w.into_inner().map_err(|e| e.into_error())?;
+        file.seek(SeekFrom::Start(0))?;
+
+        let buf = RollBuf::with_max_capacity(RUN_BUF_CAP, self.max_buffer, file);
+        let idx_builder = IndexBuilder::from_parts(
+            self.delimiter,
+            self.terminator,
+            self.quote,
+            Backend::detect(),
+        );
+        let mut parser = Parser::from_parts(buf, idx_builder);
+        let is_buf_full = parser.parse()?;
+        let mut source = Source::File { parser, rec: 0, is_buf_full, _tmp: tmp };
+        source.fill()?;
+        Ok(source)
+    }
+}
+
+/// The records held in the memory.
+struct Chunk {
+    buf: Vec<u8>,
+    idx: Index,
+    // the record numbers in the sorted order
+    order: Vec<usize>,
+}
+
+impl Chunk {
+    fn new() -> Self {
+        Chunk {
+            buf: Vec::new(),
+            idx: Index::new(),
+            order: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, buf: &[u8], rec: &[Range<usize>]) {
+        self.order.push(self.order.len());
+        push_record(&mut self.buf, &mut self.idx, buf, rec);
+    }
+
+    fn clear(&mut self) {
+        self.buf.clear();
+        self.idx = Index::new();
+        self.order.clear();
+    }
+
+    // the approximate size of the chunk in bytes
+    fn mem_size(&self) -> usize {
+        self.buf.len()
+            + self.idx.fields().len() * 2 * ::std::mem::size_of::<usize>()
+            + self.order.len() * 2 * ::std::mem::size_of::<usize>()
+    }
+
+    fn sort(&mut self, key_idx: &[usize], cmp: &[Compare]) {
+        let buf = &self.buf;
+        let idx = &self.idx;
+        let fields = idx.fields();
+        // the records were checked to have all the key fields, so the comparison cannot fail
+        self.order.sort_by(|&a, &b| {
+            let ra = idx.get_record(a).unwrap_or(0..0);
+            let rb = idx.get_record(b).unwrap_or(0..0);
+            cmp_records(buf, buf, &fields[ra], &fields[rb], key_idx, key_idx, cmp)
+                .unwrap_or(Ordering::Equal)
+        });
+    }
+
+    // the `n`-th record in the sorted order
+    #[inline]
+    fn get(&self, n: usize) -> Option<(&[u8], &[Range<usize>])> {
+        let r = self.order.get(n).and_then(|&r| self.idx.get_record(r))?;
+        Some((&self.buf, &self.idx.fields()[r]))
+    }
+}
+
+/// The sorted sequence of records taking part in the merge.
+// there is only one source per run, so the size of the variants does not matter
+#[allow(clippy::large_enum_variant)]
+enum Source {
+    Memory {
+        chunk: Chunk,
+        pos: usize,
+    },
+    File {
+        parser: Parser<File>,
+        rec: usize,
+        is_buf_full: bool,
+        // dropped after the file is closed
+        _tmp: TempPath,
+    },
+}
+
+impl Source {
+    /// Return the current record or `None` if the source is exhausted.
+    #[inline]
+    fn current(&self) -> Option<(&[u8], &[Range<usize>])> {
+        match *self {
+            Source::Memory { ref chunk, pos } => chunk.get(pos),
+            Source::File { ref parser, rec, .. } => {
+                let (buf, idx) = parser.output();
+                let r = idx.get_record(rec)?;
+                Some((buf, &idx.fields()[r]))
+            }
+        }
+    }
+
+    #[inline]
+    fn advance(&mut self) -> Result<(), Error> {
+        match *self {
+            Source::Memory { ref mut pos, .. } => {
+                *pos += 1;
+                Ok(())
+            }
+            Source::File { ref mut rec, .. } => {
+                *rec += 1;
+                self.fill()
+            }
+        }
+    }
+
+    // parse the next records of the file if the current ones were all consumed
+    fn fill(&mut self) -> Result<(), Error> {
+        if let Source::File { ref mut parser, ref mut rec, ref mut is_buf_full, .. } = *self {
+            while *rec >= parser.output().1.records().len() && *is_buf_full {
+                parser.consume(*rec);
+                *rec = 0;
+                *is_buf_full = parser.parse()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The reader of the records merged from the sorted runs.
+///
+/// The records are written back in the format of the input by `write_record`, so they are
+/// parsed again by the merge join. The parsed fields keep their escaped contents, e.g. the
+/// doubled quotes, and each of them is quoted when the input is, so the same fields are parsed
+/// from the output, including the quoted delimiters and terminators. It costs another pass over
+/// the records, but the join takes the sorted input like any other one.
+pub struct Merge {
+    sources: Vec<Source>,
+    // the binary min-heap of the non-exhausted sources
+    heap: Vec<usize>,
+    key_idx: Vec<usize>,
+    cmp: Vec<Compare>,
+    delimiter: u8,
+    terminator: u8,
+    quote: Option<u8>,
+    out: Vec<u8>,
+    pos: usize,
+}
+
+impl Merge {
+    fn new(sources: Vec<Source>, sorter: &Sorter) -> Self {
+        let heap: Vec<usize> = (0..sources.len())
+            .filter(|&i| sources[i].current().is_some())
+            .collect();
+        let mut m = Merge {
+            sources,
+            heap,
+            key_idx: sorter.key_idx.clone(),
+            cmp: sorter.cmp.clone(),
+            delimiter: sorter.delimiter,
+            terminator: sorter.terminator,
+            quote: sorter.quote,
+            out: Vec::with_capacity(OUT_CAP),
+            pos: 0,
+        };
+        for i in (0..m.heap.len() / 2).rev() {
+            m.sift_down(i);
+        }
+        m
+    }
+
+    /// Return the number of the temporary run files.
+    pub fn runs(&self) -> usize {
+        self.sources.iter().filter(|s| match **s {
+            Source::File { .. } => true,
+            Source::Memory { .. } => false,
+        }).count()
+    }
+
+    // compare the current records of the sources `a` and `b`, the earlier source wins the tie
+    #[inline]
+    fn is_less(&self, a: usize, b: usize) -> bool {
+        let ord = match (self.sources[a].current(), self.sources[b].current()) {
+            (Some((buf_a, rec_a)), Some((buf_b, rec_b))) => {
+                cmp_records(buf_a, buf_b, rec_a, rec_b, &self.key_idx, &self.key_idx, &self.cmp)
+                    .unwrap_or(Ordering::Equal)
+            }
+            _ => Ordering::Equal,
+        };
+        match ord {
+            Ordering::Less => true,
+            Ordering::Greater => false,
+            Ordering::Equal => a < b,
+        }
+    }
+
+    fn sift_down(&mut self, mut i: usize) {
+        let n = self.heap.len();
+        loop {
+            let l = 2 * i + 1;
+            if l >= n {
+                return;
+            }
+            let mut m = l;
+            if l + 1 < n && self.is_less(self.heap[l + 1], self.heap[l]) {
+                m = l + 1;
+            }
+            if self.is_less(self.heap[m], self.heap[i]) {
+                self.heap.swap(i, m);
+                i = m;
+            } else {
+                return;
+            }
+        }
+    }
+
+    // write the next records into `out`, return `false` if all the sources are exhausted
+    fn fill_out(&mut self) -> Result<bool, Error> {
+        self.out.clear();
+        self.pos = 0;
+        while self.out.len() < OUT_CAP {
+            let top = match self.heap.first() {
+                Some(&top) => top,
+                None => break,
+            };
+            if let Some((buf, rec)) = self.sources[top].current() {
+                write_record(&mut self.out, buf, rec, self.delimiter, self.terminator, self.quote);
+            }
+            self.sources[top].advance()?;
+            if self.sources[top].current().is_none() {
+                let last = self.heap.len() - 1;
+                self.heap.swap(0, last);
+                self.heap.pop();
+            }
+            self.sift_down(0);
+        }
+        Ok(!self.out.is_empty())
+    }
+}
+
+impl io::Read for Merge {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pos >= self.out.len() {
+            let is_filled = self.fill_out().map_err(io::Error::other)?;
+            if !is_filled {
+                return Ok(0);
+            }
+        }
+        let n = ::std::cmp::min(buf.len(), self.out.len() - self.pos);
+        buf[..n].copy_from_slice(&self.out[self.pos..(self.pos + n)]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+/// Write the record in the input format. If `quote` is set, all the fields are quoted, since
+/// their contents were not unescaped, i.e. the quotes inside them are still doubled. A field
+/// with a lone quote is not valid in the input, so it is not written back the same way.
+#[inline]
+pub(crate) fn write_record(
+    out: &mut Vec<u8>,
+    buf: &[u8],
+    rec: &[Range<usize>],
+    delimiter: u8,
+    terminator: u8,
+    quote: Option<u8>,
+) {
+    for (i, f) in rec.iter().enumerate() {
+        if i > 0 {
+            out.push(delimiter);
+        }
+        match quote {
+            Some(q) => {
+                out.push(q);
+                out.extend_from_slice(&buf[f.clone()]);
+                out.push(q);
+            }
+            None => out.extend_from_slice(&buf[f.clone()]),
+        }
+    }
+    out.push(terminator);
+}
+
+/// The path of a temporary file, which is removed on drop.
+pub(crate) struct TempPath(pub(crate) PathBuf);
+
+impl TempPath {
+    pub(crate) fn new(dir: &Path) -> Self {
+        let n = RUN_COUNTER.fetch_add(1, AtomicOrdering::SeqCst);
+        TempPath(dir.join(format!("rjoin-{}-{}.tmp", process::id(), n)))
+    }
+}
+
+impl Drop for TempPath {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use csv::compare::Kind;
+    use csvroll::index_builder::unescape;
+    use std::io::Read;
+
+    fn sort(input: &str, key_idx: Vec<usize>, cmp: Vec<Compare>, mem_limit: usize) -> (String, usize) {
+        let buf = RollBuf::with_capacity(16, input.as_bytes());
+        let idx_builder = IndexBuilder::new(b',', b'\n');
+        let parser = Parser::from_parts(buf, idx_builder);
+        let sorter = Sorter::from_parts(key_idx, cmp, b',', b'\n', None, mem_limit, env::temp_dir());
+        let mut merge = sorter.sort(parser).unwrap();
+        let runs = merge.runs();
+        let mut out = String::new();
+        merge.read_to_string(&mut out).unwrap();
+        (out, runs)
+    }
+
+    #[test]
+    fn test_sort() {
+        struct TestCase {
+            note: String,
+            input: String,
+            key_idx: Vec<usize>,
+            mem_limit: usize,
+            want: (String, usize),
+        }
+
+        let test_cases = vec![
+            TestCase {
+                note: "empty input".into(),
+                input: "".into(),
+                key_idx: vec![0],
+                mem_limit: DEFAULT_MEM_LIMIT,
+                want: ("".into(), 0),
+            },
+            TestCase {
+                note: "in memory".into(),
+                input: "c,0\na,1\nb,2\na,3".into(),
+                key_idx: vec![0],
+                mem_limit: DEFAULT_MEM_LIMIT,
+                want: ("a,1\na,3\nb,2\nc,0\n".into(), 0),
+            },
+            TestCase {
+                note: "run per record".into(),
+                input: "c,0\na,1\nb,2\na,3".into(),
+                key_idx: vec![0],
+                mem_limit: 1,
+                want: ("a,1\na,3\nb,2\nc,0\n".into(), 4),
+            },
+            TestCase {
+                note: "multiple runs with multiple key fields".into(),
+                input: "b,1,x\na,2,y\nb,0,z\na,2,w\nc,0,v\na,1,u\nb,1,t".into(),
+                key_idx: vec![0, 1],
+                mem_limit: 100,
+                want: ("a,1,u\na,2,y\na,2,w\nb,0,z\nb,1,x\nb,1,t\nc,0,v\n".into(), 3),
+            },
+            TestCase {
+                note: "key in the second field".into(),
+                input: "x,b\ny,a\nz,c\n".into(),
+                key_idx: vec![1],
+                mem_limit: 60,
+                want: ("y,a\nx,b\nz,c\n".into(), 1),
+            },
+        ];
+
+        for t in test_cases {
+            let TestCase { note, input, key_idx, mem_limit, want } = t;
+            println!("{}", note);
+            assert_eq!(sort(&input, key_idx, Vec::new(), mem_limit), want);
+        }
+    }
+
+    #[test]
+    fn test_sort_numeric() {
+        let input = "10,a\n9,b\n-1.5,c\n09,d\n1e1,e";
+        let want = "-1.5,c\n1e1,e\n9,b\n09,d\n10,a\n".to_owned();
+        assert_eq!(sort(input, vec![0], vec![Compare::new(Kind::Numeric)], 1), (want, 5));
+        let want = "-1.5,c\n9,b\n09,d\n10,a\n1e1,e\n".to_owned();
+        assert_eq!(sort(input, vec![0], vec![Compare::new(Kind::General)], 20), (want, 5));
+    }
+
+    #[test]
+    fn test_sort_quoted() {
+        let input = "\"b,1\",x\n\"a\"\"\",y\n";
+        let buf = RollBuf::with_capacity(16, input.as_bytes());
+        let idx_builder = IndexBuilder::with_quote(b',', b'\n', b'"');
+        let parser = Parser::from_parts(buf, idx_builder);
+        let sorter = Sorter::from_parts(vec![0], Vec::new(), b',', b'\n', Some(b'"'), 1, env::temp_dir());
+        let mut out = String::new();
+        sorter.sort(parser).unwrap().read_to_string(&mut out).unwrap();
+        assert_eq!(out, "\"a\"\"\",\"y\"\n\"b,1\",\"x\"\n");
+    }
+
+    #[test]
+    fn test_sort_round_trip() {
+        // the fields of each record, unescaped
+        fn parse(input: &[u8]) -> Vec<Vec<Vec<u8>>> {
+            let buf = RollBuf::with_capacity(16, input);
+            let mut parser = Parser::from_parts(buf, IndexBuilder::with_quote(b',', b'\n', b'"'));
+            let mut out = Vec::new();
+            loop {
+                let is_buf_full = parser.parse().unwrap();
+                let n = {
+                    let (buf, idx) = parser.output();
+                    for n in 0..idx.records().len() {
+                        let rec = &idx.fields()[idx.get_record(n).unwrap()];
+                        out.push(rec.iter().map(|f| unescape(&buf[f.clone()], b'"').into_owned()).collect());
+                    }
+                    idx.records().len()
+                };
+                parser.consume(n);
+                if !is_buf_full {
+                    return out;
+                }
+            }
+        }
+
+        let input = "d,\"he said \"\"hi\"\"\"\nb,\"p,q\"\n\"c\nc\",\"l1\nl2\"\na,x\n\"e\",\"\"\"\"\n";
+        let mut want = parse(input.as_bytes());
+        want.sort_by(|a, b| a[0].cmp(&b[0]));
+        assert_eq!(want.len(), 5);
+
+        for &mem_limit in &[1, DEFAULT_MEM_LIMIT] {
+            let buf = RollBuf::with_capacity(16, input.as_bytes());
+            let parser = Parser::from_parts(buf, IndexBuilder::with_quote(b',', b'\n', b'"'));
+            let sorter = Sorter::from_parts(vec![0], Vec::new(), b',', b'\n', Some(b'"'), mem_limit, env::temp_dir());
+            let mut out = Vec::new();
+            sorter.sort(parser).unwrap().read_to_end(&mut out).unwrap();
+            assert_eq!(parse(&out), want, "memory limit {}", mem_limit);
+        }
+    }
+
+    #[test]
+    fn test_sort_short_record() {
+        let buf = RollBuf::with_capacity(16, "a,0\nb".as_bytes());
+        let idx_builder = IndexBuilder::new(b',', b'\n');
+        let parser = Parser::from_parts(buf, idx_builder);
+        let sorter = Sorter::new(vec![1], b',', b'\n', None);
+        assert!(sorter.sort(parser).is_err());
+    }
+}
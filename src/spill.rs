@@ -0,0 +1,211 @@
+use super::sort::{TempPath, write_record,};
+use csvroll::parser::{Parser, Index,};
+use csvroll::error::Error as CsvError;
+use csvroll::index_builder::{IndexBuilder, Backend,};
+use rollbuf::{RollBuf, DEFAULT_MAX_CAPACITY,};
+use std::env;
+use std::error::Error;
+use std::fs::{File, OpenOptions,};
+use std::io::{self, Seek, SeekFrom, Write,};
+use std::ops::Range;
+use std::path::PathBuf;
+
+/// The default size of a group of records with the same key, above which the group is spilled.
+pub const DEFAULT_SPILL_THRESHOLD: usize = 1 << 22;
+// the buffer capacity of the spill file when it is written or replayed
+const SPILL_BUF_CAP: usize = 1 << 16;
+
+/// The settings of spilling the large groups of records with the same key to the disk.
+///
+/// Once the records of a group take `threshold` bytes of the buffer, they are written into a
+/// temporary file in `tmp_dir` in the same format as the input, so the memory stays bounded no
+/// matter how many records share the key.
+#[derive(Debug, Clone)]
+pub struct Spiller {
+    threshold: usize,
+    delimiter: u8,
+    terminator: u8,
+    quote: Option<u8>,
+    tmp_dir: PathBuf,
+    max_buffer: usize,
+}
+
+impl Spiller {
+    /// Create a new instance of `Spiller` with the default threshold and temporary directory.
+    pub fn new(delimiter: u8, terminator: u8, quote: Option<u8>) -> Self {
+        Self::from_parts(DEFAULT_SPILL_THRESHOLD, delimiter, terminator, quote, env::temp_dir())
+    }
+
+    /// Create a new instance of `Spiller`. The `delimiter`, `terminator` and `quote` must match
+    /// the format of the input.
+    pub fn from_parts(
+        threshold: usize,
+        delimiter: u8,
+        terminator: u8,
+        quote: Option<u8>,
+        tmp_dir: PathBuf,
+    ) -> Self {
+        Spiller {
+            threshold,
+            delimiter,
+            terminator,
+            quote,
+            tmp_dir,
+            max_buffer: DEFAULT_MAX_CAPACITY,
+        }
+    }
+
+    /// Set the maximum capacity of the buffer replaying the spill file, i.e. the maximum size of
+    /// a record.
+    pub fn with_max_buffer(mut self, max_buffer: usize) -> Self {
+        self.max_buffer = max_buffer;
+        self
+    }
+
+    #[inline]
+    pub fn threshold(&self) -> usize {
+        self.threshold
+    }
+
+    /// Create a new empty spill file.
+    pub fn create(&self) -> Result<SpillFile, CsvError> {
+        let tmp = TempPath::new(&self.tmp_dir);
+        let file = OpenOptions::new().read(true).write(true).create_new(true).open(&tmp.0)?;
+        Ok(SpillFile {
+            w: io::BufWriter::with_capacity(SPILL_BUF_CAP, file),
+            spiller: self.clone(),
+            rec_buf: Vec::new(),
+            len: 0,
+            _tmp: tmp,
+        })
+    }
+}
+
+/// The temporary file of the records of a group, which did not fit into the memory.
+pub struct SpillFile {
+    w: io::BufWriter<File>,
+    spiller: Spiller,
+    rec_buf: Vec<u8>,
+    len: u64,
+    // dropped after the file is closed
+    _tmp: TempPath,
+}
+
+impl SpillFile {
+    /// Append the record `rec` of `buf` to the file.
+    #[inline]
+    pub fn push(&mut self, buf: &[u8], rec: &[Range<usize>]) -> Result<(), CsvError> {
+        self.rec_buf.clear();
+        let s = &self.spiller;
+        write_record(&mut self.rec_buf, buf, rec, s.delimiter, s.terminator, s.quote);
+        self.w.write_all(&self.rec_buf)?;
+        self.len += 1;
+        Ok(())
+    }
+
+    /// Return the number of the records in the file.
+    #[inline]
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Write the buffered records into the file, so that it can be replayed.
+    pub fn finish(&mut self) -> Result<(), CsvError> {
+        self.w.flush()?;
+        Ok(())
+    }
+
+    /// Read the records of the file from the beginning and pass them to `f` chunk by chunk. All
+    /// the records of the index belong to the group. The file must be finished first.
+    pub fn replay<F>(&self, mut f: F) -> Result<(), Box<dyn Error>>
+        where F: FnMut(&[u8], &Index) -> Result<(), Box<dyn Error>>,
+    {
+        let mut file = self.w.get_ref().try_clone()?;
+        file.seek(SeekFrom::Start(0))?;
+        let s = &self.spiller;
+        let buf = RollBuf::with_max_capacity(SPILL_BUF_CAP, s.max_buffer, file);
+        let idx_builder = IndexBuilder::from_parts(s.delimiter, s.terminator, s.quote, Backend::detect());
+        let mut parser = Parser::from_parts(buf, idx_builder);
+        loop {
+            let is_buf_full = parser.parse()?;
+            let n = {
+                let (buf, idx) = parser.output();
+                if !idx.records().is_empty() {
+                    f(buf, idx)?;
+                }
+                idx.records().len()
+            };
+            parser.consume(n);
+            if !is_buf_full {
+                return Ok(());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spill_replay() {
+        struct TestCase {
+            quote: Option<u8>,
+            records: Vec<Vec<String>>,
+        }
+
+        let test_cases = vec![
+            TestCase {
+                quote: None,
+                records: vec![],
+            },
+            TestCase {
+                quote: None,
+                records: (0..1000).map(|i| vec!["a".to_owned(), i.to_string()]).collect(),
+            },
+            TestCase {
+                quote: Some(b'"'),
+                records: vec![
+                    vec!["a".to_owned(), "x,\"\"y\"\"".to_owned()],
+                    vec!["a".to_owned(), "".to_owned()],
+                ],
+            },
+        ];
+
+        for t in test_cases {
+            let spiller = Spiller::new(b',', b'\n', t.quote);
+            let mut file = spiller.create().unwrap();
+            for r in &t.records {
+                let mut buf = Vec::new();
+                let mut rec = Vec::new();
+                for f in r {
+                    rec.push(buf.len()..(buf.len() + f.len()));
+                    buf.extend_from_slice(f.as_bytes());
+                }
+                file.push(&buf, &rec).unwrap();
+            }
+            file.finish().unwrap();
+            assert_eq!(file.len(), t.records.len() as u64);
+
+            // the file can be replayed more than once
+            for _ in 0..2 {
+                let mut got: Vec<Vec<String>> = Vec::new();
+                file.replay(|buf, idx| {
+                    for i in 0..idx.records().len() {
+                        let r = idx.get_record(i).unwrap();
+                        got.push(idx.fields()[r].iter()
+                            .map(|f| String::from_utf8(buf[f.clone()].to_vec()).unwrap())
+                            .collect());
+                    }
+                    Ok(())
+                }).unwrap();
+                assert_eq!(got, t.records);
+            }
+        }
+    }
+}